@@ -0,0 +1,387 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::FrameFormat;
+use crate::headers::ContentType;
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const WAVE_FORMAT_ALAW: u16 = 6;
+const WAVE_FORMAT_MULAW: u16 = 7;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    /// Linear PCM of 8 (unsigned), 16, 24 or 32 bits, little-endian.
+    Pcm(u16),
+    /// Big-endian 16 bit linear PCM of `audio/L16`.
+    L16,
+    /// Native-endian 16 bit linear PCM, as written to `AudioSource`.
+    Native,
+    Float,
+    ALaw,
+    MuLaw,
+}
+
+/// Layout of audio samples: encoding, sampling rate and channel count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Layout {
+    encoding: Encoding,
+    sampling_rate: usize,
+    channel_count: usize,
+}
+
+/// Decodes a WAV file or raw audio to samples of the format. Raw audio is taken by its
+/// media type: `audio/L16` with its `rate` and `channels`, `audio/PCMU`, `audio/PCMA`,
+/// or linear PCM of the format without one. The audio is mixed down and resampled.
+pub fn decode_audio(
+    data: &[u8],
+    content_type: Option<&ContentType>,
+    format: FrameFormat,
+) -> crate::Result<Vec<i16>> {
+    let media_type = content_type.map_or("", |content_type| content_type.media_type.as_str());
+    let param = |name: &str, default: usize| {
+        content_type
+            .and_then(|content_type| content_type.param(name))
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default)
+    };
+    let (layout, payload) = match media_type {
+        _ if data.starts_with(b"RIFF") => parse_wav(data)?,
+        "audio/wav" | "audio/wave" | "audio/x-wav" | "audio/vnd.wave" => parse_wav(data)?,
+        "audio/l16" => (
+            Layout {
+                encoding: Encoding::L16,
+                sampling_rate: param("rate", 8000),
+                channel_count: param("channels", 1),
+            },
+            data,
+        ),
+        "audio/pcmu" | "audio/basic" => (
+            Layout {
+                encoding: Encoding::MuLaw,
+                sampling_rate: param("rate", 8000),
+                channel_count: param("channels", 1),
+            },
+            data,
+        ),
+        "audio/pcma" => (
+            Layout {
+                encoding: Encoding::ALaw,
+                sampling_rate: param("rate", 8000),
+                channel_count: param("channels", 1),
+            },
+            data,
+        ),
+        "" | "application/octet-stream" | "audio/x-raw" => (
+            Layout {
+                encoding: Encoding::Native,
+                sampling_rate: format.sampling_rate,
+                channel_count: 1,
+            },
+            data,
+        ),
+        other => return Err(crate::Error::InvalidAudio(format!("unsupported {other}"))),
+    };
+    if layout.sampling_rate == 0 || layout.channel_count == 0 {
+        return Err(crate::Error::InvalidAudio(
+            "no sampling rate or channels".to_owned(),
+        ));
+    }
+    let samples = decode_samples(payload, layout.encoding);
+    let mono = mix_down(&samples, layout.channel_count);
+    let resampled = resample(&mono, layout.sampling_rate, format.sampling_rate);
+    Ok(match format.channel_count {
+        0 | 1 => resampled,
+        channels => resampled
+            .into_iter()
+            .flat_map(|sample| std::iter::repeat_n(sample, channels))
+            .collect(),
+    })
+}
+
+/// Layout and data of a RIFF WAVE file.
+fn parse_wav(data: &[u8]) -> crate::Result<(Layout, &[u8])> {
+    if data.len() < 12 || &data[..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err(invalid("not a RIFF WAVE file"));
+    }
+    let mut layout = None;
+    let mut position = 12;
+    while position + 8 <= data.len() {
+        let id = &data[position..position + 4];
+        let size = u32_at(data, position + 4) as usize;
+        let start = position + 8;
+        // Streamed files may have the size of the data unset.
+        let end = start.saturating_add(size).min(data.len());
+        let chunk = &data[start..end];
+        match id {
+            b"fmt " => layout = Some(parse_fmt(chunk)?),
+            b"data" => {
+                let layout = layout.ok_or_else(|| invalid("data before fmt chunk"))?;
+                return Ok((layout, chunk));
+            }
+            _ => {}
+        }
+        position = end + (size & 1);
+    }
+    Err(invalid("no data chunk"))
+}
+
+fn parse_fmt(chunk: &[u8]) -> crate::Result<Layout> {
+    if chunk.len() < 16 {
+        return Err(invalid("short fmt chunk"));
+    }
+    let mut tag = u16_at(chunk, 0);
+    let channel_count = u16_at(chunk, 2) as usize;
+    let sampling_rate = u32_at(chunk, 4) as usize;
+    let bits = u16_at(chunk, 14);
+    if tag == WAVE_FORMAT_EXTENSIBLE {
+        // The sub-format GUID starts with the format tag.
+        if chunk.len() < 26 {
+            return Err(invalid("short extensible fmt chunk"));
+        }
+        tag = u16_at(chunk, 24);
+    }
+    let encoding = match (tag, bits) {
+        (WAVE_FORMAT_PCM, 8 | 16 | 24 | 32) => Encoding::Pcm(bits),
+        (WAVE_FORMAT_IEEE_FLOAT, 32) => Encoding::Float,
+        (WAVE_FORMAT_ALAW, 8) => Encoding::ALaw,
+        (WAVE_FORMAT_MULAW, 8) => Encoding::MuLaw,
+        (tag, bits) => return Err(invalid(&format!("unsupported format {tag} of {bits} bits"))),
+    };
+    Ok(Layout {
+        encoding,
+        sampling_rate,
+        channel_count,
+    })
+}
+
+fn decode_samples(data: &[u8], encoding: Encoding) -> Vec<i16> {
+    match encoding {
+        Encoding::Pcm(8) => data
+            .iter()
+            .map(|&byte| ((byte as i16) - 128) << 8)
+            .collect(),
+        Encoding::Pcm(24) => data
+            .chunks_exact(3)
+            .map(|bytes| i16::from_le_bytes([bytes[1], bytes[2]]))
+            .collect(),
+        Encoding::Pcm(32) => data
+            .chunks_exact(4)
+            .map(|bytes| i16::from_le_bytes([bytes[2], bytes[3]]))
+            .collect(),
+        Encoding::Pcm(_) => data
+            .chunks_exact(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+            .collect(),
+        Encoding::L16 => data
+            .chunks_exact(2)
+            .map(|bytes| i16::from_be_bytes([bytes[0], bytes[1]]))
+            .collect(),
+        Encoding::Native => super::samples(data).collect(),
+        Encoding::Float => data
+            .chunks_exact(4)
+            .map(|bytes| {
+                let sample = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+            })
+            .collect(),
+        Encoding::ALaw => data.iter().map(|&byte| alaw_to_linear(byte)).collect(),
+        Encoding::MuLaw => data.iter().map(|&byte| mulaw_to_linear(byte)).collect(),
+    }
+}
+
+fn mix_down(samples: &[i16], channel_count: usize) -> Vec<i16> {
+    if channel_count == 1 {
+        return samples.to_vec();
+    }
+    samples
+        .chunks_exact(channel_count)
+        .map(|frame| {
+            let sum: i32 = frame.iter().map(|&sample| sample as i32).sum();
+            (sum / channel_count as i32) as i16
+        })
+        .collect()
+}
+
+/// Linear interpolation, enough for prompts recorded for telephony.
+fn resample(samples: &[i16], from: usize, to: usize) -> Vec<i16> {
+    if from == to || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let len = (samples.len() as u64 * to as u64 / from as u64) as usize;
+    let step = from as f64 / to as f64;
+    (0..len)
+        .map(|index| {
+            let position = index as f64 * step;
+            let left = position as usize;
+            let right = (left + 1).min(samples.len() - 1);
+            let fraction = position - left as f64;
+            let sample = samples[left] as f64 * (1.0 - fraction) + samples[right] as f64 * fraction;
+            sample.round() as i16
+        })
+        .collect()
+}
+
+/// G.711 A-law expansion.
+fn alaw_to_linear(byte: u8) -> i16 {
+    let byte = byte ^ 0x55;
+    let exponent = (byte >> 4) & 0x07;
+    let mantissa = (byte & 0x0F) as i16;
+    let magnitude = match exponent {
+        0 => (mantissa << 4) + 8,
+        _ => ((mantissa << 4) + 0x108) << (exponent - 1),
+    };
+    if byte & 0x80 != 0 {
+        magnitude
+    } else {
+        -magnitude
+    }
+}
+
+/// G.711 mu-law expansion.
+fn mulaw_to_linear(byte: u8) -> i16 {
+    let byte = !byte;
+    let exponent = (byte >> 4) & 0x07;
+    let mantissa = (byte & 0x0F) as i16;
+    let magnitude = (((mantissa << 3) + 0x84) << exponent) - 0x84;
+    if byte & 0x80 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+fn u16_at(data: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([data[at], data[at + 1]])
+}
+
+fn u32_at(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+fn invalid(reason: &str) -> crate::Error {
+    crate::Error::InvalidAudio(reason.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::FrameDuration;
+
+    fn format(sampling_rate: usize) -> FrameFormat {
+        FrameFormat::new(sampling_rate, FrameDuration::Ms20)
+    }
+
+    fn wav(tag: u16, channels: u16, rate: u32, bits: u16, data: &[u8]) -> Vec<u8> {
+        let mut wav = b"RIFF".to_vec();
+        wav.extend((36 + data.len() as u32).to_le_bytes());
+        wav.extend(b"WAVEfmt ");
+        wav.extend(16u32.to_le_bytes());
+        wav.extend(tag.to_le_bytes());
+        wav.extend(channels.to_le_bytes());
+        wav.extend(rate.to_le_bytes());
+        let block = channels * bits / 8;
+        wav.extend((rate * block as u32).to_le_bytes());
+        wav.extend(block.to_le_bytes());
+        wav.extend(bits.to_le_bytes());
+        wav.extend(b"data");
+        wav.extend((data.len() as u32).to_le_bytes());
+        wav.extend(data);
+        wav
+    }
+
+    #[test]
+    fn g711_reference_values() {
+        assert_eq!(mulaw_to_linear(0xFF), 0);
+        assert_eq!(mulaw_to_linear(0x7F), 0);
+        assert_eq!(mulaw_to_linear(0x00), -32124);
+        assert_eq!(mulaw_to_linear(0x80), 32124);
+        assert_eq!(alaw_to_linear(0xD5), 8);
+        assert_eq!(alaw_to_linear(0x55), -8);
+        assert_eq!(alaw_to_linear(0x2A), -32256);
+        assert_eq!(alaw_to_linear(0xAA), 32256);
+    }
+
+    #[test]
+    fn raw_by_media_type() {
+        let pcmu = ContentType::new("audio/PCMU");
+        assert_eq!(
+            decode_audio(&[0xFF, 0x80], Some(&pcmu), format(8000)).unwrap(),
+            [0, 32124]
+        );
+        let l16: ContentType = "audio/L16; rate=8000".parse().unwrap();
+        assert_eq!(
+            decode_audio(&[0x01, 0x02], Some(&l16), format(8000)).unwrap(),
+            [0x0102]
+        );
+        let native: Vec<u8> = [5i16, -5].iter().flat_map(|s| s.to_ne_bytes()).collect();
+        assert_eq!(decode_audio(&native, None, format(8000)).unwrap(), [5, -5]);
+        let mp3 = ContentType::new("audio/mpeg");
+        assert!(decode_audio(&[0; 4], Some(&mp3), format(8000)).is_err());
+    }
+
+    #[test]
+    fn wav_formats() {
+        let pcm16: Vec<u8> = [100i16, -100]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        let data = wav(WAVE_FORMAT_PCM, 1, 8000, 16, &pcm16);
+        assert_eq!(
+            decode_audio(&data, None, format(8000)).unwrap(),
+            [100, -100]
+        );
+        let alaw = wav(WAVE_FORMAT_ALAW, 1, 8000, 8, &[0xD5, 0x2A]);
+        assert_eq!(
+            decode_audio(&alaw, None, format(8000)).unwrap(),
+            [8, -32256]
+        );
+        let pcm8 = wav(WAVE_FORMAT_PCM, 1, 8000, 8, &[128, 0]);
+        assert_eq!(
+            decode_audio(&pcm8, None, format(8000)).unwrap(),
+            [0, -32768]
+        );
+    }
+
+    #[test]
+    fn stereo_is_mixed_down() {
+        let stereo: Vec<u8> = [100i16, 300, -100, -300]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        let data = wav(WAVE_FORMAT_PCM, 2, 8000, 16, &stereo);
+        assert_eq!(
+            decode_audio(&data, None, format(8000)).unwrap(),
+            [200, -200]
+        );
+    }
+
+    #[test]
+    fn resampled_to_the_format() {
+        let pcm16: Vec<u8> = [0i16; 1600].iter().flat_map(|s| s.to_le_bytes()).collect();
+        let data = wav(WAVE_FORMAT_PCM, 1, 16000, 16, &pcm16);
+        assert_eq!(decode_audio(&data, None, format(8000)).unwrap().len(), 800);
+        assert_eq!(resample(&[0, 100], 8000, 16000), [0, 50, 100, 100]);
+    }
+
+    #[test]
+    fn invalid_wav() {
+        assert!(decode_audio(b"RIFF\0\0\0\0WAVE", None, format(8000)).is_err());
+        let float64 = wav(WAVE_FORMAT_IEEE_FLOAT, 1, 8000, 64, &[0; 8]);
+        assert!(decode_audio(&float64, None, format(8000)).is_err());
+        let no_rate = wav(WAVE_FORMAT_PCM, 1, 0, 16, &[0; 2]);
+        assert!(decode_audio(&no_rate, None, format(8000)).is_err());
+    }
+}
@@ -0,0 +1,221 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::FrameFormat;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DtmfMode {
+    /// Tones are mixed into the audio stream.
+    #[default]
+    InBand,
+    /// Tones are sent as RFC 4733 named events.
+    Rfc4733,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DtmfParams {
+    pub mode: DtmfMode,
+    pub tone_millis: usize,
+    pub gap_millis: usize,
+    /// Level of each of the two tones for in-band generation.
+    pub level_dbfs: f64,
+    /// Power level of RFC 4733 events in -dBm0, 0..=63.
+    pub volume: u8,
+}
+
+impl Default for DtmfParams {
+    fn default() -> Self {
+        Self {
+            mode: DtmfMode::default(),
+            tone_millis: 100,
+            gap_millis: 50,
+            level_dbfs: -10.0,
+            volume: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DtmfEvent {
+    /// RFC 4733 event code.
+    pub event_id: u8,
+    pub volume: u8,
+    pub duration: usize,
+    pub gap: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct DtmfGenerator {
+    format: FrameFormat,
+    params: DtmfParams,
+}
+
+impl DtmfGenerator {
+    pub fn new(format: FrameFormat, params: DtmfParams) -> Self {
+        Self { format, params }
+    }
+
+    pub fn params(&self) -> DtmfParams {
+        self.params
+    }
+
+    /// Renders the tone followed by the gap as samples.
+    pub fn tone(&self, digit: char) -> crate::Result<Vec<i16>> {
+        let (low, high) = frequencies(digit).ok_or(crate::Error::InvalidDtmfDigit(digit))?;
+        let rate = self.format.sampling_rate as f64;
+        let amplitude = i16::MAX as f64 * 10f64.powf(self.params.level_dbfs / 20.0);
+        let tone = self.format.millis_to_samples(self.params.tone_millis);
+        let gap = self.format.millis_to_samples(self.params.gap_millis);
+        let mut samples = Vec::with_capacity(tone + gap);
+        samples.extend((0..tone).map(|n| {
+            let t = n as f64 / rate;
+            let value = amplitude
+                * ((2.0 * std::f64::consts::PI * low * t).sin()
+                    + (2.0 * std::f64::consts::PI * high * t).sin());
+            value.clamp(i16::MIN as f64, i16::MAX as f64) as i16
+        }));
+        samples.resize(tone + gap, 0);
+        Ok(samples)
+    }
+
+    pub fn sequence(&self, digits: &str) -> crate::Result<Vec<i16>> {
+        let mut samples = Vec::new();
+        for digit in digits.chars() {
+            samples.extend(self.tone(digit)?);
+        }
+        Ok(samples)
+    }
+
+    /// Describes the digits as named events, durations are in samples.
+    pub fn events(&self, digits: &str) -> crate::Result<Vec<DtmfEvent>> {
+        digits
+            .chars()
+            .map(|digit| {
+                Ok(DtmfEvent {
+                    event_id: event_id(digit).ok_or(crate::Error::InvalidDtmfDigit(digit))?,
+                    volume: self.params.volume.min(63),
+                    duration: self.format.millis_to_samples(self.params.tone_millis),
+                    gap: self.format.millis_to_samples(self.params.gap_millis),
+                })
+            })
+            .collect()
+    }
+}
+
+fn event_id(digit: char) -> Option<u8> {
+    match digit.to_ascii_uppercase() {
+        digit @ '0'..='9' => Some(digit as u8 - b'0'),
+        '*' => Some(10),
+        '#' => Some(11),
+        digit @ 'A'..='D' => Some(digit as u8 - b'A' + 12),
+        _ => None,
+    }
+}
+
+fn frequencies(digit: char) -> Option<(f64, f64)> {
+    const LOW: [f64; 4] = [697.0, 770.0, 852.0, 941.0];
+    const HIGH: [f64; 4] = [1209.0, 1336.0, 1477.0, 1633.0];
+    let (row, column) = match digit.to_ascii_uppercase() {
+        '1' => (0, 0),
+        '2' => (0, 1),
+        '3' => (0, 2),
+        'A' => (0, 3),
+        '4' => (1, 0),
+        '5' => (1, 1),
+        '6' => (1, 2),
+        'B' => (1, 3),
+        '7' => (2, 0),
+        '8' => (2, 1),
+        '9' => (2, 2),
+        'C' => (2, 3),
+        '*' => (3, 0),
+        '0' => (3, 1),
+        '#' => (3, 2),
+        'D' => (3, 3),
+        _ => return None,
+    };
+    Some((LOW[row], HIGH[column]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::FrameDuration;
+
+    fn generator(params: DtmfParams) -> DtmfGenerator {
+        DtmfGenerator::new(FrameFormat::new(8000, FrameDuration::Ms20), params)
+    }
+
+    #[test]
+    fn tone_and_gap_lengths() {
+        let samples = generator(DtmfParams::default()).tone('5').unwrap();
+        assert_eq!(samples.len(), 800 + 400);
+        assert!(samples[..800].iter().any(|&sample| sample != 0));
+        assert!(samples[800..].iter().all(|&sample| sample == 0));
+        let sequence = generator(DtmfParams::default()).sequence("12#").unwrap();
+        assert_eq!(sequence.len(), 3 * 1200);
+    }
+
+    #[test]
+    fn peak_level() {
+        // Each of the two tones is at -10 dBFS, their sum peaks at twice the amplitude.
+        let amplitude = i16::MAX as f64 * 10f64.powf(-10.0 / 20.0);
+        let samples = generator(DtmfParams::default()).tone('1').unwrap();
+        let peak = samples
+            .iter()
+            .map(|sample| sample.unsigned_abs())
+            .max()
+            .unwrap() as f64;
+        assert!(peak <= 2.0 * amplitude, "{peak}");
+        assert!(peak > 1.9 * amplitude, "{peak}");
+    }
+
+    #[test]
+    fn events() {
+        let params = DtmfParams {
+            mode: DtmfMode::Rfc4733,
+            volume: 70,
+            ..Default::default()
+        };
+        let events = generator(params).events("9*#aD").unwrap();
+        let ids: Vec<u8> = events.iter().map(|event| event.event_id).collect();
+        assert_eq!(ids, [9, 10, 11, 12, 15]);
+        assert_eq!(
+            events[0],
+            DtmfEvent {
+                event_id: 9,
+                volume: 63,
+                duration: 800,
+                gap: 400,
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_digits() {
+        let generator = generator(DtmfParams::default());
+        assert!(matches!(
+            generator.tone('E'),
+            Err(crate::Error::InvalidDtmfDigit('E'))
+        ));
+        assert!(matches!(
+            generator.sequence("12x"),
+            Err(crate::Error::InvalidDtmfDigit('x'))
+        ));
+        assert!(matches!(
+            generator.events("1 2"),
+            Err(crate::Error::InvalidDtmfDigit(' '))
+        ));
+    }
+}
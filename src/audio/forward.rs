@@ -0,0 +1,349 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::FrameFormat;
+use crate::uni;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Discard the oldest queued chunk to make room for the new one.
+    #[default]
+    DropOldest,
+    /// Stop forwarding; the request is to be completed with the `error` cause.
+    FailRequest,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ForwarderParams {
+    pub chunk_millis: usize,
+    pub queue_capacity: usize,
+    pub policy: OverflowPolicy,
+}
+
+impl Default for ForwarderParams {
+    fn default() -> Self {
+        Self {
+            chunk_millis: 100,
+            queue_capacity: 50,
+            policy: OverflowPolicy::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ForwarderCounters {
+    /// Chunks taken by the receiver.
+    pub forwarded_chunks: usize,
+    pub dropped_chunks: usize,
+    pub dropped_bytes: usize,
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    chunks: VecDeque<Vec<u8>>,
+    counters: ForwarderCounters,
+    closed: bool,
+}
+
+impl Queue {
+    fn pop(&mut self) -> Option<Vec<u8>> {
+        let chunk = self.chunks.pop_front()?;
+        self.counters.forwarded_chunks += 1;
+        Some(chunk)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    queue: Mutex<Queue>,
+    available: Condvar,
+}
+
+impl Shared {
+    /// The queue stays consistent if a thread panics holding the lock, so poisoning is ignored.
+    fn lock(&self) -> MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Batches recognizer audio into chunks and hands them to the backend without blocking
+/// the media thread.
+#[derive(Debug)]
+pub struct AudioForwarder {
+    shared: Arc<Shared>,
+    params: ForwarderParams,
+    chunk_bytes: usize,
+    pending: Vec<u8>,
+    failed: bool,
+}
+
+/// The backend end of the forwarder queue.
+#[derive(Debug, Clone)]
+pub struct AudioReceiver {
+    shared: Arc<Shared>,
+}
+
+impl AudioForwarder {
+    pub fn new(format: FrameFormat, params: ForwarderParams) -> (Self, AudioReceiver) {
+        let shared = Arc::new(Shared::default());
+        let chunk_bytes = (format.millis_to_samples(params.chunk_millis)
+            * uni::BYTES_PER_SAMPLE as usize)
+            .max(format.bytes_per_frame());
+        let forwarder = Self {
+            shared: Arc::clone(&shared),
+            params,
+            chunk_bytes,
+            pending: Vec::with_capacity(chunk_bytes),
+            failed: false,
+        };
+        (forwarder, AudioReceiver { shared })
+    }
+
+    /// Takes the audio of a frame. Never blocks on the backend.
+    pub fn push(&mut self, audio: &[u8]) -> crate::Result<()> {
+        if self.failed {
+            return Err(crate::Error::AudioQueueOverflow);
+        }
+        self.pending.extend_from_slice(audio);
+        while self.pending.len() >= self.chunk_bytes {
+            let rest = self.pending.split_off(self.chunk_bytes);
+            let chunk = std::mem::replace(&mut self.pending, rest);
+            self.enqueue(chunk)?;
+        }
+        Ok(())
+    }
+
+    /// Sends the incomplete chunk, e.g. at the end of input.
+    pub fn flush(&mut self) -> crate::Result<()> {
+        if self.failed {
+            return Err(crate::Error::AudioQueueOverflow);
+        }
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::take(&mut self.pending);
+        self.enqueue(chunk)
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.failed
+    }
+
+    /// The completion cause the request should end with after an overflow.
+    pub fn completion_cause(&self) -> Option<uni::mrcp_recog_completion_cause_e> {
+        self.failed
+            .then_some(uni::RECOGNIZER_COMPLETION_CAUSE_ERROR as _)
+    }
+
+    pub fn counters(&self) -> ForwarderCounters {
+        self.shared.lock().counters
+    }
+
+    fn enqueue(&mut self, chunk: Vec<u8>) -> crate::Result<()> {
+        let mut queue = self.shared.lock();
+        if queue.chunks.len() >= self.params.queue_capacity {
+            match self.params.policy {
+                OverflowPolicy::DropOldest => {
+                    if let Some(dropped) = queue.chunks.pop_front() {
+                        queue.counters.dropped_chunks += 1;
+                        queue.counters.dropped_bytes += dropped.len();
+                    }
+                }
+                OverflowPolicy::FailRequest => {
+                    queue.counters.dropped_chunks += 1;
+                    queue.counters.dropped_bytes += chunk.len();
+                    self.failed = true;
+                    return Err(crate::Error::AudioQueueOverflow);
+                }
+            }
+        }
+        if self.params.queue_capacity > 0 {
+            queue.chunks.push_back(chunk);
+        } else {
+            queue.counters.dropped_chunks += 1;
+            queue.counters.dropped_bytes += chunk.len();
+        }
+        self.shared.available.notify_one();
+        Ok(())
+    }
+}
+
+impl Drop for AudioForwarder {
+    fn drop(&mut self) {
+        self.shared.lock().closed = true;
+        self.shared.available.notify_all();
+    }
+}
+
+impl AudioReceiver {
+    /// Waits for the next chunk. Returns `None` when the forwarder is gone and the queue is drained.
+    pub fn recv(&self) -> Option<Vec<u8>> {
+        let mut queue = self.shared.lock();
+        loop {
+            if let Some(chunk) = queue.pop() {
+                return Some(chunk);
+            }
+            if queue.closed {
+                return None;
+            }
+            queue = self
+                .shared
+                .available
+                .wait(queue)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Vec<u8>> {
+        let queue = self.shared.lock();
+        let (mut queue, _) = self
+            .shared
+            .available
+            .wait_timeout_while(queue, timeout, |queue| {
+                queue.chunks.is_empty() && !queue.closed
+            })
+            .unwrap_or_else(PoisonError::into_inner);
+        queue.pop()
+    }
+
+    pub fn try_recv(&self) -> Option<Vec<u8>> {
+        self.shared.lock().pop()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        let queue = self.shared.lock();
+        queue.closed && queue.chunks.is_empty()
+    }
+
+    pub fn counters(&self) -> ForwarderCounters {
+        self.shared.lock().counters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::FrameDuration;
+
+    /// Forwarder of 20 ms chunks, 320 bytes at 8 kHz.
+    fn forwarder(queue_capacity: usize, policy: OverflowPolicy) -> (AudioForwarder, AudioReceiver) {
+        AudioForwarder::new(
+            FrameFormat::new(8000, FrameDuration::Ms20),
+            ForwarderParams {
+                chunk_millis: 20,
+                queue_capacity,
+                policy,
+            },
+        )
+    }
+
+    #[test]
+    fn chunks_and_flush() {
+        let (mut forwarder, receiver) = forwarder(10, OverflowPolicy::DropOldest);
+        forwarder.push(&[1; 200]).unwrap();
+        assert_eq!(receiver.try_recv(), None);
+        forwarder.push(&[2; 500]).unwrap();
+        let first = receiver.try_recv().unwrap();
+        assert_eq!(first.len(), 320);
+        assert_eq!((first[199], first[200]), (1, 2));
+        assert_eq!(receiver.try_recv().unwrap().len(), 320);
+        assert_eq!(receiver.try_recv(), None);
+        forwarder.flush().unwrap();
+        assert_eq!(receiver.try_recv(), Some(vec![2; 60]));
+        forwarder.flush().unwrap();
+        assert_eq!(receiver.try_recv(), None);
+        assert_eq!(receiver.counters().forwarded_chunks, 3);
+    }
+
+    #[test]
+    fn drop_oldest() {
+        let (mut forwarder, receiver) = forwarder(2, OverflowPolicy::DropOldest);
+        for byte in 1..=4 {
+            forwarder.push(&[byte; 320]).unwrap();
+        }
+        assert!(!forwarder.is_failed());
+        assert_eq!(forwarder.completion_cause(), None);
+        assert_eq!(
+            forwarder.counters(),
+            ForwarderCounters {
+                forwarded_chunks: 0,
+                dropped_chunks: 2,
+                dropped_bytes: 640,
+            }
+        );
+        assert_eq!(receiver.try_recv(), Some(vec![3; 320]));
+        assert_eq!(receiver.try_recv(), Some(vec![4; 320]));
+        assert_eq!(receiver.counters().forwarded_chunks, 2);
+    }
+
+    #[test]
+    fn fail_request() {
+        let (mut forwarder, receiver) = forwarder(1, OverflowPolicy::FailRequest);
+        forwarder.push(&[1; 320]).unwrap();
+        assert!(matches!(
+            forwarder.push(&[2; 320]),
+            Err(crate::Error::AudioQueueOverflow)
+        ));
+        assert!(forwarder.is_failed());
+        assert_eq!(
+            forwarder.completion_cause(),
+            Some(uni::RECOGNIZER_COMPLETION_CAUSE_ERROR as _)
+        );
+        assert!(forwarder.push(&[3; 10]).is_err());
+        assert!(forwarder.flush().is_err());
+        assert_eq!(receiver.try_recv(), Some(vec![1; 320]));
+        assert_eq!(receiver.try_recv(), None);
+        let counters = receiver.counters();
+        assert_eq!((counters.dropped_chunks, counters.dropped_bytes), (1, 320));
+    }
+
+    #[test]
+    fn closed_after_drop() {
+        let (mut forwarder, receiver) = forwarder(4, OverflowPolicy::DropOldest);
+        forwarder.push(&[1; 320]).unwrap();
+        let waiting = std::thread::spawn({
+            let receiver = receiver.clone();
+            move || {
+                let mut chunks = 0;
+                while receiver.recv().is_some() {
+                    chunks += 1;
+                }
+                chunks
+            }
+        });
+        forwarder.push(&[2; 320]).unwrap();
+        drop(forwarder);
+        assert_eq!(waiting.join().unwrap(), 2);
+        assert!(receiver.is_closed());
+        assert_eq!(receiver.recv(), None);
+        assert_eq!(receiver.recv_timeout(Duration::from_millis(10)), None);
+    }
+
+    #[test]
+    fn recv_timeout_waits_for_a_chunk() {
+        let (mut forwarder, receiver) = forwarder(4, OverflowPolicy::DropOldest);
+        assert_eq!(receiver.recv_timeout(Duration::from_millis(10)), None);
+        assert!(!receiver.is_closed());
+        forwarder.push(&[1; 320]).unwrap();
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_millis(10)),
+            Some(vec![1; 320])
+        );
+    }
+}
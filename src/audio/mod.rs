@@ -0,0 +1,133 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use crate::uni;
+
+mod decode;
+pub use decode::decode_audio;
+
+mod dtmf;
+pub use dtmf::{DtmfEvent, DtmfGenerator, DtmfMode, DtmfParams};
+
+mod forward;
+pub use forward::{
+    AudioForwarder, AudioReceiver, ForwarderCounters, ForwarderParams, OverflowPolicy,
+};
+
+mod process;
+pub use process::{AudioProcessor, ProcessorParams};
+
+mod sink;
+pub use sink::AudioSink;
+
+mod source;
+pub use source::AudioSource;
+
+mod stats;
+pub use stats::{AnalyzerParams, AudioAnalyzer, AudioStats};
+
+/// Frame durations supported by UniMRCP 1.8.0 and higher.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FrameDuration {
+    #[default]
+    Ms10,
+    Ms20,
+    Ms30,
+    Ms40,
+}
+
+impl FrameDuration {
+    pub fn millis(self) -> usize {
+        match self {
+            FrameDuration::Ms10 => 10,
+            FrameDuration::Ms20 => 20,
+            FrameDuration::Ms30 => 30,
+            FrameDuration::Ms40 => 40,
+        }
+    }
+}
+
+impl TryFrom<usize> for FrameDuration {
+    type Error = crate::Error;
+
+    fn try_from(millis: usize) -> crate::Result<Self> {
+        match millis {
+            10 => Ok(FrameDuration::Ms10),
+            20 => Ok(FrameDuration::Ms20),
+            30 => Ok(FrameDuration::Ms30),
+            40 => Ok(FrameDuration::Ms40),
+            other => Err(crate::Error::UnsupportedFrameDuration(other)),
+        }
+    }
+}
+
+/// Linear PCM layout of the frames exchanged with the media engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameFormat {
+    pub sampling_rate: usize,
+    pub channel_count: usize,
+    pub frame_duration: FrameDuration,
+}
+
+impl FrameFormat {
+    pub fn new(sampling_rate: usize, frame_duration: FrameDuration) -> Self {
+        Self {
+            sampling_rate,
+            channel_count: 1,
+            frame_duration,
+        }
+    }
+
+    /// Reads the negotiated format. Servers older than 1.8.0 leave `frame_duration` zeroed,
+    /// which is treated as the former hardcoded 10 ms.
+    pub unsafe fn from_descriptor(
+        descriptor: *const uni::mpf_codec_descriptor_t,
+    ) -> crate::Result<Self> {
+        if descriptor.is_null() {
+            return Err(crate::Error::NullCodecDescriptor);
+        }
+        let frame_duration = match (*descriptor).frame_duration {
+            0 => FrameDuration::default(),
+            millis => FrameDuration::try_from(millis)?,
+        };
+        Ok(Self {
+            sampling_rate: (*descriptor).sampling_rate as _,
+            channel_count: ((*descriptor).channel_count as usize).max(1),
+            frame_duration,
+        })
+    }
+
+    pub fn samples_per_frame(&self) -> usize {
+        self.sampling_rate * self.frame_duration.millis() / 1000 * self.channel_count
+    }
+
+    pub fn bytes_per_frame(&self) -> usize {
+        self.samples_per_frame() * uni::BYTES_PER_SAMPLE as usize
+    }
+
+    pub fn samples_to_millis(&self, samples: usize) -> usize {
+        samples * 1000 / (self.sampling_rate * self.channel_count).max(1)
+    }
+
+    pub fn millis_to_samples(&self, millis: usize) -> usize {
+        millis * self.sampling_rate * self.channel_count / 1000
+    }
+}
+
+/// Interprets raw L16 frame payload as samples.
+pub fn samples(bytes: &[u8]) -> impl Iterator<Item = i16> + '_ {
+    bytes
+        .chunks_exact(2)
+        .map(|pair| i16::from_ne_bytes([pair[0], pair[1]]))
+}
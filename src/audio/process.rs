@@ -0,0 +1,427 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::FrameFormat;
+use crate::headers::SynthHeaders;
+
+const MIN_RATE: f64 = 0.25;
+const MAX_RATE: f64 = 4.0;
+const WINDOW_MS: usize = 20;
+const TOLERANCE_MS: usize = 10;
+const RELEASE_MS: f64 = 50.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcessorParams {
+    /// Gain applied to the samples, negative infinity gives silence,
+    /// NaN and positive infinity are taken as 0.0.
+    pub gain_db: f64,
+    /// Tempo multiplier, above 1.0 the audio is shorter, the pitch is kept.
+    /// Limited to the range from 0.25 to 4.0, a non-finite rate is taken as 1.0.
+    pub rate: f64,
+    /// Peak level the limiter keeps amplified audio under.
+    pub limit_dbfs: f64,
+}
+
+impl Default for ProcessorParams {
+    fn default() -> Self {
+        Self {
+            gain_db: 0.0,
+            rate: 1.0,
+            limit_dbfs: -1.0,
+        }
+    }
+}
+
+impl ProcessorParams {
+    /// Gain of prosody-volume and rate of prosody-rate of the request.
+    pub fn from_headers(headers: &SynthHeaders) -> Self {
+        Self {
+            gain_db: headers.volume_gain_db(),
+            rate: headers.rate_multiplier(),
+            ..Default::default()
+        }
+    }
+
+    /// True when processing would leave the audio as is.
+    pub fn is_identity(&self) -> bool {
+        self.gain_db == 0.0 && self.rate == 1.0
+    }
+}
+
+/// Post-processing of synthesized audio for backends that ignore prosody:
+/// pitch preserving time stretching (WSOLA) followed by gain with a peak limiter.
+/// Time stretching is applied to mono audio only.
+#[derive(Debug, Clone)]
+pub struct AudioProcessor {
+    params: ProcessorParams,
+    factor: f64,
+    limiter: Limiter,
+    stretch: Option<TimeStretch>,
+}
+
+impl AudioProcessor {
+    pub fn new(format: FrameFormat, params: ProcessorParams) -> Self {
+        let rate = match params.rate.is_finite() {
+            true => params.rate.clamp(MIN_RATE, MAX_RATE),
+            false => 1.0,
+        };
+        // Negative infinity is kept for silence.
+        let gain_db = match params.gain_db.is_nan() || params.gain_db == f64::INFINITY {
+            true => 0.0,
+            false => params.gain_db,
+        };
+        let stretch = (rate != 1.0 && format.channel_count == 1)
+            .then(|| TimeStretch::new(format.sampling_rate, rate));
+        Self {
+            params: ProcessorParams {
+                gain_db,
+                rate,
+                ..params
+            },
+            factor: 10f64.powf(gain_db / 20.0),
+            limiter: Limiter::new(format, params.limit_dbfs),
+            stretch,
+        }
+    }
+
+    pub fn params(&self) -> ProcessorParams {
+        self.params
+    }
+
+    /// Processes the samples, time stretching holds back up to a few tens of milliseconds
+    /// until more audio comes or `flush` is called.
+    pub fn process(&mut self, samples: &[i16]) -> Vec<i16> {
+        match self.stretch.as_mut() {
+            Some(stretch) => {
+                let stretched = stretch.process(samples);
+                self.amplify(stretched)
+            }
+            None => self.amplify(samples.iter().map(|&sample| sample as f32)),
+        }
+    }
+
+    /// Gives the audio held back at the end of the request.
+    pub fn flush(&mut self) -> Vec<i16> {
+        match self.stretch.as_mut() {
+            Some(stretch) => {
+                let stretched = stretch.flush();
+                self.amplify(stretched)
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Drops the audio held back and prepares the processor for the next request.
+    pub fn reset(&mut self) {
+        self.limiter.gain = 1.0;
+        if let Some(stretch) = self.stretch.as_mut() {
+            stretch.reset();
+        }
+    }
+
+    /// Offset in samples of the written audio of an offset in the processed audio,
+    /// e.g. of the playback position to compare with marks reported by the backend.
+    pub fn input_offset(&self, offset: usize) -> usize {
+        match self.stretch {
+            Some(_) => (offset as f64 * self.params.rate).round() as usize,
+            None => offset,
+        }
+    }
+
+    fn amplify(&mut self, samples: impl IntoIterator<Item = f32>) -> Vec<i16> {
+        let samples = samples.into_iter();
+        if self.factor == 1.0 && self.stretch.is_none() {
+            return samples.map(|sample| sample as i16).collect();
+        }
+        samples
+            .map(|sample| self.limiter.apply(sample as f64 * self.factor))
+            .collect()
+    }
+}
+
+/// Reduces the gain at once when a sample would exceed the threshold
+/// and restores it smoothly, so that loud audio is not hard clipped.
+#[derive(Debug, Clone)]
+struct Limiter {
+    threshold: f64,
+    release: f64,
+    gain: f64,
+}
+
+impl Limiter {
+    fn new(format: FrameFormat, limit_dbfs: f64) -> Self {
+        let release_samples = RELEASE_MS * format.sampling_rate as f64 / 1000.0;
+        Self {
+            threshold: i16::MAX as f64 * 10f64.powf(limit_dbfs.min(0.0) / 20.0),
+            release: 1.0 - (-1.0 / release_samples.max(1.0)).exp(),
+            gain: 1.0,
+        }
+    }
+
+    fn apply(&mut self, sample: f64) -> i16 {
+        let magnitude = sample.abs();
+        let needed = if magnitude > self.threshold {
+            self.threshold / magnitude
+        } else {
+            1.0
+        };
+        self.gain = needed.min(self.gain + (1.0 - self.gain) * self.release);
+        (sample * self.gain)
+            .round()
+            .clamp(i16::MIN as f64, i16::MAX as f64) as i16
+    }
+}
+
+/// Waveform similarity overlap-add: frames taken from the input at the rate
+/// are shifted within the tolerance to best continue the previous frame
+/// and overlap-added with a Hann window at half the window length.
+#[derive(Debug, Clone)]
+struct TimeStretch {
+    rate: f64,
+    window: Vec<f32>,
+    hop: usize,
+    tolerance: usize,
+    input: Vec<f32>,
+    /// Position in `input` the next frame is nominally taken from.
+    nominal: f64,
+    /// Position in `input` of the previous frame.
+    previous: Option<usize>,
+    /// Windowed second half of the previous frame.
+    overlap: Vec<f32>,
+    received: usize,
+    produced: usize,
+}
+
+impl TimeStretch {
+    fn new(sampling_rate: usize, rate: f64) -> Self {
+        let hop = (sampling_rate * WINDOW_MS / 2000).max(8);
+        let len = hop * 2;
+        let window = (0..len)
+            .map(|n| {
+                let phase = 2.0 * std::f64::consts::PI * n as f64 / len as f64;
+                (0.5 - 0.5 * phase.cos()) as f32
+            })
+            .collect();
+        Self {
+            rate,
+            window,
+            hop,
+            tolerance: (sampling_rate * TOLERANCE_MS / 1000).max(4),
+            input: Vec::new(),
+            nominal: 0.0,
+            previous: None,
+            overlap: vec![0.0; hop],
+            received: 0,
+            produced: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.input.clear();
+        self.nominal = 0.0;
+        self.previous = None;
+        self.overlap.fill(0.0);
+        self.received = 0;
+        self.produced = 0;
+    }
+
+    fn process(&mut self, samples: &[i16]) -> Vec<f32> {
+        self.received += samples.len();
+        self.input
+            .extend(samples.iter().map(|&sample| sample as f32));
+        let mut out = Vec::with_capacity((samples.len() as f64 / self.rate) as usize + self.hop);
+        self.run(&mut out);
+        self.produced += out.len();
+        out
+    }
+
+    /// Pads the input with silence to get the last frames out, the output
+    /// is cut to the duration of the input divided by the rate.
+    fn flush(&mut self) -> Vec<f32> {
+        let expected = (self.received as f64 / self.rate).round() as usize;
+        let padding = self.window.len() + self.tolerance + self.hop;
+        self.input.resize(self.input.len() + padding, 0.0);
+        let mut out = Vec::new();
+        self.run(&mut out);
+        out.extend_from_slice(&self.overlap);
+        out.resize(expected.saturating_sub(self.produced), 0.0);
+        self.reset();
+        out
+    }
+
+    fn run(&mut self, out: &mut Vec<f32>) {
+        let (len, hop) = (self.window.len(), self.hop);
+        loop {
+            let nominal = self.nominal.round() as usize;
+            let (low, high) = (
+                nominal.saturating_sub(self.tolerance),
+                nominal + self.tolerance,
+            );
+            let needed = match self.previous {
+                Some(previous) => (high + len).max(previous + len),
+                None => nominal + len,
+            };
+            if self.input.len() < needed {
+                break;
+            }
+            let position = match self.previous {
+                Some(previous) => self.best_position(low, high, previous + hop),
+                None => nominal,
+            };
+            let (head, tail) = self.input[position..position + len].split_at(hop);
+            // The first frame starts unfaded.
+            let first = self.previous.is_none();
+            for ((overlap, sample), weight) in self.overlap.iter().zip(head).zip(&self.window) {
+                out.push(overlap + sample * if first { 1.0 } else { *weight });
+            }
+            for ((overlap, sample), weight) in
+                self.overlap.iter_mut().zip(tail).zip(&self.window[hop..])
+            {
+                *overlap = sample * weight;
+            }
+            self.previous = Some(position);
+            self.nominal += self.rate * hop as f64;
+            self.drain(position);
+        }
+    }
+
+    /// Start of the frame within the range whose first half is most similar
+    /// to the natural continuation of the previous frame at `target`.
+    fn best_position(&self, low: usize, high: usize, target: usize) -> usize {
+        let target = &self.input[target..target + self.hop];
+        let mut best = (low, f32::NEG_INFINITY);
+        for position in low..=high {
+            let candidate = &self.input[position..position + self.hop];
+            let (mut product, mut energy) = (0.0, 0.0);
+            for (a, b) in candidate.iter().zip(target) {
+                product += a * b;
+                energy += a * a;
+            }
+            let similarity = if energy > 0.0 {
+                product / energy.sqrt()
+            } else {
+                0.0
+            };
+            if similarity > best.1 {
+                best = (position, similarity);
+            }
+        }
+        best.0
+    }
+
+    /// Drops the input no frame can start from anymore.
+    fn drain(&mut self, position: usize) {
+        let next = (self.nominal as usize).saturating_sub(self.tolerance);
+        let consumed = next.min(position);
+        if consumed == 0 {
+            return;
+        }
+        self.input.drain(..consumed);
+        self.nominal -= consumed as f64;
+        self.previous = Some(position - consumed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::FrameDuration;
+
+    fn format() -> FrameFormat {
+        FrameFormat::new(8000, FrameDuration::Ms20)
+    }
+
+    fn tone(len: usize) -> Vec<i16> {
+        (0..len)
+            .map(|n| {
+                let phase = 2.0 * std::f64::consts::PI * 200.0 * n as f64 / 8000.0;
+                (phase.sin() * 8000.0) as i16
+            })
+            .collect()
+    }
+
+    fn processor(gain_db: f64, rate: f64) -> AudioProcessor {
+        AudioProcessor::new(
+            format(),
+            ProcessorParams {
+                gain_db,
+                rate,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn flush_gives_input_length_over_rate() {
+        let input = tone(8000);
+        for rate in [0.5, 0.8, 1.25, 2.0] {
+            for chunk in [160, 333, 8000] {
+                let mut processor = processor(0.0, rate);
+                let mut output = Vec::new();
+                for samples in input.chunks(chunk) {
+                    output.extend(processor.process(samples));
+                }
+                output.extend(processor.flush());
+                let expected = (input.len() as f64 / rate).round() as usize;
+                assert_eq!(output.len(), expected, "rate {rate}, chunk {chunk}");
+            }
+        }
+    }
+
+    #[test]
+    fn flush_resets_the_stretch() {
+        let mut processor = processor(0.0, 2.0);
+        processor.process(&tone(1000));
+        processor.flush();
+        let mut output = processor.process(&tone(800));
+        output.extend(processor.flush());
+        assert_eq!(output.len(), 400);
+    }
+
+    #[test]
+    fn unit_gain_and_rate_leave_audio_as_is() {
+        let input = tone(480);
+        let mut processor = processor(0.0, 1.0);
+        assert!(processor.params().is_identity());
+        assert_eq!(processor.process(&input), input);
+        assert!(processor.flush().is_empty());
+    }
+
+    #[test]
+    fn gain_and_limiter() {
+        let quieter = processor(-6.0, 1.0).process(&[10000, -10000]);
+        assert_eq!(quieter, [5012, -5012]);
+        let threshold = (i16::MAX as f64 * 10f64.powf(-1.0 / 20.0)).round() as i16;
+        let louder = processor(20.0, 1.0).process(&tone(800));
+        assert!(louder.iter().all(|sample| sample.abs() <= threshold));
+        assert_eq!(processor(f64::NEG_INFINITY, 1.0).process(&[1000]), [0]);
+    }
+
+    #[test]
+    fn non_finite_params() {
+        for value in [f64::NAN, f64::INFINITY] {
+            let params = processor(value, value).params();
+            assert_eq!((params.gain_db, params.rate), (0.0, 1.0));
+        }
+        assert_eq!(processor(0.0, f64::NEG_INFINITY).params().rate, 1.0);
+        assert_eq!(processor(0.0, 10.0).params().rate, MAX_RATE);
+    }
+
+    #[test]
+    fn input_offset_scales_with_rate() {
+        assert_eq!(processor(0.0, 2.0).input_offset(800), 1600);
+        assert_eq!(processor(0.0, 0.5).input_offset(800), 400);
+        assert_eq!(processor(6.0, 1.0).input_offset(800), 800);
+    }
+}
@@ -0,0 +1,85 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::{AnalyzerParams, AudioAnalyzer, AudioStats, FrameFormat};
+use crate::uni;
+
+/// Audio received by the `write_frame` callback of a recognizer sink stream.
+#[derive(Debug)]
+pub struct AudioSink {
+    format: FrameFormat,
+    received: usize,
+    analyzer: Option<AudioAnalyzer>,
+}
+
+impl AudioSink {
+    pub fn new(format: FrameFormat) -> Self {
+        Self {
+            format,
+            received: 0,
+            analyzer: None,
+        }
+    }
+
+    /// Enables collection of per request audio statistics.
+    pub fn with_analyzer(mut self, params: AnalyzerParams) -> Self {
+        self.analyzer = Some(AudioAnalyzer::new(self.format, params));
+        self
+    }
+
+    pub fn format(&self) -> FrameFormat {
+        self.format
+    }
+
+    /// Starts counting audio of a new request.
+    pub fn reset(&mut self) {
+        self.received = 0;
+        if let Some(analyzer) = self.analyzer.as_mut() {
+            analyzer.reset();
+        }
+    }
+
+    pub fn stats(&self) -> Option<AudioStats> {
+        self.analyzer.as_ref().map(AudioAnalyzer::stats)
+    }
+
+    /// Amount of audio received since the last reset in samples.
+    pub fn position(&self) -> usize {
+        self.received
+    }
+
+    pub fn position_millis(&self) -> usize {
+        self.format.samples_to_millis(self.received)
+    }
+
+    /// Returns the audio payload of the frame, if any.
+    pub unsafe fn write_frame<'a>(&mut self, frame: *const uni::mpf_frame_t) -> Option<&'a [u8]> {
+        if frame.is_null()
+            || (*frame).type_ & uni::MEDIA_FRAME_TYPE_AUDIO as std::os::raw::c_int == 0
+            || (*frame).codec_frame.buffer.is_null()
+        {
+            return None;
+        }
+        let audio = std::slice::from_raw_parts(
+            (*frame).codec_frame.buffer as *const u8,
+            (*frame).codec_frame.size,
+        );
+        self.received += audio.len() / uni::BYTES_PER_SAMPLE as usize;
+        if let Some(analyzer) = self.analyzer.as_mut() {
+            let samples = super::samples(audio).collect::<Vec<_>>();
+            analyzer.process(&samples);
+        }
+        Some(audio)
+    }
+}
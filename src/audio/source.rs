@@ -0,0 +1,350 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::{AudioProcessor, DtmfEvent, DtmfGenerator, DtmfMode, FrameFormat};
+use crate::uni;
+use std::collections::VecDeque;
+
+#[derive(Debug)]
+enum Segment {
+    Audio(VecDeque<u8>),
+    Event { event: DtmfEvent, elapsed: usize },
+}
+
+/// Synthesized audio waiting to be played by the `read_frame` callback of a source stream.
+#[derive(Debug)]
+pub struct AudioSource {
+    format: FrameFormat,
+    segments: VecDeque<Segment>,
+    played: usize,
+    finished: bool,
+    paused: bool,
+    processor: Option<AudioProcessor>,
+    /// Byte of a sample split between writes, kept for the processor.
+    odd_byte: Option<u8>,
+}
+
+impl AudioSource {
+    pub fn new(format: FrameFormat) -> Self {
+        Self {
+            format,
+            segments: VecDeque::new(),
+            played: 0,
+            finished: false,
+            paused: false,
+            processor: None,
+            odd_byte: None,
+        }
+    }
+
+    pub fn format(&self) -> FrameFormat {
+        self.format
+    }
+
+    /// Post-processes the audio written from now on, e.g. when the backend ignores prosody.
+    /// The processor is dropped on `reset` along with the audio of the request.
+    pub fn set_processor(&mut self, processor: Option<AudioProcessor>) {
+        self.flush_processor();
+        // Without a processor bytes are queued as they come.
+        if processor.is_none() {
+            if let Some(byte) = self.odd_byte.take() {
+                self.audio_tail().push_back(byte);
+            }
+        }
+        self.processor = processor;
+    }
+
+    pub fn processor(&self) -> Option<&AudioProcessor> {
+        self.processor.as_ref()
+    }
+
+    pub fn write(&mut self, audio: &[u8]) {
+        let Some(processor) = self.processor.as_mut() else {
+            self.audio_tail().extend(audio);
+            return;
+        };
+        let mut bytes = Vec::with_capacity(audio.len() + 1);
+        bytes.extend(self.odd_byte.take());
+        bytes.extend_from_slice(audio);
+        if bytes.len() % 2 == 1 {
+            self.odd_byte = bytes.pop();
+        }
+        let samples: Vec<i16> = super::samples(&bytes).collect();
+        let processed = processor.process(&samples);
+        self.push_samples(&processed);
+    }
+
+    pub fn write_samples(&mut self, samples: &[i16]) {
+        match self.processor.as_mut() {
+            Some(processor) => {
+                let processed = processor.process(samples);
+                self.push_samples(&processed);
+            }
+            None => self.push_samples(samples),
+        }
+    }
+
+    /// Enqueues DTMF digits after the audio written so far, they are not post-processed.
+    pub fn write_dtmf(&mut self, generator: &DtmfGenerator, digits: &str) -> crate::Result<()> {
+        self.flush_processor();
+        match generator.params().mode {
+            DtmfMode::InBand => self.push_samples(&generator.sequence(digits)?),
+            DtmfMode::Rfc4733 => {
+                for event in generator.events(digits)? {
+                    self.segments
+                        .push_back(Segment::Event { event, elapsed: 0 });
+                    self.push_samples(&vec![0; event.gap]);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks that no more audio will be written for the current request.
+    pub fn finish(&mut self) {
+        self.flush_processor();
+        self.finished = true;
+    }
+
+    /// True when all the audio has been written and played.
+    pub fn is_completed(&self) -> bool {
+        self.finished && self.segments.is_empty()
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Drops pending audio and prepares the source for the next request.
+    pub fn reset(&mut self) {
+        self.segments.clear();
+        self.played = 0;
+        self.finished = false;
+        self.paused = false;
+        self.processor = None;
+        self.odd_byte = None;
+    }
+
+    pub fn buffered_samples(&self) -> usize {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Audio(audio) => audio.len() / uni::BYTES_PER_SAMPLE as usize,
+                Segment::Event { event, elapsed } => event.duration - elapsed,
+            })
+            .sum()
+    }
+
+    /// Playback position of the current request in samples.
+    pub fn position(&self) -> usize {
+        self.played
+    }
+
+    pub fn position_millis(&self) -> usize {
+        self.format.samples_to_millis(self.position())
+    }
+
+    /// Playback position in samples of the audio as written, before time stretching.
+    /// Offsets reported by the backend, e.g. of marks, refer to it.
+    pub fn input_position(&self) -> usize {
+        match self.processor.as_ref() {
+            Some(processor) => processor.input_offset(self.played),
+            None => self.played,
+        }
+    }
+
+    /// Fills `out` with buffered audio padding the tail with silence.
+    /// Returns `false` and leaves `out` untouched if there is nothing to play.
+    /// Named events are played as silence, use `read_frame` to send them out of band.
+    pub fn read(&mut self, out: &mut [u8]) -> bool {
+        if self.paused {
+            return false;
+        }
+        match self.segments.front_mut() {
+            None => false,
+            Some(Segment::Audio(audio)) => {
+                let len = out.len().min(audio.len());
+                for (dst, src) in out.iter_mut().zip(audio.drain(..len)) {
+                    *dst = src;
+                }
+                out[len..].fill(0);
+                if audio.is_empty() {
+                    self.segments.pop_front();
+                }
+                self.played += len / uni::BYTES_PER_SAMPLE as usize;
+                true
+            }
+            Some(Segment::Event { .. }) => {
+                out.fill(0);
+                self.advance_event(out.len() / uni::BYTES_PER_SAMPLE as usize);
+                true
+            }
+        }
+    }
+
+    pub unsafe fn read_frame(&mut self, frame: *mut uni::mpf_frame_t) -> bool {
+        if frame.is_null() || self.paused {
+            return false;
+        }
+        if let Some(Segment::Event { event, elapsed }) = self.segments.front() {
+            let (event, elapsed) = (*event, *elapsed);
+            let frame_samples = self.format.samples_per_frame();
+            let duration = (elapsed + frame_samples).min(event.duration);
+            // An event of a single frame is ended by an extra frame of the same duration.
+            let single = elapsed == 0 && duration == event.duration && duration > 0;
+            let end = duration == event.duration && !single;
+            let event_frame = &mut (*frame).event_frame;
+            event_frame.set_event_id(event.event_id as _);
+            event_frame.set_volume(event.volume as _);
+            event_frame.set_reserved(0);
+            event_frame.set_edge(end as _);
+            event_frame.set_duration(duration as _);
+            (*frame).marker = if elapsed == 0 {
+                uni::MPF_MARKER_START_OF_EVENT as _
+            } else if end {
+                uni::MPF_MARKER_END_OF_EVENT as _
+            } else {
+                uni::MPF_MARKER_NONE as _
+            };
+            (*frame).type_ |= uni::MEDIA_FRAME_TYPE_EVENT as std::os::raw::c_int;
+            match self.segments.front_mut() {
+                Some(Segment::Event { elapsed, .. }) if single => {
+                    *elapsed = duration;
+                    self.played += duration;
+                }
+                _ => self.advance_event(frame_samples),
+            }
+            return true;
+        }
+        if (*frame).codec_frame.buffer.is_null() {
+            return false;
+        }
+        let out = std::slice::from_raw_parts_mut(
+            (*frame).codec_frame.buffer as *mut u8,
+            (*frame).codec_frame.size,
+        );
+        if self.read(out) {
+            (*frame).type_ |= uni::MEDIA_FRAME_TYPE_AUDIO as std::os::raw::c_int;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn push_samples(&mut self, samples: &[i16]) {
+        self.audio_tail()
+            .extend(samples.iter().flat_map(|sample| sample.to_ne_bytes()));
+    }
+
+    fn flush_processor(&mut self) {
+        if let Some(processor) = self.processor.as_mut() {
+            let tail = processor.flush();
+            self.push_samples(&tail);
+        }
+    }
+
+    fn audio_tail(&mut self) -> &mut VecDeque<u8> {
+        if !matches!(self.segments.back(), Some(Segment::Audio(_))) {
+            self.segments.push_back(Segment::Audio(VecDeque::new()));
+        }
+        match self.segments.back_mut() {
+            Some(Segment::Audio(audio)) => audio,
+            _ => unreachable!(),
+        }
+    }
+
+    fn advance_event(&mut self, samples: usize) {
+        if let Some(Segment::Event { event, elapsed }) = self.segments.front_mut() {
+            let step = samples.min(event.duration - *elapsed);
+            *elapsed += step;
+            self.played += step;
+            if *elapsed >= event.duration {
+                self.segments.pop_front();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{DtmfMode, DtmfParams, FrameDuration};
+
+    /// Marker, end bit and duration of the event frames of a digit
+    /// with the playback position after each of them.
+    fn event_frames(tone_millis: usize) -> Vec<(u32, u32, u32, usize)> {
+        let format = FrameFormat::new(8000, FrameDuration::Ms20);
+        let generator = DtmfGenerator::new(
+            format,
+            DtmfParams {
+                mode: DtmfMode::Rfc4733,
+                tone_millis,
+                ..Default::default()
+            },
+        );
+        let mut source = AudioSource::new(format);
+        source.write_dtmf(&generator, "5").unwrap();
+        let mut frames = Vec::new();
+        let mut buffer = vec![0u8; format.bytes_per_frame()];
+        loop {
+            let mut frame: uni::mpf_frame_t = unsafe { std::mem::zeroed() };
+            frame.codec_frame.buffer = buffer.as_mut_ptr() as _;
+            frame.codec_frame.size = buffer.len();
+            let read = unsafe { source.read_frame(&mut frame) };
+            // The gap after the event is audio.
+            if !read || frame.type_ != uni::MEDIA_FRAME_TYPE_EVENT as std::os::raw::c_int {
+                break;
+            }
+            assert_eq!(frame.event_frame.event_id(), 5);
+            frames.push((
+                frame.marker as u32,
+                frame.event_frame.edge(),
+                frame.event_frame.duration(),
+                source.position(),
+            ));
+        }
+        frames
+    }
+
+    #[test]
+    fn event_frames_are_marked() {
+        assert_eq!(
+            event_frames(50),
+            [
+                (uni::MPF_MARKER_START_OF_EVENT, 0, 160, 160),
+                (uni::MPF_MARKER_NONE, 0, 320, 320),
+                (uni::MPF_MARKER_END_OF_EVENT, 1, 400, 400),
+            ]
+        );
+    }
+
+    #[test]
+    fn single_frame_event_gets_an_end_frame() {
+        assert_eq!(
+            event_frames(20),
+            [
+                (uni::MPF_MARKER_START_OF_EVENT, 0, 160, 160),
+                (uni::MPF_MARKER_END_OF_EVENT, 1, 160, 160),
+            ]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::FrameFormat;
+use crate::headers::VendorParams;
+
+#[derive(Debug, Clone, Copy)]
+pub struct AnalyzerParams {
+    /// Frames with RMS level above the threshold are counted as speech.
+    pub speech_threshold_dbfs: f64,
+    /// Samples with absolute value at or above the threshold are counted as clipped.
+    pub clipping_threshold: i16,
+}
+
+impl Default for AnalyzerParams {
+    fn default() -> Self {
+        Self {
+            speech_threshold_dbfs: -40.0,
+            clipping_threshold: i16::MAX - 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AudioStats {
+    pub duration_ms: usize,
+    pub speech_ms: usize,
+    pub leading_silence_ms: usize,
+    pub peak_dbfs: f64,
+    pub rms_dbfs: f64,
+    pub clipping_percent: f64,
+}
+
+impl AudioStats {
+    pub fn silence_ratio(&self) -> f64 {
+        if self.duration_ms == 0 {
+            return 1.0;
+        }
+        1.0 - self.speech_ms as f64 / self.duration_ms as f64
+    }
+
+    /// Represents the statistics as vendor-specific parameters with the given key prefix.
+    pub fn to_vendor_params(&self, prefix: &str) -> VendorParams {
+        [
+            ("duration", self.duration_ms.to_string()),
+            ("speech-duration", self.speech_ms.to_string()),
+            ("leading-silence", self.leading_silence_ms.to_string()),
+            ("peak-level", format!("{:.1}", self.peak_dbfs)),
+            ("rms-level", format!("{:.1}", self.rms_dbfs)),
+            ("clipping", format!("{:.2}", self.clipping_percent)),
+        ]
+        .into_iter()
+        .map(|(key, value)| (format!("{prefix}{key}"), value))
+        .collect()
+    }
+}
+
+impl std::fmt::Display for AudioStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "duration={}ms speech={}ms leading-silence={}ms peak={:.1}dBFS rms={:.1}dBFS clipping={:.2}%",
+            self.duration_ms,
+            self.speech_ms,
+            self.leading_silence_ms,
+            self.peak_dbfs,
+            self.rms_dbfs,
+            self.clipping_percent
+        )
+    }
+}
+
+/// Accumulates level statistics of the audio of a single request.
+#[derive(Debug, Clone)]
+pub struct AudioAnalyzer {
+    format: FrameFormat,
+    params: AnalyzerParams,
+    samples: usize,
+    speech_samples: usize,
+    leading_silence: Option<usize>,
+    peak: u16,
+    sum_of_squares: f64,
+    clipped: usize,
+}
+
+impl AudioAnalyzer {
+    pub fn new(format: FrameFormat, params: AnalyzerParams) -> Self {
+        Self {
+            format,
+            params,
+            samples: 0,
+            speech_samples: 0,
+            leading_silence: None,
+            peak: 0,
+            sum_of_squares: 0.0,
+            clipped: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new(self.format, self.params);
+    }
+
+    /// Takes the audio of one frame.
+    pub fn process(&mut self, frame: &[i16]) {
+        if frame.is_empty() {
+            return;
+        }
+        let mut frame_sum = 0.0;
+        for &sample in frame {
+            let magnitude = sample.unsigned_abs();
+            self.peak = self.peak.max(magnitude);
+            if magnitude >= self.params.clipping_threshold.unsigned_abs() {
+                self.clipped += 1;
+            }
+            frame_sum += (sample as f64) * (sample as f64);
+        }
+        self.sum_of_squares += frame_sum;
+        let frame_rms = (frame_sum / frame.len() as f64).sqrt();
+        if to_dbfs(frame_rms) > self.params.speech_threshold_dbfs {
+            self.speech_samples += frame.len();
+            self.leading_silence.get_or_insert(self.samples);
+        }
+        self.samples += frame.len();
+    }
+
+    pub fn stats(&self) -> AudioStats {
+        let rms = if self.samples == 0 {
+            0.0
+        } else {
+            (self.sum_of_squares / self.samples as f64).sqrt()
+        };
+        let clipping_percent = if self.samples == 0 {
+            0.0
+        } else {
+            100.0 * self.clipped as f64 / self.samples as f64
+        };
+        AudioStats {
+            duration_ms: self.format.samples_to_millis(self.samples),
+            speech_ms: self.format.samples_to_millis(self.speech_samples),
+            leading_silence_ms: self
+                .format
+                .samples_to_millis(self.leading_silence.unwrap_or(self.samples)),
+            peak_dbfs: to_dbfs(self.peak as f64),
+            rms_dbfs: to_dbfs(rms),
+            clipping_percent,
+        }
+    }
+}
+
+fn to_dbfs(level: f64) -> f64 {
+    if level <= 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    20.0 * (level / i16::MAX as f64).log10()
+}
@@ -62,7 +62,7 @@ pub fn get_param(engine: *const uni::mrcp_engine_t, key: &[u8]) -> crate::Result
         let raw_value = uni::mrcp_engine_param_get(engine, name);
         if raw_value.is_null() {
             return Err(crate::Error::NoSuchEngineParam(
-                std::ffi::CString::from_raw(name),
+                std::ffi::CStr::from_ptr(name).to_owned(),
             ));
         }
         Ok(std::ffi::CStr::from_ptr(raw_value)
@@ -70,3 +70,19 @@ pub fn get_param(engine: *const uni::mrcp_engine_t, key: &[u8]) -> crate::Result
             .map(ToOwned::to_owned)?)
     }
 }
+
+/// Parses the param value, `default` is returned if the param is absent.
+pub fn get_param_or<T: std::str::FromStr>(
+    engine: *const uni::mrcp_engine_t,
+    key: &[u8],
+    default: T,
+) -> crate::Result<T> {
+    match get_param(engine, key) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map_err(|_| crate::Error::InvalidParamValue(value)),
+        Err(crate::Error::NoSuchEngineParam(_)) => Ok(default),
+        Err(e) => Err(e),
+    }
+}
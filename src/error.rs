@@ -12,8 +12,25 @@ pub enum Error {
 
     // -- Internals
     NoSuchEngineParam(std::ffi::CString),
+    InvalidParamValue(String),
+    InvalidLanguageTag(String),
     NoSuchHeader(u32),
+    InvalidHeaderValue(u32),
     NullRequest,
+    VendorParams(Vec<crate::headers::VendorParamError>),
+    NullCodecDescriptor,
+    UnsupportedFrameDuration(usize),
+    AudioQueueOverflow,
+    InvalidDtmfDigit(char),
+    InvalidContentType(String),
+    UnsupportedContentType(String),
+    UnsupportedCharset(String),
+    InvalidText(String),
+    InvalidMultipart(String),
+    InvalidLexicon(String),
+    InvalidAudio(String),
+    FetchFailure(String),
+    Ssml(crate::ssml::ParseError),
 }
 
 impl core::fmt::Display for Error {
@@ -0,0 +1,150 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+/// Decodes text in the given charset, UTF-8 if none is given.
+/// A byte order mark overrides the charset.
+pub fn decode(bytes: &[u8], charset: Option<&str>) -> crate::Result<String> {
+    if let Some(rest) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        return Ok(std::str::from_utf8(rest)?.to_owned());
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xFF\xFE") {
+        return decode_utf16(rest, u16::from_le_bytes, "utf-16le");
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xFE\xFF") {
+        return decode_utf16(rest, u16::from_be_bytes, "utf-16be");
+    }
+    let charset = charset.map(|c| c.trim().to_ascii_lowercase());
+    match charset.as_deref() {
+        None | Some("utf-8" | "utf8") => Ok(std::str::from_utf8(bytes)?.to_owned()),
+        Some("us-ascii" | "ascii") => {
+            if bytes.is_ascii() {
+                Ok(bytes.iter().map(|&b| b as char).collect())
+            } else {
+                Err(crate::Error::InvalidText("us-ascii".to_owned()))
+            }
+        }
+        Some("iso-8859-1" | "latin1" | "latin-1") => Ok(bytes.iter().map(|&b| b as char).collect()),
+        Some("windows-1251" | "cp1251") => Ok(decode_single_byte(bytes, |b| match b {
+            0x80..=0xBF => WINDOWS_1251[b as usize - 0x80],
+            _ => 0x0410 + (b as u16 - 0xC0),
+        })),
+        Some("koi8-r" | "koi8r") => Ok(decode_single_byte(bytes, |b| match b {
+            0x80..=0xBF => KOI8_R[b as usize - 0x80],
+            0xC0..=0xDF => KOI8_R_LETTERS[b as usize - 0xC0],
+            _ => KOI8_R_LETTERS[b as usize - 0xE0] - 0x20,
+        })),
+        Some("utf-16le") => decode_utf16(bytes, u16::from_le_bytes, "utf-16le"),
+        Some("utf-16be" | "utf-16") => decode_utf16(bytes, u16::from_be_bytes, "utf-16be"),
+        Some(other) => Err(crate::Error::UnsupportedCharset(other.to_owned())),
+    }
+}
+
+fn decode_single_byte(bytes: &[u8], high: impl Fn(u8) -> u16) -> String {
+    bytes
+        .iter()
+        .map(|&b| {
+            if b < 0x80 {
+                b as char
+            } else {
+                char::from_u32(high(b) as u32).unwrap_or(char::REPLACEMENT_CHARACTER)
+            }
+        })
+        .collect()
+}
+
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16, charset: &str) -> crate::Result<String> {
+    let pairs = bytes.chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return Err(crate::Error::InvalidText(charset.to_owned()));
+    }
+    let units = pairs.map(|pair| unit([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map_err(|_| crate::Error::InvalidText(charset.to_owned()))
+}
+
+const WINDOWS_1251: [u16; 64] = [
+    0x0402, 0x0403, 0x201A, 0x0453, 0x201E, 0x2026, 0x2020, 0x2021, 0x20AC, 0x2030, 0x0409, 0x2039,
+    0x040A, 0x040C, 0x040B, 0x040F, 0x0452, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014,
+    0xFFFD, 0x2122, 0x0459, 0x203A, 0x045A, 0x045C, 0x045B, 0x045F, 0x00A0, 0x040E, 0x045E, 0x0408,
+    0x00A4, 0x0490, 0x00A6, 0x00A7, 0x0401, 0x00A9, 0x0404, 0x00AB, 0x00AC, 0x00AD, 0x00AE, 0x0407,
+    0x00B0, 0x00B1, 0x0406, 0x0456, 0x0491, 0x00B5, 0x00B6, 0x00B7, 0x0451, 0x2116, 0x0454, 0x00BB,
+    0x0458, 0x0405, 0x0455, 0x0457,
+];
+
+const KOI8_R: [u16; 64] = [
+    0x2500, 0x2502, 0x250C, 0x2510, 0x2514, 0x2518, 0x251C, 0x2524, 0x252C, 0x2534, 0x253C, 0x2580,
+    0x2584, 0x2588, 0x258C, 0x2590, 0x2591, 0x2592, 0x2593, 0x2320, 0x25A0, 0x2219, 0x221A, 0x2248,
+    0x2264, 0x2265, 0x00A0, 0x2321, 0x00B0, 0x00B2, 0x00B7, 0x00F7, 0x2550, 0x2551, 0x2552, 0x0451,
+    0x2553, 0x2554, 0x2555, 0x2556, 0x2557, 0x2558, 0x2559, 0x255A, 0x255B, 0x255C, 0x255D, 0x255E,
+    0x255F, 0x2560, 0x2561, 0x0401, 0x2562, 0x2563, 0x2564, 0x2565, 0x2566, 0x2567, 0x2568, 0x2569,
+    0x256A, 0x256B, 0x256C, 0x00A9,
+];
+
+/// Lowercase letters at 0xC0..=0xDF, uppercase ones follow at 0xE0..=0xFF.
+const KOI8_R_LETTERS: [u16; 32] = [
+    0x044E, 0x0430, 0x0431, 0x0446, 0x0434, 0x0435, 0x0444, 0x0433, 0x0445, 0x0438, 0x0439, 0x043A,
+    0x043B, 0x043C, 0x043D, 0x043E, 0x043F, 0x044F, 0x0440, 0x0441, 0x0442, 0x0443, 0x0436, 0x0432,
+    0x044C, 0x044B, 0x0437, 0x0448, 0x044D, 0x0449, 0x0447, 0x044A,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_order_marks() {
+        assert_eq!(decode(b"\xEF\xBB\xBFok", Some("koi8-r")).unwrap(), "ok");
+        assert_eq!(decode(b"\xFF\xFEo\0k\0", None).unwrap(), "ok");
+        assert_eq!(decode(b"\xFE\xFF\0o\0k", Some("utf-8")).unwrap(), "ok");
+    }
+
+    #[test]
+    fn cyrillic_single_byte() {
+        let cp1251 = b"\xCF\xF0\xE8\xE2\xE5\xF2, \xB8\xE6";
+        assert_eq!(decode(cp1251, Some("windows-1251")).unwrap(), "Привет, ёж");
+        assert_eq!(decode(cp1251, Some("CP1251")).unwrap(), "Привет, ёж");
+        let koi8 = b"\xF0\xD2\xC9\xD7\xC5\xD4, \xA3\xD6";
+        assert_eq!(decode(koi8, Some("KOI8-R")).unwrap(), "Привет, ёж");
+    }
+
+    #[test]
+    fn utf16() {
+        assert_eq!(decode(b"\x1F\x04\x40\x04", Some("utf-16le")).unwrap(), "Пр");
+        assert_eq!(decode(b"\x04\x1F\x04\x40", Some("utf-16be")).unwrap(), "Пр");
+        assert!(matches!(
+            decode(b"\x1F\x04\x40", Some("utf-16le")),
+            Err(crate::Error::InvalidText(_))
+        ));
+    }
+
+    #[test]
+    fn utf8_and_ascii() {
+        assert_eq!(decode("ёж".as_bytes(), None).unwrap(), "ёж");
+        assert!(decode(b"\xFF", None).is_err());
+        assert!(matches!(
+            decode(b"\xE9", Some("us-ascii")),
+            Err(crate::Error::InvalidText(_))
+        ));
+        assert_eq!(decode(b"\xE9", Some("latin1")).unwrap(), "é");
+    }
+
+    #[test]
+    fn unknown_charset() {
+        assert!(matches!(
+            decode(b"text", Some("ebcdic")),
+            Err(crate::Error::UnsupportedCharset(charset)) if charset == "ebcdic"
+        ));
+    }
+}
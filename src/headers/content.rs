@@ -0,0 +1,296 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::{charset, Multipart};
+
+pub const TEXT_PLAIN: &str = "text/plain";
+pub const APPLICATION_SSML: &str = "application/ssml+xml";
+pub const TEXT_URI_LIST: &str = "text/uri-list";
+
+/// Value of the content-type header, the media type is kept in lowercase.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentType {
+    pub media_type: String,
+    pub params: Vec<(String, String)>,
+}
+
+impl ContentType {
+    pub fn new(media_type: &str) -> Self {
+        Self {
+            media_type: media_type.to_ascii_lowercase(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Parameter value by case-insensitive name.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn charset(&self) -> Option<&str> {
+        self.param("charset")
+    }
+
+    pub fn boundary(&self) -> Option<&str> {
+        self.param("boundary")
+    }
+
+    pub fn is_multipart(&self) -> bool {
+        self.media_type.starts_with("multipart/")
+    }
+}
+
+impl std::str::FromStr for ContentType {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        let mut parts = split_params(s).into_iter();
+        let media_type = parts.next().unwrap_or_default().trim();
+        if !media_type
+            .split_once('/')
+            .is_some_and(|(kind, subtype)| !kind.is_empty() && !subtype.is_empty())
+        {
+            return Err(crate::Error::InvalidContentType(s.to_owned()));
+        }
+        let mut content_type = Self::new(media_type);
+        for param in parts.map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, value) = param
+                .split_once('=')
+                .ok_or_else(|| crate::Error::InvalidContentType(s.to_owned()))?;
+            content_type
+                .params
+                .push((name.trim().to_ascii_lowercase(), unquote(value.trim())));
+        }
+        Ok(content_type)
+    }
+}
+
+impl std::fmt::Display for ContentType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.media_type)?;
+        for (name, value) in &self.params {
+            if !value.is_empty() && !value.contains(|c: char| TSPECIALS.contains(c) || c <= ' ') {
+                write!(f, "; {name}={value}")?;
+                continue;
+            }
+            write!(f, "; {name}=\"")?;
+            for c in value.chars() {
+                if matches!(c, '"' | '\\') {
+                    write!(f, "\\")?;
+                }
+                write!(f, "{c}")?;
+            }
+            write!(f, "\"")?;
+        }
+        Ok(())
+    }
+}
+
+/// Characters that make a parameter value quoted, RFC 2045.
+const TSPECIALS: &str = "()<>@,;:\\\"/[]?=";
+
+/// Splits at the semicolons outside of quoted strings.
+fn split_params(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut start, mut quoted, mut escaped) = (0, false, false);
+    for (index, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                parts.push(&s[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// Value of a parameter with the quotes and backslash escapes removed.
+fn unquote(value: &str) -> String {
+    let Some(inner) = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    else {
+        return value.to_owned();
+    };
+    let mut unquoted = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.extend(chars.next()),
+            c => unquoted.push(c),
+        }
+    }
+    unquoted
+}
+
+/// Body of a SPEAK request by its content type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpeakContent {
+    PlainText(String),
+    Ssml(String),
+    UriList(Vec<String>),
+    Multipart(Multipart),
+}
+
+impl SpeakContent {
+    /// Decodes the body according to the content type.
+    /// A body without content type is taken as SSML if it looks like XML and as plain text otherwise.
+    pub fn decode(content_type: Option<&ContentType>, body: &[u8]) -> crate::Result<Self> {
+        let charset = content_type.and_then(ContentType::charset);
+        let Some(content_type) = content_type else {
+            let text = charset::decode(body, None)?;
+            return Ok(if text.trim_start().starts_with('<') {
+                SpeakContent::Ssml(text)
+            } else {
+                SpeakContent::PlainText(text)
+            });
+        };
+        match content_type.media_type.as_str() {
+            TEXT_PLAIN => Ok(SpeakContent::PlainText(charset::decode(body, charset)?)),
+            APPLICATION_SSML | "application/xml" | "text/xml" => Ok(SpeakContent::Ssml(
+                charset::decode(body, charset.or(xml_encoding(body)))?,
+            )),
+            TEXT_URI_LIST => Ok(SpeakContent::UriList(
+                charset::decode(body, charset)?
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(ToOwned::to_owned)
+                    .collect(),
+            )),
+            _ if content_type.is_multipart() => Ok(SpeakContent::Multipart(Multipart::parse(
+                content_type,
+                body,
+            )?)),
+            other => Err(crate::Error::UnsupportedContentType(other.to_owned())),
+        }
+    }
+
+    /// Text of plain text and SSML content.
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            SpeakContent::PlainText(text) | SpeakContent::Ssml(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Text to be spoken, SSML is parsed and rendered as plain text.
+    /// Multipart content is spoken by its root part.
+    pub fn plain_text(&self) -> crate::Result<Option<String>> {
+        match self {
+            SpeakContent::PlainText(text) => Ok(Some(text.clone())),
+            SpeakContent::Ssml(ssml) => Ok(Some(crate::ssml::Document::parse(ssml)?.plain_text())),
+            SpeakContent::Multipart(multipart) => match multipart.root() {
+                Some(root) => root.content()?.plain_text(),
+                None => Ok(None),
+            },
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Encoding from the XML declaration, e.g. `<?xml version="1.0" encoding="windows-1251"?>`.
+fn xml_encoding(body: &[u8]) -> Option<&str> {
+    let declaration = body.strip_prefix(b"<?xml")?;
+    let end = declaration.windows(2).position(|w| w == b"?>")?;
+    let declaration = std::str::from_utf8(&declaration[..end]).ok()?;
+    let (_, rest) = declaration.split_once("encoding")?;
+    let rest = rest.trim_start().strip_prefix('=')?.trim_start();
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    rest[1..].split(quote).next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parameters() {
+        let content_type: ContentType = "Text/Plain; Charset=\"UTF-8\"".parse().unwrap();
+        assert_eq!(content_type.media_type, "text/plain");
+        assert_eq!(content_type.charset(), Some("UTF-8"));
+        assert!("text".parse::<ContentType>().is_err());
+        assert!("text/plain; charset".parse::<ContentType>().is_err());
+    }
+
+    #[test]
+    fn quoted_values_are_kept_whole() {
+        let content_type: ContentType = "multipart/mixed; boundary=\"a;b=c\"; start=x"
+            .parse()
+            .unwrap();
+        assert_eq!(content_type.boundary(), Some("a;b=c"));
+        assert_eq!(content_type.param("start"), Some("x"));
+        let escaped: ContentType = r#"multipart/mixed; boundary="a\"b\\c""#.parse().unwrap();
+        assert_eq!(escaped.boundary(), Some(r#"a"b\c"#));
+        for content_type in [content_type, escaped] {
+            assert_eq!(
+                content_type.to_string().parse::<ContentType>().unwrap(),
+                content_type
+            );
+        }
+    }
+
+    #[test]
+    fn xml_encoding_sniffing() {
+        assert_eq!(
+            xml_encoding(b"<?xml version=\"1.0\" encoding=\"koi8-r\"?><speak/>"),
+            Some("koi8-r")
+        );
+        assert_eq!(
+            xml_encoding(b"<?xml version='1.0' encoding = 'windows-1251' ?>"),
+            Some("windows-1251")
+        );
+        assert_eq!(xml_encoding(b"<?xml version=\"1.0\"?><speak/>"), None);
+        assert_eq!(xml_encoding(b"<speak encoding=\"koi8-r\"/>"), None);
+    }
+
+    #[test]
+    fn speak_content_by_type() {
+        let ssml = ContentType::new(APPLICATION_SSML);
+        let body = b"<?xml version=\"1.0\" encoding=\"windows-1251\"?><speak>\xCF\xF0\xE8\xE2\xE5\xF2</speak>";
+        let SpeakContent::Ssml(text) = SpeakContent::decode(Some(&ssml), body).unwrap() else {
+            panic!("expected SSML");
+        };
+        assert!(text.ends_with("<speak>Привет</speak>"));
+        let uri_list = ContentType::new(TEXT_URI_LIST);
+        assert_eq!(
+            SpeakContent::decode(
+                Some(&uri_list),
+                b"# prompts\nfile:a.wav\n\nhttp://h/b.wav\n"
+            )
+            .unwrap(),
+            SpeakContent::UriList(vec!["file:a.wav".to_owned(), "http://h/b.wav".to_owned()])
+        );
+        assert_eq!(
+            SpeakContent::decode(None, b"  <speak/>").unwrap(),
+            SpeakContent::Ssml("  <speak/>".to_owned())
+        );
+        assert_eq!(
+            SpeakContent::decode(None, b"hello").unwrap(),
+            SpeakContent::PlainText("hello".to_owned())
+        );
+        assert!(matches!(
+            SpeakContent::decode(Some(&ContentType::new("image/png")), b""),
+            Err(crate::Error::UnsupportedContentType(_))
+        ));
+    }
+}
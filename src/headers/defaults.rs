@@ -0,0 +1,129 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::SpeechCompletePolicy;
+use crate::{engine::get_param_or, uni};
+
+/// Values used by `RecogHeaders` when a header is absent in the request.
+#[derive(Debug, Clone)]
+pub struct RecogDefaults {
+    pub confidence_threshold: f64,
+    pub sensitivity: f64,
+    pub speed_vs_accuracy: f64,
+    pub n_best_list_length: usize,
+    pub noinput_timeout: usize,
+    pub recognition_timeout: usize,
+    pub start_input_timers: bool,
+    pub silence_timeout: usize,
+    pub speech_incomplete_timeout: usize,
+    pub dtmf_interdigit_timeout: usize,
+    pub dtmf_term_timeout: usize,
+    pub speech_complete_policy: SpeechCompletePolicy,
+}
+
+impl Default for RecogDefaults {
+    fn default() -> Self {
+        Self {
+            confidence_threshold: 0.5,
+            sensitivity: 0.6,
+            speed_vs_accuracy: 0.5,
+            n_best_list_length: 1,
+            noinput_timeout: 5000,
+            recognition_timeout: 20000,
+            start_input_timers: true,
+            silence_timeout: 1000,
+            speech_incomplete_timeout: 3000,
+            dtmf_interdigit_timeout: 5000,
+            dtmf_term_timeout: 10000,
+            speech_complete_policy: SpeechCompletePolicy::default(),
+        }
+    }
+}
+
+impl RecogDefaults {
+    /// Overrides the built-in defaults with `default-*` engine params.
+    pub fn from_engine(engine: *const uni::mrcp_engine_t) -> crate::Result<Self> {
+        let builtin = Self::default();
+        Ok(Self {
+            confidence_threshold: get_param_or(
+                engine,
+                b"default-confidence-threshold\0",
+                builtin.confidence_threshold,
+            )?,
+            sensitivity: get_param_or(engine, b"default-sensitivity\0", builtin.sensitivity)?,
+            speed_vs_accuracy: get_param_or(
+                engine,
+                b"default-speed-vs-accuracy\0",
+                builtin.speed_vs_accuracy,
+            )?,
+            n_best_list_length: get_param_or(
+                engine,
+                b"default-n-best-list-length\0",
+                builtin.n_best_list_length,
+            )?,
+            noinput_timeout: get_param_or(
+                engine,
+                b"default-noinput-timeout\0",
+                builtin.noinput_timeout,
+            )?,
+            recognition_timeout: get_param_or(
+                engine,
+                b"default-recognition-timeout\0",
+                builtin.recognition_timeout,
+            )?,
+            start_input_timers: get_param_or(
+                engine,
+                b"default-start-input-timers\0",
+                builtin.start_input_timers,
+            )?,
+            silence_timeout: get_param_or(
+                engine,
+                b"default-speech-complete-timeout\0",
+                builtin.silence_timeout,
+            )?,
+            speech_incomplete_timeout: get_param_or(
+                engine,
+                b"default-speech-incomplete-timeout\0",
+                builtin.speech_incomplete_timeout,
+            )?,
+            dtmf_interdigit_timeout: get_param_or(
+                engine,
+                b"default-dtmf-interdigit-timeout\0",
+                builtin.dtmf_interdigit_timeout,
+            )?,
+            dtmf_term_timeout: get_param_or(
+                engine,
+                b"default-dtmf-term-timeout\0",
+                builtin.dtmf_term_timeout,
+            )?,
+            speech_complete_policy: SpeechCompletePolicy::from_engine(engine)?,
+        })
+    }
+}
+
+/// Values used by `SynthHeaders` when a header is absent in the request.
+#[derive(Debug, Clone, Default)]
+pub struct SynthDefaults {
+    pub voice_name: String,
+}
+
+impl SynthDefaults {
+    /// Overrides the built-in defaults with `default-*` engine params.
+    pub fn from_engine(engine: *const uni::mrcp_engine_t) -> crate::Result<Self> {
+        let builtin = Self::default();
+        Ok(Self {
+            voice_name: get_param_or(engine, b"default-voice\0", builtin.voice_name)?,
+        })
+    }
+}
@@ -0,0 +1,320 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+use super::{assign, RecogParams, SynthParams, VendorParams};
+use crate::{
+    inline_mrcp_generic_header_property_check, inline_mrcp_resource_header_prepare,
+    inline_mrcp_resource_header_property_add, inline_mrcp_resource_header_property_check, uni,
+};
+
+const RECOG_PARAMS: &[u32] = &[
+    uni::RECOGNIZER_HEADER_CONFIDENCE_THRESHOLD,
+    uni::RECOGNIZER_HEADER_SENSITIVITY_LEVEL,
+    uni::RECOGNIZER_HEADER_SPEED_VS_ACCURACY,
+    uni::RECOGNIZER_HEADER_N_BEST_LIST_LENGTH,
+    uni::RECOGNIZER_HEADER_NO_INPUT_TIMEOUT,
+    uni::RECOGNIZER_HEADER_RECOGNITION_TIMEOUT,
+    uni::RECOGNIZER_HEADER_SPEECH_COMPLETE_TIMEOUT,
+    uni::RECOGNIZER_HEADER_SPEECH_INCOMPLETE_TIMEOUT,
+    uni::RECOGNIZER_HEADER_DTMF_INTERDIGIT_TIMEOUT,
+    uni::RECOGNIZER_HEADER_DTMF_TERM_TIMEOUT,
+    uni::RECOGNIZER_HEADER_DTMF_TERM_CHAR,
+    uni::RECOGNIZER_HEADER_SPEECH_LANGUAGE,
+    uni::RECOGNIZER_HEADER_RECOGNITION_MODE,
+    uni::RECOGNIZER_HEADER_SAVE_WAVEFORM,
+    uni::RECOGNIZER_HEADER_MEDIA_TYPE,
+    uni::RECOGNIZER_HEADER_HOTWORD_MIN_DURATION,
+    uni::RECOGNIZER_HEADER_HOTWORD_MAX_DURATION,
+    uni::RECOGNIZER_HEADER_EARLY_NO_MATCH,
+];
+
+const SYNTH_PARAMS: &[u32] = &[
+    uni::SYNTHESIZER_HEADER_VOICE_NAME,
+    uni::SYNTHESIZER_HEADER_VOICE_GENDER,
+    uni::SYNTHESIZER_HEADER_VOICE_AGE,
+    uni::SYNTHESIZER_HEADER_VOICE_VARIANT,
+    uni::SYNTHESIZER_HEADER_PROSODY_VOLUME,
+    uni::SYNTHESIZER_HEADER_PROSODY_RATE,
+    uni::SYNTHESIZER_HEADER_SPEECH_LANGUAGE,
+    uni::SYNTHESIZER_HEADER_KILL_ON_BARGE_IN,
+    uni::SYNTHESIZER_HEADER_SPEAKER_PROFILE,
+    uni::SYNTHESIZER_HEADER_LOAD_LEXICON,
+    uni::SYNTHESIZER_HEADER_LEXICON_SEARCH_ORDER,
+    uni::SYNTHESIZER_HEADER_AUDIO_FETCH_HINT,
+];
+
+impl RecogParams {
+    /// Builds the response to GET-PARAMS with the requested params of the channel.
+    /// If the request names no params all of them are reported. The response fails
+    /// with method-failed if the vendor-specific params can not be written.
+    pub fn get_params_response(
+        &self,
+        request: *const uni::mrcp_message_t,
+    ) -> *mut uni::mrcp_message_t {
+        if request.is_null() {
+            return std::ptr::null_mut();
+        }
+        let headers = self.channel_values();
+        unsafe {
+            let response = uni::mrcp_response_create(request, (*request).pool);
+            if response.is_null() {
+                return response;
+            }
+            let recog_header =
+                inline_mrcp_resource_header_prepare(response) as *mut uni::mrcp_recog_header_t;
+            if recog_header.is_null() {
+                return response;
+            }
+            let pool = (*response).pool;
+            let report = Report::new(request, response, RECOG_PARAMS);
+            report.set(uni::RECOGNIZER_HEADER_CONFIDENCE_THRESHOLD, || {
+                (*recog_header).confidence_threshold = headers.confidence_threshold() as _
+            });
+            report.set(uni::RECOGNIZER_HEADER_SENSITIVITY_LEVEL, || {
+                (*recog_header).sensitivity_level = headers.sensitivity() as _
+            });
+            report.set(uni::RECOGNIZER_HEADER_SPEED_VS_ACCURACY, || {
+                (*recog_header).speed_vs_accuracy = headers.speed_vs_accuracy() as _
+            });
+            report.set(uni::RECOGNIZER_HEADER_N_BEST_LIST_LENGTH, || {
+                (*recog_header).n_best_list_length = headers.n_best_list_length()
+            });
+            report.set(uni::RECOGNIZER_HEADER_NO_INPUT_TIMEOUT, || {
+                (*recog_header).no_input_timeout = headers.noinput_timeout()
+            });
+            report.set(uni::RECOGNIZER_HEADER_RECOGNITION_TIMEOUT, || {
+                (*recog_header).recognition_timeout = headers.recognition_timeout()
+            });
+            report.set(uni::RECOGNIZER_HEADER_SPEECH_COMPLETE_TIMEOUT, || {
+                (*recog_header).speech_complete_timeout = *headers
+                    .speech_complete_timeout
+                    .as_ref()
+                    .unwrap_or(&headers.defaults().silence_timeout)
+            });
+            report.set(uni::RECOGNIZER_HEADER_SPEECH_INCOMPLETE_TIMEOUT, || {
+                (*recog_header).speech_incomplete_timeout = headers.speech_incomplete_timeout()
+            });
+            report.set(uni::RECOGNIZER_HEADER_DTMF_INTERDIGIT_TIMEOUT, || {
+                (*recog_header).dtmf_interdigit_timeout = headers.dtmf_interdigit_timeout()
+            });
+            report.set(uni::RECOGNIZER_HEADER_DTMF_TERM_TIMEOUT, || {
+                (*recog_header).dtmf_term_timeout = headers.dtmf_term_timeout()
+            });
+            if let Ok(term_char) = headers.dtmf_term_char {
+                report.set(uni::RECOGNIZER_HEADER_DTMF_TERM_CHAR, || {
+                    (*recog_header).dtmf_term_char = term_char as u8 as _
+                });
+            }
+            if let Ok(language) = &headers.speech_language {
+                report.set(uni::RECOGNIZER_HEADER_SPEECH_LANGUAGE, || {
+                    assign(
+                        &mut (*recog_header).speech_language,
+                        &language.to_string(),
+                        pool,
+                    )
+                });
+            }
+            if let Ok(mode) = &headers.recognition_mode {
+                report.set(uni::RECOGNIZER_HEADER_RECOGNITION_MODE, || {
+                    assign(&mut (*recog_header).recognition_mode, mode, pool)
+                });
+            }
+            if let Ok(save_waveform) = headers.save_waveform {
+                report.set(uni::RECOGNIZER_HEADER_SAVE_WAVEFORM, || {
+                    (*recog_header).save_waveform = save_waveform as _
+                });
+            }
+            if let Ok(media_type) = &headers.media_type {
+                report.set(uni::RECOGNIZER_HEADER_MEDIA_TYPE, || {
+                    assign(&mut (*recog_header).media_type, media_type, pool)
+                });
+            }
+            if let Ok(duration) = headers.hotword_min_duration {
+                report.set(uni::RECOGNIZER_HEADER_HOTWORD_MIN_DURATION, || {
+                    (*recog_header).hotword_min_duration = duration
+                });
+            }
+            if let Ok(duration) = headers.hotword_max_duration {
+                report.set(uni::RECOGNIZER_HEADER_HOTWORD_MAX_DURATION, || {
+                    (*recog_header).hotword_max_duration = duration
+                });
+            }
+            if let Ok(early_no_match) = headers.early_no_match {
+                report.set(uni::RECOGNIZER_HEADER_EARLY_NO_MATCH, || {
+                    (*recog_header).early_no_match = early_no_match as _
+                });
+            }
+            if report.vendor_specific(&headers.vendor_specific).is_err() {
+                (*response).start_line.status_code = uni::MRCP_STATUS_CODE_METHOD_FAILED as _;
+            }
+            response
+        }
+    }
+}
+
+impl SynthParams {
+    /// Builds the response to GET-PARAMS with the requested params of the channel.
+    /// If the request names no params all of them are reported. The response fails
+    /// with method-failed if the vendor-specific params can not be written.
+    pub fn get_params_response(
+        &self,
+        request: *const uni::mrcp_message_t,
+    ) -> *mut uni::mrcp_message_t {
+        if request.is_null() {
+            return std::ptr::null_mut();
+        }
+        let headers = self.channel_values();
+        unsafe {
+            let response = uni::mrcp_response_create(request, (*request).pool);
+            if response.is_null() {
+                return response;
+            }
+            let synth_header =
+                inline_mrcp_resource_header_prepare(response) as *mut uni::mrcp_synth_header_t;
+            if synth_header.is_null() {
+                return response;
+            }
+            let pool = (*response).pool;
+            let report = Report::new(request, response, SYNTH_PARAMS);
+            if !headers.voice_name().is_empty() {
+                report.set(uni::SYNTHESIZER_HEADER_VOICE_NAME, || {
+                    assign(
+                        &mut (*synth_header).voice_param.name,
+                        headers.voice_name(),
+                        pool,
+                    )
+                });
+            }
+            if let Ok(gender) = headers.voice_gender {
+                report.set(uni::SYNTHESIZER_HEADER_VOICE_GENDER, || {
+                    (*synth_header).voice_param.gender = gender.to_raw()
+                });
+            }
+            if let Ok(age) = headers.voice_age {
+                report.set(uni::SYNTHESIZER_HEADER_VOICE_AGE, || {
+                    (*synth_header).voice_param.age = age
+                });
+            }
+            if let Ok(variant) = headers.voice_variant {
+                report.set(uni::SYNTHESIZER_HEADER_VOICE_VARIANT, || {
+                    (*synth_header).voice_param.variant = variant
+                });
+            }
+            if let Ok(volume) = headers.prosody_volume {
+                report.set(uni::SYNTHESIZER_HEADER_PROSODY_VOLUME, || {
+                    (*synth_header).prosody_param.volume = volume.to_raw()
+                });
+            }
+            if let Ok(rate) = headers.prosody_rate {
+                report.set(uni::SYNTHESIZER_HEADER_PROSODY_RATE, || {
+                    (*synth_header).prosody_param.rate = rate.to_raw()
+                });
+            }
+            if let Ok(language) = &headers.speech_language {
+                report.set(uni::SYNTHESIZER_HEADER_SPEECH_LANGUAGE, || {
+                    assign(
+                        &mut (*synth_header).speech_language,
+                        &language.to_string(),
+                        pool,
+                    )
+                });
+            }
+            report.set(uni::SYNTHESIZER_HEADER_KILL_ON_BARGE_IN, || {
+                (*synth_header).kill_on_barge_in = headers.kill_on_barge_in() as _
+            });
+            if let Ok(profile) = &headers.speaker_profile {
+                report.set(uni::SYNTHESIZER_HEADER_SPEAKER_PROFILE, || {
+                    assign(&mut (*synth_header).speaker_profile, profile, pool)
+                });
+            }
+            if let Ok(load_lexicon) = headers.load_lexicon {
+                report.set(uni::SYNTHESIZER_HEADER_LOAD_LEXICON, || {
+                    (*synth_header).load_lexicon = load_lexicon as _
+                });
+            }
+            if let Ok(order) = &headers.lexicon_search_order {
+                report.set(uni::SYNTHESIZER_HEADER_LEXICON_SEARCH_ORDER, || {
+                    assign(&mut (*synth_header).lexicon_search_order, order, pool)
+                });
+            }
+            if let Ok(hint) = &headers.audio_fetch_hint {
+                report.set(uni::SYNTHESIZER_HEADER_AUDIO_FETCH_HINT, || {
+                    assign(&mut (*synth_header).audio_fetch_hint, hint, pool)
+                });
+            }
+            if report.vendor_specific(&headers.vendor_specific).is_err() {
+                (*response).start_line.status_code = uni::MRCP_STATUS_CODE_METHOD_FAILED as _;
+            }
+            response
+        }
+    }
+}
+
+struct Report {
+    request: *const uni::mrcp_message_t,
+    response: *mut uni::mrcp_message_t,
+    all: bool,
+}
+
+impl Report {
+    unsafe fn new(
+        request: *const uni::mrcp_message_t,
+        response: *mut uni::mrcp_message_t,
+        params: &[u32],
+    ) -> Self {
+        let all = !params
+            .iter()
+            .any(|&id| inline_mrcp_resource_header_property_check(request, id as _) == uni::TRUE)
+            && inline_mrcp_generic_header_property_check(
+                request,
+                uni::GENERIC_HEADER_VENDOR_SPECIFIC_PARAMS as _,
+            ) != uni::TRUE;
+        Self {
+            request,
+            response,
+            all,
+        }
+    }
+
+    unsafe fn set(&self, id: u32, write: impl FnOnce()) {
+        if self.all
+            || inline_mrcp_resource_header_property_check(self.request, id as _) == uni::TRUE
+        {
+            write();
+            inline_mrcp_resource_header_property_add(self.response, id as _);
+        }
+    }
+
+    /// Reports the requested vendor-specific params, or all of them if none is named.
+    /// Fails if the header can not be written to the response.
+    unsafe fn vendor_specific(&self, params: &VendorParams) -> crate::Result<()> {
+        let requested = if inline_mrcp_generic_header_property_check(
+            self.request,
+            uni::GENERIC_HEADER_VENDOR_SPECIFIC_PARAMS as _,
+        ) == uni::TRUE
+        {
+            let names = super::extract_vendor_specific_parameters(self.request);
+            params
+                .iter()
+                .filter(|(name, _)| names.contains_key(name))
+                .collect::<VendorParams>()
+        } else if self.all {
+            params.clone()
+        } else {
+            return Ok(());
+        };
+        requested.apply(self.response)
+    }
+}
@@ -12,22 +12,57 @@
 //    See the License for the specific language governing permissions and
 //    limitations under the License.
 
-use crate::{inline_mrcp_generic_header_get, inline_mrcp_generic_header_property_check, uni};
-use std::collections::HashMap;
+use crate::{
+    inline_apt_string_assign_n, inline_mrcp_generic_header_get,
+    inline_mrcp_generic_header_property_check, uni,
+};
 
 mod synth;
 pub use synth::SynthHeaders;
 
+mod charset;
+pub use charset::decode as decode_text;
+
+mod content;
+pub use content::{ContentType, SpeakContent, APPLICATION_SSML, TEXT_PLAIN, TEXT_URI_LIST};
+
+mod multipart;
+pub(crate) use multipart::strip_id;
+pub use multipart::{Multipart, Part};
+
+mod prosody;
+pub use prosody::{ProsodyRate, ProsodyVolume, RateLabel, VolumeLabel};
+
+mod values;
+pub use values::{SpeechLength, SpeechUnit, VoiceGender};
+
 mod recog;
 pub use recog::RecogHeaders;
 
-fn extract_vendor_specific_parameters(
-    request: *const uni::mrcp_message_t,
-) -> HashMap<String, String> {
+mod policy;
+pub use policy::SpeechCompletePolicy;
+
+mod defaults;
+pub use defaults::{RecogDefaults, SynthDefaults};
+
+mod params;
+pub use params::{ChannelParams, Headers, RecogParams, SynthParams};
+
+mod get_params;
+
+mod vendor;
+#[cfg(feature = "serde")]
+pub use vendor::from_vendor_params_serde;
+pub use vendor::{
+    from_vendor_params, FromVendorParams, SkippedParam, VendorNamespace, VendorParamError,
+    VendorParams,
+};
+
+fn extract_vendor_specific_parameters(request: *const uni::mrcp_message_t) -> VendorParams {
+    let mut params = VendorParams::new();
     if request.is_null() {
-        return HashMap::new();
+        return params;
     }
-    let mut params = HashMap::new();
     unsafe {
         if inline_mrcp_generic_header_property_check(
             request,
@@ -35,17 +70,17 @@ fn extract_vendor_specific_parameters(
         ) == uni::TRUE
         {
             let generic_header = inline_mrcp_generic_header_get(request);
-            if !generic_header.is_null() {
+            if !generic_header.is_null() && !(*generic_header).vendor_specific_params.is_null() {
                 let vendor_parameters = (*generic_header).vendor_specific_params;
                 let pairs = (*vendor_parameters).elts as *mut uni::apt_pair_t;
                 for offset in 0..(*vendor_parameters).nelts {
                     let pair = pairs.offset(offset as _) as *mut uni::apt_str_t;
                     let key = &*pair.offset(0);
                     let value = &*pair.offset(1);
-                    if let (Ok(key), Ok(value)) = (apt_str_to_string(key), apt_str_to_string(value))
-                    {
-                        params.insert(key, value);
-                    };
+                    match (apt_str_to_string(key), apt_str_to_string(value)) {
+                        (Ok(key), Ok(value)) => params.push(key, value),
+                        _ => params.push_skipped(apt_str_bytes(key), apt_str_bytes(value)),
+                    }
                 }
             }
         }
@@ -53,6 +88,18 @@ fn extract_vendor_specific_parameters(
     params
 }
 
+fn apt_str_bytes(origin: &uni::apt_str_t) -> &[u8] {
+    unsafe {
+        let ptr = origin.buf as *const u8;
+        let len = origin.length;
+        if len == 0 || ptr.is_null() {
+            &[]
+        } else {
+            std::slice::from_raw_parts(ptr, len)
+        }
+    }
+}
+
 fn apt_str_to_string(origin: &uni::apt_str_t) -> crate::Result<String> {
     unsafe {
         let ptr = origin.buf as *const u8;
@@ -65,3 +112,69 @@ fn apt_str_to_string(origin: &uni::apt_str_t) -> crate::Result<String> {
         }
     }
 }
+
+unsafe fn assign(str: &mut uni::apt_str_t, value: &str, pool: *mut uni::apr_pool_t) {
+    inline_apt_string_assign_n(str, value.as_ptr() as _, value.len(), pool);
+}
+
+fn extract_content_type(request: *const uni::mrcp_message_t) -> crate::Result<ContentType> {
+    extract_generic(request, uni::GENERIC_HEADER_CONTENT_TYPE, |header| {
+        apt_str_to_string(&header.content_type)?.parse()
+    })
+}
+
+fn extract_body(request: *const uni::mrcp_message_t) -> crate::Result<String> {
+    let content_type = extract_content_type(request).ok();
+    let body = extract_raw_body(request)?;
+    charset::decode(body, content_type.as_ref().and_then(ContentType::charset))
+}
+
+fn extract_content_id(request: *const uni::mrcp_message_t) -> crate::Result<String> {
+    extract_generic(request, uni::GENERIC_HEADER_CONTENT_ID, |header| {
+        apt_str_to_string(&header.content_id)
+    })
+}
+
+/// Parts of a multipart body, any other non-empty body is a single part.
+fn extract_parts(request: *const uni::mrcp_message_t) -> crate::Result<Vec<Part>> {
+    let content_type = match extract_content_type(request) {
+        Ok(content_type) => content_type,
+        Err(crate::Error::NoSuchHeader(_)) => ContentType::new(TEXT_PLAIN),
+        Err(err) => return Err(err),
+    };
+    let body = extract_raw_body(request)?;
+    if content_type.is_multipart() {
+        return Ok(Multipart::parse(&content_type, body)?.parts);
+    }
+    if body.is_empty() {
+        return Ok(Vec::new());
+    }
+    let content_id = extract_content_id(request).ok();
+    Ok(vec![Part::new(content_type, content_id, body.to_vec())])
+}
+
+fn extract_raw_body<'a>(request: *const uni::mrcp_message_t) -> crate::Result<&'a [u8]> {
+    if request.is_null() {
+        return Err(crate::Error::NullRequest);
+    }
+    unsafe { Ok(apt_str_bytes(&(*request).body)) }
+}
+
+fn extract_generic<T>(
+    request: *const uni::mrcp_message_t,
+    id: u32,
+    read: impl FnOnce(&uni::mrcp_generic_header_t) -> crate::Result<T>,
+) -> crate::Result<T> {
+    if request.is_null() {
+        return Err(crate::Error::NullRequest);
+    }
+    unsafe {
+        if inline_mrcp_generic_header_property_check(request, id as _) == uni::TRUE {
+            let generic_header = inline_mrcp_generic_header_get(request);
+            if !generic_header.is_null() {
+                return read(&*generic_header);
+            }
+        }
+    }
+    Err(crate::Error::NoSuchHeader(id))
+}
@@ -0,0 +1,322 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::{charset, ContentType, SpeakContent, TEXT_PLAIN};
+
+/// Body of a `multipart/*` message split into its parts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Multipart {
+    pub content_type: ContentType,
+    pub parts: Vec<Part>,
+}
+
+impl Multipart {
+    /// Splits the body by the boundary of the content type, the preamble and the epilogue are dropped.
+    pub fn parse(content_type: &ContentType, body: &[u8]) -> crate::Result<Self> {
+        let boundary = content_type
+            .boundary()
+            .filter(|boundary| content_type.is_multipart() && !boundary.is_empty())
+            .ok_or_else(|| crate::Error::InvalidContentType(content_type.to_string()))?;
+        let delimiter = format!("--{boundary}");
+        let invalid = |reason: &str| crate::Error::InvalidMultipart(reason.to_owned());
+        let (_, mut rest) =
+            split_delimiter(body, delimiter.as_bytes()).ok_or_else(|| invalid("no boundary"))?;
+        let mut parts = Vec::new();
+        while !rest.starts_with(b"--") {
+            let line_end = rest
+                .iter()
+                .position(|b| *b == b'\n')
+                .ok_or_else(|| invalid("no closing boundary"))?;
+            if !rest[..line_end].iter().all(u8::is_ascii_whitespace) {
+                return Err(invalid("garbage after boundary"));
+            }
+            let (part, next) = split_delimiter(&rest[line_end + 1..], delimiter.as_bytes())
+                .ok_or_else(|| invalid("no closing boundary"))?;
+            parts.push(Part::parse(part)?);
+            rest = next;
+        }
+        Ok(Self {
+            content_type: content_type.clone(),
+            parts,
+        })
+    }
+
+    /// Part by its Content-ID, the reference may be a `cid:` URI or in angle brackets.
+    pub fn get(&self, content_id: &str) -> Option<&Part> {
+        self.parts.iter().find(|part| part.has_id(content_id))
+    }
+
+    /// The part named by the `start` parameter of `multipart/related`, otherwise the first one.
+    pub fn root(&self) -> Option<&Part> {
+        match self.content_type.param("start") {
+            Some(start) => self.get(start),
+            None => self.parts.first(),
+        }
+    }
+}
+
+/// Body part with its own headers, the body is decoded from its transfer encoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Part {
+    /// Headers with names in lowercase.
+    pub headers: Vec<(String, String)>,
+    /// Content-Type of the part, `text/plain` if it has none.
+    pub content_type: ContentType,
+    /// Content-ID without angle brackets.
+    pub content_id: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl Part {
+    pub fn new(content_type: ContentType, content_id: Option<String>, body: Vec<u8>) -> Self {
+        Self {
+            headers: Vec::new(),
+            content_type,
+            content_id,
+            body,
+        }
+    }
+
+    fn parse(data: &[u8]) -> crate::Result<Self> {
+        let (head, body) = split_head(data);
+        let mut headers: Vec<(String, String)> = Vec::new();
+        for line in std::str::from_utf8(head)?.lines() {
+            if line.starts_with([' ', '\t']) {
+                if let Some((_, value)) = headers.last_mut() {
+                    value.push(' ');
+                    value.push_str(line.trim());
+                    continue;
+                }
+            }
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| crate::Error::InvalidMultipart(line.to_owned()))?;
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_owned()));
+        }
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(known, _)| known == name)
+                .map(|(_, value)| value.as_str())
+        };
+        let content_type = match header("content-type") {
+            Some(content_type) => content_type.parse()?,
+            None => ContentType::new(TEXT_PLAIN),
+        };
+        let content_id = header("content-id").map(|id| strip_id(id).to_owned());
+        let body = match header("content-transfer-encoding").map(str::to_ascii_lowercase) {
+            None => body.to_vec(),
+            Some(encoding) if matches!(encoding.as_str(), "7bit" | "8bit" | "binary") => {
+                body.to_vec()
+            }
+            Some(encoding) if encoding == "base64" => decode_base64(body)?,
+            Some(encoding) => return Err(crate::Error::InvalidMultipart(encoding)),
+        };
+        Ok(Self {
+            headers,
+            content_type,
+            content_id,
+            body,
+        })
+    }
+
+    /// Header value by case-insensitive name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether the Content-ID matches the reference, e.g. `cid:intro@example.com`
+    /// or `session:grammar@example.com`.
+    pub fn has_id(&self, reference: &str) -> bool {
+        self.content_id
+            .as_deref()
+            .is_some_and(|id| id == strip_id(reference))
+    }
+
+    /// Body decoded with the charset of the part.
+    pub fn text(&self) -> crate::Result<String> {
+        charset::decode(&self.body, self.content_type.charset())
+    }
+
+    /// Body decoded as SPEAK content by the content type of the part.
+    pub fn content(&self) -> crate::Result<SpeakContent> {
+        SpeakContent::decode(Some(&self.content_type), &self.body)
+    }
+}
+
+/// Content-ID of a reference such as `cid:id`, `<id>` or `<session:id>`.
+pub(crate) fn strip_id(reference: &str) -> &str {
+    let reference = reference.trim();
+    let reference = reference
+        .strip_prefix('<')
+        .and_then(|id| id.strip_suffix('>'))
+        .unwrap_or(reference);
+    reference
+        .strip_prefix("cid:")
+        .or_else(|| reference.strip_prefix("session:"))
+        .unwrap_or(reference)
+}
+
+/// Finds the delimiter at the start of a line and returns the data before the line break
+/// preceding it and the data after it.
+fn split_delimiter<'a>(data: &'a [u8], delimiter: &[u8]) -> Option<(&'a [u8], &'a [u8])> {
+    let mut from = 0;
+    while let Some(found) = data[from..]
+        .windows(delimiter.len())
+        .position(|window| window == delimiter)
+        .map(|position| from + position)
+    {
+        let rest = &data[found + delimiter.len()..];
+        let terminated = rest.starts_with(b"--")
+            || matches!(rest.first(), None | Some(b' ' | b'\t' | b'\r' | b'\n'));
+        let before = &data[..found];
+        let line_start = if found == 0 {
+            Some(before)
+        } else {
+            before
+                .strip_suffix(b"\n")
+                .map(|before| before.strip_suffix(b"\r").unwrap_or(before))
+        };
+        if let (Some(before), true) = (line_start, terminated) {
+            return Some((before, rest));
+        }
+        from = found + 1;
+    }
+    None
+}
+
+/// Splits the headers of a part from its body at the first empty line.
+fn split_head(data: &[u8]) -> (&[u8], &[u8]) {
+    if let Some(body) = data
+        .strip_prefix(b"\r\n")
+        .or_else(|| data.strip_prefix(b"\n"))
+    {
+        return (&[], body);
+    }
+    for (index, _) in data.iter().enumerate().filter(|(_, b)| **b == b'\n') {
+        let rest = &data[index + 1..];
+        if let Some(body) = rest
+            .strip_prefix(b"\r\n")
+            .or_else(|| rest.strip_prefix(b"\n"))
+        {
+            return (&data[..index + 1], body);
+        }
+    }
+    (data, &[])
+}
+
+fn decode_base64(data: &[u8]) -> crate::Result<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(data.len() / 4 * 3);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &byte in data.iter().filter(|b| !b.is_ascii_whitespace()) {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            _ => return Err(crate::Error::InvalidMultipart("invalid base64".to_owned())),
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &str = "preamble\r\n\
+        --a;b\r\n\
+        Content-Type: application/ssml+xml\r\n\
+        Content-ID: <intro@example.com>\r\n\
+        \r\n\
+        <speak>Hello</speak>\r\n\
+        --a;b\r\n\
+        Content-Type: audio/pcmu\r\n\
+        Content-ID: <beep@example.com>\r\n\
+        Content-Transfer-Encoding: base64\r\n\
+        \r\n\
+        AQID/w==\r\n\
+        --a;b--\r\n\
+        epilogue";
+
+    fn content_type(value: &str) -> ContentType {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn quoted_boundary() {
+        let content_type =
+            content_type("multipart/related; boundary=\"a;b\"; start=\"<intro@example.com>\"");
+        assert_eq!(content_type.boundary(), Some("a;b"));
+        let multipart = Multipart::parse(&content_type, BODY.as_bytes()).unwrap();
+        assert_eq!(multipart.parts.len(), 2);
+        let root = multipart.root().unwrap();
+        assert_eq!(root.content_type.media_type, "application/ssml+xml");
+        assert_eq!(root.text().unwrap(), "<speak>Hello</speak>");
+        assert_eq!(
+            root.content().unwrap(),
+            SpeakContent::Ssml("<speak>Hello</speak>".to_owned())
+        );
+    }
+
+    #[test]
+    fn parts_by_content_id() {
+        let multipart = Multipart::parse(
+            &content_type("multipart/mixed; boundary=\"a;b\""),
+            BODY.as_bytes(),
+        )
+        .unwrap();
+        let beep = multipart.get("cid:beep@example.com").unwrap();
+        assert_eq!(beep.body, [1, 2, 3, 255]);
+        assert_eq!(beep.header("CONTENT-TRANSFER-ENCODING"), Some("base64"));
+        assert!(multipart.get("<intro@example.com>").is_some());
+        assert!(multipart.get("<session:intro@example.com>").is_some());
+        assert!(multipart.get("session:intro@example.com").is_some());
+        assert!(multipart.get("cid:missing@example.com").is_none());
+        assert_eq!(multipart.root(), multipart.parts.first());
+    }
+
+    #[test]
+    fn delimiter_only_at_line_start() {
+        let body = "--xx\nContent-Type: text/plain\n\nnot --xx here\n--xx--\n";
+        let multipart = Multipart::parse(
+            &content_type("multipart/mixed; boundary=xx"),
+            body.as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(multipart.parts.len(), 1);
+        assert_eq!(multipart.parts[0].body, b"not --xx here");
+    }
+
+    #[test]
+    fn invalid_bodies() {
+        let mixed = content_type("multipart/mixed; boundary=xx");
+        assert!(Multipart::parse(&mixed, b"no delimiter").is_err());
+        assert!(Multipart::parse(&mixed, b"--xx\n\nunterminated").is_err());
+        assert!(Multipart::parse(&content_type("text/plain; boundary=xx"), b"--xx--").is_err());
+        assert!(Multipart::parse(&content_type("multipart/mixed"), b"--xx--").is_err());
+    }
+}
@@ -0,0 +1,194 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::{RecogDefaults, RecogHeaders, SynthDefaults, SynthHeaders};
+use crate::uni;
+
+/// Typed headers of a resource that can be kept as channel-level params.
+pub trait Headers: Sized {
+    type Defaults: Clone + Default;
+
+    fn from_request(request: *const uni::mrcp_message_t, defaults: &Self::Defaults) -> Self;
+
+    /// Headers with no values but the defaults.
+    fn from_defaults(defaults: &Self::Defaults) -> Self {
+        Self::from_request(std::ptr::null(), defaults)
+    }
+
+    /// Takes the headers absent in `self` from `channel`.
+    fn inherit(self, channel: &Self) -> Self;
+}
+
+/// Params set on a channel by SET-PARAMS requests.
+#[derive(Debug)]
+pub struct ChannelParams<H: Headers> {
+    defaults: H::Defaults,
+    current: Option<H>,
+}
+
+pub type RecogParams = ChannelParams<RecogHeaders>;
+pub type SynthParams = ChannelParams<SynthHeaders>;
+
+impl<H: Headers> Default for ChannelParams<H> {
+    fn default() -> Self {
+        Self::new(H::Defaults::default())
+    }
+}
+
+impl<H: Headers> ChannelParams<H> {
+    pub fn new(defaults: H::Defaults) -> Self {
+        Self {
+            defaults,
+            current: None,
+        }
+    }
+
+    pub fn defaults(&self) -> &H::Defaults {
+        &self.defaults
+    }
+
+    pub fn current(&self) -> Option<&H> {
+        self.current.as_ref()
+    }
+
+    /// Applies SET-PARAMS, the headers of the request override the ones set before.
+    pub fn set_params(&mut self, request: *const uni::mrcp_message_t) {
+        let headers = H::from_request(request, &self.defaults);
+        self.current = Some(match self.current.take() {
+            Some(current) => headers.inherit(&current),
+            None => headers,
+        });
+    }
+
+    /// Headers of the request completed with the channel-level params.
+    pub fn effective(&self, request: *const uni::mrcp_message_t) -> H {
+        let headers = H::from_request(request, &self.defaults);
+        match &self.current {
+            Some(current) => headers.inherit(current),
+            None => headers,
+        }
+    }
+
+    /// Params of the channel: the ones set by SET-PARAMS over the defaults.
+    pub fn channel_values(&self) -> H {
+        let headers = H::from_defaults(&self.defaults);
+        match &self.current {
+            Some(current) => headers.inherit(current),
+            None => headers,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.current = None;
+    }
+}
+
+impl SynthParams {
+    /// Kill-on-barge-in of the request or the channel, the other headers are not parsed.
+    pub fn kill_on_barge_in(&self, request: *const uni::mrcp_message_t) -> bool {
+        match super::synth::extract_kill_on_barge_in(request) {
+            Ok(kill_on_barge_in) => kill_on_barge_in,
+            Err(_) => match &self.current {
+                Some(current) => current.kill_on_barge_in(),
+                None => true,
+            },
+        }
+    }
+}
+
+impl Headers for RecogHeaders {
+    type Defaults = RecogDefaults;
+
+    fn from_request(request: *const uni::mrcp_message_t, defaults: &RecogDefaults) -> Self {
+        RecogHeaders::with_defaults(request, defaults)
+    }
+
+    fn inherit(self, channel: &Self) -> Self {
+        Self {
+            confidence_threshold: inherit(self.confidence_threshold, &channel.confidence_threshold),
+            sensitivity: inherit(self.sensitivity, &channel.sensitivity),
+            speed_vs_accuracy: inherit(self.speed_vs_accuracy, &channel.speed_vs_accuracy),
+            n_best_list_length: inherit(self.n_best_list_length, &channel.n_best_list_length),
+            noinput_timeout: inherit(self.noinput_timeout, &channel.noinput_timeout),
+            recognition_timeout: inherit(self.recognition_timeout, &channel.recognition_timeout),
+            start_input_timers: inherit(self.start_input_timers, &channel.start_input_timers),
+            silence_timeout: inherit(self.silence_timeout, &channel.silence_timeout),
+            speech_complete_timeout: inherit(
+                self.speech_complete_timeout,
+                &channel.speech_complete_timeout,
+            ),
+            speech_incomplete_timeout: inherit(
+                self.speech_incomplete_timeout,
+                &channel.speech_incomplete_timeout,
+            ),
+            dtmf_interdigit_timeout: inherit(
+                self.dtmf_interdigit_timeout,
+                &channel.dtmf_interdigit_timeout,
+            ),
+            dtmf_term_timeout: inherit(self.dtmf_term_timeout, &channel.dtmf_term_timeout),
+            dtmf_term_char: inherit(self.dtmf_term_char, &channel.dtmf_term_char),
+            dtmf_buffer_time: inherit(self.dtmf_buffer_time, &channel.dtmf_buffer_time),
+            clear_dtmf_buffer: inherit(self.clear_dtmf_buffer, &channel.clear_dtmf_buffer),
+            speech_language: inherit(self.speech_language, &channel.speech_language),
+            hotword_min_duration: inherit(self.hotword_min_duration, &channel.hotword_min_duration),
+            hotword_max_duration: inherit(self.hotword_max_duration, &channel.hotword_max_duration),
+            early_no_match: inherit(self.early_no_match, &channel.early_no_match),
+            recognition_mode: inherit(self.recognition_mode, &channel.recognition_mode),
+            cancel_if_queue: inherit(self.cancel_if_queue, &channel.cancel_if_queue),
+            save_waveform: inherit(self.save_waveform, &channel.save_waveform),
+            media_type: inherit(self.media_type, &channel.media_type),
+            input_waveform_uri: inherit(self.input_waveform_uri, &channel.input_waveform_uri),
+            interpret_text: inherit(self.interpret_text, &channel.interpret_text),
+            vendor_specific: self.vendor_specific.inherit(&channel.vendor_specific),
+            ..self
+        }
+    }
+}
+
+impl Headers for SynthHeaders {
+    type Defaults = SynthDefaults;
+
+    fn from_request(request: *const uni::mrcp_message_t, defaults: &SynthDefaults) -> Self {
+        SynthHeaders::with_defaults(request, defaults)
+    }
+
+    fn inherit(self, channel: &Self) -> Self {
+        Self {
+            voice_name: inherit(self.voice_name, &channel.voice_name),
+            voice_gender: inherit(self.voice_gender, &channel.voice_gender),
+            voice_age: inherit(self.voice_age, &channel.voice_age),
+            voice_variant: inherit(self.voice_variant, &channel.voice_variant),
+            prosody_volume: inherit(self.prosody_volume, &channel.prosody_volume),
+            prosody_rate: inherit(self.prosody_rate, &channel.prosody_rate),
+            speech_language: inherit(self.speech_language, &channel.speech_language),
+            kill_on_barge_in: inherit(self.kill_on_barge_in, &channel.kill_on_barge_in),
+            speaker_profile: inherit(self.speaker_profile, &channel.speaker_profile),
+            load_lexicon: inherit(self.load_lexicon, &channel.load_lexicon),
+            lexicon_search_order: inherit(self.lexicon_search_order, &channel.lexicon_search_order),
+            audio_fetch_hint: inherit(self.audio_fetch_hint, &channel.audio_fetch_hint),
+            fetch_timeout: inherit(self.fetch_timeout, &channel.fetch_timeout),
+            vendor_specific: self.vendor_specific.inherit(&channel.vendor_specific),
+            ..self
+        }
+    }
+}
+
+fn inherit<T: Clone>(own: crate::Result<T>, channel: &crate::Result<T>) -> crate::Result<T> {
+    match (own, channel) {
+        (Err(crate::Error::NoSuchHeader(_) | crate::Error::NullRequest), Ok(value)) => {
+            Ok(value.clone())
+        }
+        (own, _) => own,
+    }
+}
@@ -0,0 +1,76 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use crate::{engine, uni};
+use std::sync::Arc;
+
+/// How the speech-complete-timeout sent by a client is turned into milliseconds.
+#[derive(Clone, Default)]
+pub enum SpeechCompletePolicy {
+    /// The value is taken as is, in milliseconds as RFC 6787 says.
+    Strict,
+    /// Small values are supposed to be sent in seconds by mistake:
+    /// 0–1 → 1000 ms, 2–4 → seconds × 1000, 5–20 → 1200 ms.
+    #[default]
+    Seconds,
+    Custom(Arc<dyn Fn(usize) -> usize + Send + Sync>),
+}
+
+impl SpeechCompletePolicy {
+    pub const ENGINE_PARAM: &'static [u8] = b"speech-complete-timeout-policy\0";
+
+    /// Reads the policy from the engine params, the heuristic is used if the param is absent.
+    pub fn from_engine(engine: *const uni::mrcp_engine_t) -> crate::Result<Self> {
+        engine::get_param_or(engine, Self::ENGINE_PARAM, Self::default())
+    }
+
+    pub fn custom(normalize: impl Fn(usize) -> usize + Send + Sync + 'static) -> Self {
+        Self::Custom(Arc::new(normalize))
+    }
+
+    pub fn normalize(&self, timeout: usize) -> usize {
+        match self {
+            SpeechCompletePolicy::Strict => timeout,
+            SpeechCompletePolicy::Seconds => match timeout {
+                0..=1 => 1000,
+                timeout @ 2..=4 => timeout * 1000,
+                5..=20 => 1200,
+                value => value,
+            },
+            SpeechCompletePolicy::Custom(normalize) => normalize(timeout),
+        }
+    }
+}
+
+impl std::str::FromStr for SpeechCompletePolicy {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "strict" | "rfc" | "milliseconds" => Ok(Self::Strict),
+            "seconds" | "heuristic" => Ok(Self::Seconds),
+            _ => Err(crate::Error::InvalidParamValue(s.to_owned())),
+        }
+    }
+}
+
+impl std::fmt::Debug for SpeechCompletePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpeechCompletePolicy::Strict => write!(f, "Strict"),
+            SpeechCompletePolicy::Seconds => write!(f, "Seconds"),
+            SpeechCompletePolicy::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
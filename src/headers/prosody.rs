@@ -0,0 +1,304 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use crate::uni;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeLabel {
+    Silent,
+    XSoft,
+    Soft,
+    Medium,
+    Loud,
+    XLoud,
+    Default,
+}
+
+impl VolumeLabel {
+    /// Gain relative to the default volume.
+    pub fn gain_db(self) -> f64 {
+        match self {
+            VolumeLabel::Silent => f64::NEG_INFINITY,
+            VolumeLabel::XSoft => -12.0,
+            VolumeLabel::Soft => -6.0,
+            VolumeLabel::Medium | VolumeLabel::Default => 0.0,
+            VolumeLabel::Loud => 6.0,
+            VolumeLabel::XLoud => 12.0,
+        }
+    }
+}
+
+impl std::str::FromStr for VolumeLabel {
+    type Err = crate::Error;
+
+    /// Parses SSML labels such as `x-soft`.
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.trim() {
+            "silent" => Ok(VolumeLabel::Silent),
+            "x-soft" => Ok(VolumeLabel::XSoft),
+            "soft" => Ok(VolumeLabel::Soft),
+            "medium" => Ok(VolumeLabel::Medium),
+            "loud" => Ok(VolumeLabel::Loud),
+            "x-loud" => Ok(VolumeLabel::XLoud),
+            "default" => Ok(VolumeLabel::Default),
+            _ => Err(crate::Error::InvalidParamValue(s.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProsodyVolume {
+    Label(VolumeLabel),
+    /// Absolute volume in the range 0.0 to 100.0.
+    Numeric(f32),
+    /// Relative change on the same scale, e.g. +10.
+    Relative(f32),
+}
+
+impl ProsodyVolume {
+    /// Gain relative to the default volume, which is 100 on the numeric scale.
+    /// Silence gives negative infinity.
+    pub fn gain_db(&self) -> f64 {
+        match *self {
+            ProsodyVolume::Label(label) => label.gain_db(),
+            ProsodyVolume::Numeric(volume) => amplitude_to_db(volume as f64 / 100.0),
+            ProsodyVolume::Relative(change) => amplitude_to_db((100.0 + change as f64) / 100.0),
+        }
+    }
+
+    /// Linear amplitude factor to apply to the samples.
+    pub fn gain_factor(&self) -> f64 {
+        10f64.powf(self.gain_db() / 20.0)
+    }
+
+    pub(super) fn to_raw(self) -> uni::mrcp_prosody_volume_t {
+        match self {
+            ProsodyVolume::Label(label) => uni::mrcp_prosody_volume_t {
+                type_: uni::PROSODY_VOLUME_TYPE_LABEL,
+                value: uni::mrcp_prosody_volume_t__bindgen_ty_1 {
+                    label: match label {
+                        VolumeLabel::Silent => uni::PROSODY_VOLUME_SILENT,
+                        VolumeLabel::XSoft => uni::PROSODY_VOLUME_XSOFT,
+                        VolumeLabel::Soft => uni::PROSODY_VOLUME_SOFT,
+                        VolumeLabel::Medium => uni::PROSODY_VOLUME_MEDIUM,
+                        VolumeLabel::Loud => uni::PROSODY_VOLUME_LOUD,
+                        VolumeLabel::XLoud => uni::PROSODY_VOLUME_XLOUD,
+                        VolumeLabel::Default => uni::PROSODY_VOLUME_DEFAULT,
+                    },
+                },
+            },
+            ProsodyVolume::Numeric(numeric) => uni::mrcp_prosody_volume_t {
+                type_: uni::PROSODY_VOLUME_TYPE_NUMERIC,
+                value: uni::mrcp_prosody_volume_t__bindgen_ty_1 { numeric },
+            },
+            ProsodyVolume::Relative(relative) => uni::mrcp_prosody_volume_t {
+                type_: uni::PROSODY_VOLUME_TYPE_RELATIVE_CHANGE,
+                value: uni::mrcp_prosody_volume_t__bindgen_ty_1 { relative },
+            },
+        }
+    }
+
+    pub(super) fn from_raw(volume: &uni::mrcp_prosody_volume_t) -> crate::Result<Self> {
+        let invalid = || crate::Error::InvalidHeaderValue(uni::SYNTHESIZER_HEADER_PROSODY_VOLUME);
+        unsafe {
+            match volume.type_ {
+                uni::PROSODY_VOLUME_TYPE_LABEL => {
+                    Ok(ProsodyVolume::Label(match volume.value.label {
+                        uni::PROSODY_VOLUME_SILENT => VolumeLabel::Silent,
+                        uni::PROSODY_VOLUME_XSOFT => VolumeLabel::XSoft,
+                        uni::PROSODY_VOLUME_SOFT => VolumeLabel::Soft,
+                        uni::PROSODY_VOLUME_MEDIUM => VolumeLabel::Medium,
+                        uni::PROSODY_VOLUME_LOUD => VolumeLabel::Loud,
+                        uni::PROSODY_VOLUME_XLOUD => VolumeLabel::XLoud,
+                        uni::PROSODY_VOLUME_DEFAULT => VolumeLabel::Default,
+                        _ => return Err(invalid()),
+                    }))
+                }
+                uni::PROSODY_VOLUME_TYPE_NUMERIC => {
+                    Ok(ProsodyVolume::Numeric(volume.value.numeric))
+                }
+                uni::PROSODY_VOLUME_TYPE_RELATIVE_CHANGE => {
+                    Ok(ProsodyVolume::Relative(volume.value.relative))
+                }
+                _ => Err(invalid()),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLabel {
+    XSlow,
+    Slow,
+    Medium,
+    Fast,
+    XFast,
+    Default,
+}
+
+impl RateLabel {
+    /// Speaking rate relative to the default one.
+    pub fn multiplier(self) -> f64 {
+        match self {
+            RateLabel::XSlow => 0.5,
+            RateLabel::Slow => 0.75,
+            RateLabel::Medium | RateLabel::Default => 1.0,
+            RateLabel::Fast => 1.25,
+            RateLabel::XFast => 1.5,
+        }
+    }
+}
+
+impl std::str::FromStr for RateLabel {
+    type Err = crate::Error;
+
+    /// Parses SSML labels such as `x-fast`.
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.trim() {
+            "x-slow" => Ok(RateLabel::XSlow),
+            "slow" => Ok(RateLabel::Slow),
+            "medium" => Ok(RateLabel::Medium),
+            "fast" => Ok(RateLabel::Fast),
+            "x-fast" => Ok(RateLabel::XFast),
+            "default" => Ok(RateLabel::Default),
+            _ => Err(crate::Error::InvalidParamValue(s.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProsodyRate {
+    Label(RateLabel),
+    /// Relative change, e.g. 1.5 for 50% faster.
+    Relative(f32),
+}
+
+impl ProsodyRate {
+    /// Speaking rate relative to the default one, never negative.
+    pub fn multiplier(&self) -> f64 {
+        match *self {
+            ProsodyRate::Label(label) => label.multiplier(),
+            ProsodyRate::Relative(relative) => (relative as f64).max(0.0),
+        }
+    }
+
+    pub(super) fn to_raw(self) -> uni::mrcp_prosody_rate_t {
+        match self {
+            ProsodyRate::Label(label) => uni::mrcp_prosody_rate_t {
+                type_: uni::PROSODY_RATE_TYPE_LABEL,
+                value: uni::mrcp_prosody_rate_t__bindgen_ty_1 {
+                    label: match label {
+                        RateLabel::XSlow => uni::PROSODY_RATE_XSLOW,
+                        RateLabel::Slow => uni::PROSODY_RATE_SLOW,
+                        RateLabel::Medium => uni::PROSODY_RATE_MEDIUM,
+                        RateLabel::Fast => uni::PROSODY_RATE_FAST,
+                        RateLabel::XFast => uni::PROSODY_RATE_XFAST,
+                        RateLabel::Default => uni::PROSODY_RATE_DEFAULT,
+                    },
+                },
+            },
+            ProsodyRate::Relative(relative) => uni::mrcp_prosody_rate_t {
+                type_: uni::PROSODY_RATE_TYPE_RELATIVE_CHANGE,
+                value: uni::mrcp_prosody_rate_t__bindgen_ty_1 { relative },
+            },
+        }
+    }
+
+    pub(super) fn from_raw(rate: &uni::mrcp_prosody_rate_t) -> crate::Result<Self> {
+        let invalid = || crate::Error::InvalidHeaderValue(uni::SYNTHESIZER_HEADER_PROSODY_RATE);
+        unsafe {
+            match rate.type_ {
+                uni::PROSODY_RATE_TYPE_LABEL => Ok(ProsodyRate::Label(match rate.value.label {
+                    uni::PROSODY_RATE_XSLOW => RateLabel::XSlow,
+                    uni::PROSODY_RATE_SLOW => RateLabel::Slow,
+                    uni::PROSODY_RATE_MEDIUM => RateLabel::Medium,
+                    uni::PROSODY_RATE_FAST => RateLabel::Fast,
+                    uni::PROSODY_RATE_XFAST => RateLabel::XFast,
+                    uni::PROSODY_RATE_DEFAULT => RateLabel::Default,
+                    _ => return Err(invalid()),
+                })),
+                uni::PROSODY_RATE_TYPE_RELATIVE_CHANGE => {
+                    Ok(ProsodyRate::Relative(rate.value.relative))
+                }
+                _ => Err(invalid()),
+            }
+        }
+    }
+}
+
+fn amplitude_to_db(amplitude: f64) -> f64 {
+    if amplitude <= 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    20.0 * amplitude.log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headers::SynthHeaders;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 0.01, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn volume_gain() {
+        assert_eq!(ProsodyVolume::Label(VolumeLabel::Loud).gain_db(), 6.0);
+        assert_eq!(
+            ProsodyVolume::Label(VolumeLabel::Silent).gain_db(),
+            f64::NEG_INFINITY
+        );
+        assert_eq!(ProsodyVolume::Numeric(100.0).gain_db(), 0.0);
+        assert_close(ProsodyVolume::Numeric(50.0).gain_db(), -6.02);
+        assert_eq!(ProsodyVolume::Numeric(0.0).gain_db(), f64::NEG_INFINITY);
+        assert_close(ProsodyVolume::Relative(100.0).gain_db(), 6.02);
+        assert_eq!(ProsodyVolume::Relative(-100.0).gain_db(), f64::NEG_INFINITY);
+        assert_close(ProsodyVolume::Numeric(50.0).gain_factor(), 0.5);
+        assert_eq!(ProsodyVolume::Label(VolumeLabel::Silent).gain_factor(), 0.0);
+    }
+
+    #[test]
+    fn rate_multiplier() {
+        assert_eq!(ProsodyRate::Label(RateLabel::Fast).multiplier(), 1.25);
+        assert_eq!(ProsodyRate::Label(RateLabel::XSlow).multiplier(), 0.5);
+        assert_eq!(ProsodyRate::Relative(1.5).multiplier(), 1.5);
+        assert_eq!(ProsodyRate::Relative(-0.5).multiplier(), 0.0);
+    }
+
+    #[test]
+    fn labels() {
+        assert_eq!(
+            " x-soft ".parse::<VolumeLabel>().unwrap(),
+            VolumeLabel::XSoft
+        );
+        assert_eq!("x-fast".parse::<RateLabel>().unwrap(), RateLabel::XFast);
+        assert!(matches!(
+            "LOUD".parse::<VolumeLabel>(),
+            Err(crate::Error::InvalidParamValue(_))
+        ));
+        assert!("quick".parse::<RateLabel>().is_err());
+    }
+
+    #[test]
+    fn request_defaults() {
+        let mut headers = SynthHeaders::new(std::ptr::null());
+        assert_eq!(headers.volume_gain_db(), 0.0);
+        assert_eq!(headers.rate_multiplier(), 1.0);
+        headers.prosody_volume = Ok(ProsodyVolume::Label(VolumeLabel::Loud));
+        headers.prosody_rate = Ok(ProsodyRate::Relative(-2.0));
+        assert_eq!(headers.volume_gain_db(), 6.0);
+        assert_eq!(headers.rate_multiplier(), 0.0);
+    }
+}
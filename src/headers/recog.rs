@@ -12,188 +12,313 @@
 //    See the License for the specific language governing permissions and
 //    limitations under the License.
 
-use crate::{inline_mrcp_resource_header_get, inline_mrcp_resource_header_property_check, uni};
-use std::collections::HashMap;
+use super::{
+    apt_str_to_string, extract_body, extract_content_id, extract_content_type, extract_parts,
+    ContentType, Part, RecogDefaults, SpeechCompletePolicy,
+};
+use crate::{
+    inline_mrcp_resource_header_get, inline_mrcp_resource_header_property_check,
+    language::LanguageTag, uni,
+};
 
 #[derive(Debug)]
 pub struct RecogHeaders {
+    pub confidence_threshold: crate::Result<f64>,
     pub sensitivity: crate::Result<f64>,
+    pub speed_vs_accuracy: crate::Result<f64>,
+    pub n_best_list_length: crate::Result<usize>,
     pub noinput_timeout: crate::Result<usize>,
     pub recognition_timeout: crate::Result<usize>,
     pub start_input_timers: crate::Result<bool>,
+    /// Speech-complete-timeout normalized by the policy.
     pub silence_timeout: crate::Result<usize>,
-    pub vendor_specific: HashMap<String, String>,
+    /// Speech-complete-timeout as the client sent it.
+    pub speech_complete_timeout: crate::Result<usize>,
+    pub speech_incomplete_timeout: crate::Result<usize>,
+    pub dtmf_interdigit_timeout: crate::Result<usize>,
+    pub dtmf_term_timeout: crate::Result<usize>,
+    pub dtmf_term_char: crate::Result<char>,
+    pub dtmf_buffer_time: crate::Result<usize>,
+    pub clear_dtmf_buffer: crate::Result<bool>,
+    pub speech_language: crate::Result<LanguageTag>,
+    pub hotword_min_duration: crate::Result<usize>,
+    pub hotword_max_duration: crate::Result<usize>,
+    pub early_no_match: crate::Result<bool>,
+    pub recognition_mode: crate::Result<String>,
+    pub cancel_if_queue: crate::Result<bool>,
+    pub save_waveform: crate::Result<bool>,
+    pub media_type: crate::Result<String>,
+    pub input_waveform_uri: crate::Result<String>,
+    pub interpret_text: crate::Result<String>,
+    pub content_type: crate::Result<ContentType>,
+    pub content_id: crate::Result<String>,
+    /// Body decoded with the charset of the content type.
+    pub body: crate::Result<String>,
+    /// Parts of a multipart body, or the whole body as a single part,
+    /// e.g. the grammars of DEFINE-GRAMMAR and RECOGNIZE.
+    pub parts: crate::Result<Vec<Part>>,
+    pub vendor_specific: super::VendorParams,
+    pub(super) defaults: RecogDefaults,
 }
 
 impl RecogHeaders {
     pub fn new(request: *const uni::mrcp_message_t) -> Self {
+        Self::with_defaults(request, &RecogDefaults::default())
+    }
+
+    pub fn with_policy(request: *const uni::mrcp_message_t, policy: &SpeechCompletePolicy) -> Self {
+        let defaults = RecogDefaults {
+            speech_complete_policy: policy.clone(),
+            ..Default::default()
+        };
+        Self::with_defaults(request, &defaults)
+    }
+
+    pub fn with_defaults(request: *const uni::mrcp_message_t, defaults: &RecogDefaults) -> Self {
+        let policy = &defaults.speech_complete_policy;
         Self {
+            confidence_threshold: extract(
+                request,
+                uni::RECOGNIZER_HEADER_CONFIDENCE_THRESHOLD,
+                |header| Ok(header.confidence_threshold as _),
+            ),
             sensitivity: extract_sensitivity(request),
+            speed_vs_accuracy: extract(
+                request,
+                uni::RECOGNIZER_HEADER_SPEED_VS_ACCURACY,
+                |header| Ok(header.speed_vs_accuracy as _),
+            ),
+            n_best_list_length: extract(
+                request,
+                uni::RECOGNIZER_HEADER_N_BEST_LIST_LENGTH,
+                |header| Ok(header.n_best_list_length),
+            ),
             noinput_timeout: extract_noinput_timeout(request),
             recognition_timeout: extract_recognition_timeout(request),
             start_input_timers: extract_start_input_timers(request),
-            silence_timeout: extract_speech_complete_timeout(request),
+            silence_timeout: extract_speech_complete_timeout(request)
+                .map(|timeout| policy.normalize(timeout)),
+            speech_complete_timeout: extract_speech_complete_timeout(request),
+            speech_incomplete_timeout: extract(
+                request,
+                uni::RECOGNIZER_HEADER_SPEECH_INCOMPLETE_TIMEOUT,
+                |header| Ok(header.speech_incomplete_timeout),
+            ),
+            dtmf_interdigit_timeout: extract(
+                request,
+                uni::RECOGNIZER_HEADER_DTMF_INTERDIGIT_TIMEOUT,
+                |header| Ok(header.dtmf_interdigit_timeout),
+            ),
+            dtmf_term_timeout: extract(
+                request,
+                uni::RECOGNIZER_HEADER_DTMF_TERM_TIMEOUT,
+                |header| Ok(header.dtmf_term_timeout),
+            ),
+            dtmf_term_char: extract(request, uni::RECOGNIZER_HEADER_DTMF_TERM_CHAR, |header| {
+                Ok(header.dtmf_term_char as u8 as char)
+            }),
+            dtmf_buffer_time: extract(request, uni::RECOGNIZER_HEADER_DTMF_BUFFER_TIME, |header| {
+                Ok(header.dtmf_buffer_time)
+            }),
+            clear_dtmf_buffer: extract(
+                request,
+                uni::RECOGNIZER_HEADER_CLEAR_DTMF_BUFFER,
+                |header| Ok(header.clear_dtmf_buffer == uni::TRUE),
+            ),
+            speech_language: extract(request, uni::RECOGNIZER_HEADER_SPEECH_LANGUAGE, |header| {
+                apt_str_to_string(&header.speech_language)?.parse()
+            }),
+            hotword_min_duration: extract(
+                request,
+                uni::RECOGNIZER_HEADER_HOTWORD_MIN_DURATION,
+                |header| Ok(header.hotword_min_duration),
+            ),
+            hotword_max_duration: extract(
+                request,
+                uni::RECOGNIZER_HEADER_HOTWORD_MAX_DURATION,
+                |header| Ok(header.hotword_max_duration),
+            ),
+            early_no_match: extract(request, uni::RECOGNIZER_HEADER_EARLY_NO_MATCH, |header| {
+                Ok(header.early_no_match == uni::TRUE)
+            }),
+            recognition_mode: extract(request, uni::RECOGNIZER_HEADER_RECOGNITION_MODE, |header| {
+                apt_str_to_string(&header.recognition_mode)
+            }),
+            cancel_if_queue: extract(request, uni::RECOGNIZER_HEADER_CANCEL_IF_QUEUE, |header| {
+                Ok(header.cancel_if_queue == uni::TRUE)
+            }),
+            save_waveform: extract(request, uni::RECOGNIZER_HEADER_SAVE_WAVEFORM, |header| {
+                Ok(header.save_waveform == uni::TRUE)
+            }),
+            media_type: extract(request, uni::RECOGNIZER_HEADER_MEDIA_TYPE, |header| {
+                apt_str_to_string(&header.media_type)
+            }),
+            input_waveform_uri: extract(
+                request,
+                uni::RECOGNIZER_HEADER_INPUT_WAVEFORM_URI,
+                |header| apt_str_to_string(&header.input_waveform_uri),
+            ),
+            interpret_text: extract(request, uni::RECOGNIZER_HEADER_INTERPRET_TEXT, |header| {
+                apt_str_to_string(&header.interpret_text)
+            }),
+            content_type: extract_content_type(request),
+            content_id: extract_content_id(request),
+            body: extract_body(request),
+            parts: extract_parts(request),
             vendor_specific: super::extract_vendor_specific_parameters(request),
+            defaults: defaults.clone(),
         }
     }
 
+    pub fn defaults(&self) -> &RecogDefaults {
+        &self.defaults
+    }
+
+    pub fn confidence_threshold(&self) -> f64 {
+        *self
+            .confidence_threshold
+            .as_ref()
+            .unwrap_or(&self.defaults.confidence_threshold)
+    }
+
     pub fn sensitivity(&self) -> f64 {
-        *self.sensitivity.as_ref().unwrap_or(&0.6)
+        *self
+            .sensitivity
+            .as_ref()
+            .unwrap_or(&self.defaults.sensitivity)
+    }
+
+    pub fn speed_vs_accuracy(&self) -> f64 {
+        *self
+            .speed_vs_accuracy
+            .as_ref()
+            .unwrap_or(&self.defaults.speed_vs_accuracy)
+    }
+
+    pub fn n_best_list_length(&self) -> usize {
+        *self
+            .n_best_list_length
+            .as_ref()
+            .unwrap_or(&self.defaults.n_best_list_length)
     }
 
     pub fn noinput_timeout(&self) -> usize {
-        *self.noinput_timeout.as_ref().unwrap_or(&5000)
+        *self
+            .noinput_timeout
+            .as_ref()
+            .unwrap_or(&self.defaults.noinput_timeout)
     }
 
     pub fn recognition_timeout(&self) -> usize {
-        *self.recognition_timeout.as_ref().unwrap_or(&20000)
+        *self
+            .recognition_timeout
+            .as_ref()
+            .unwrap_or(&self.defaults.recognition_timeout)
     }
 
     pub fn start_input_timers(&self) -> bool {
-        *self.start_input_timers.as_ref().unwrap_or(&true)
+        *self
+            .start_input_timers
+            .as_ref()
+            .unwrap_or(&self.defaults.start_input_timers)
     }
 
     pub fn silence_timeout(&self) -> usize {
-        *self.silence_timeout.as_ref().unwrap_or(&1000)
+        *self
+            .silence_timeout
+            .as_ref()
+            .unwrap_or(&self.defaults.silence_timeout)
     }
-}
 
-fn extract_sensitivity(request: *const uni::mrcp_message_t) -> crate::Result<f64> {
-    if request.is_null() {
-        return Err(crate::Error::NullRequest);
+    pub fn speech_incomplete_timeout(&self) -> usize {
+        *self
+            .speech_incomplete_timeout
+            .as_ref()
+            .unwrap_or(&self.defaults.speech_incomplete_timeout)
     }
-    unsafe {
-        if inline_mrcp_resource_header_property_check(
-            request,
-            uni::RECOGNIZER_HEADER_SENSITIVITY_LEVEL as _,
-        ) == uni::TRUE
-        {
-            let recog_header =
-                inline_mrcp_resource_header_get(request) as *mut uni::mrcp_recog_header_t;
-            if recog_header.is_null() {
-                Err(crate::Error::NoSuchHeader(
-                    uni::RECOGNIZER_HEADER_SENSITIVITY_LEVEL,
-                ))
-            } else {
-                Ok((*recog_header).sensitivity_level as _)
-            }
-        } else {
-            Err(crate::Error::NoSuchHeader(
-                uni::RECOGNIZER_HEADER_SENSITIVITY_LEVEL,
-            ))
-        }
+
+    pub fn dtmf_interdigit_timeout(&self) -> usize {
+        *self
+            .dtmf_interdigit_timeout
+            .as_ref()
+            .unwrap_or(&self.defaults.dtmf_interdigit_timeout)
     }
-}
 
-fn extract_noinput_timeout(request: *const uni::mrcp_message_t) -> crate::Result<usize> {
-    if request.is_null() {
-        return Err(crate::Error::NullRequest);
+    pub fn dtmf_term_timeout(&self) -> usize {
+        *self
+            .dtmf_term_timeout
+            .as_ref()
+            .unwrap_or(&self.defaults.dtmf_term_timeout)
     }
-    unsafe {
-        if inline_mrcp_resource_header_property_check(
-            request,
-            uni::RECOGNIZER_HEADER_NO_INPUT_TIMEOUT as _,
-        ) == uni::TRUE
-        {
-            let recog_header =
-                inline_mrcp_resource_header_get(request) as *mut uni::mrcp_recog_header_t;
-            if recog_header.is_null() {
-                Err(crate::Error::NoSuchHeader(
-                    uni::RECOGNIZER_HEADER_NO_INPUT_TIMEOUT,
-                ))
-            } else {
-                Ok((*recog_header).no_input_timeout)
-            }
-        } else {
-            Err(crate::Error::NoSuchHeader(
-                uni::RECOGNIZER_HEADER_NO_INPUT_TIMEOUT,
-            ))
-        }
+
+    pub fn body(&self) -> Option<&str> {
+        self.body.as_deref().ok()
     }
-}
 
-fn extract_recognition_timeout(request: *const uni::mrcp_message_t) -> crate::Result<usize> {
-    if request.is_null() {
-        return Err(crate::Error::NullRequest);
+    pub fn parts(&self) -> &[Part] {
+        self.parts.as_deref().unwrap_or_default()
     }
-    unsafe {
-        if inline_mrcp_resource_header_property_check(
-            request,
-            uni::RECOGNIZER_HEADER_RECOGNITION_TIMEOUT as _,
-        ) == uni::TRUE
-        {
-            let recog_header =
-                inline_mrcp_resource_header_get(request) as *mut uni::mrcp_recog_header_t;
-            if recog_header.is_null() {
-                Err(crate::Error::NoSuchHeader(
-                    uni::RECOGNIZER_HEADER_RECOGNITION_TIMEOUT,
-                ))
-            } else {
-                Ok((*recog_header).recognition_timeout)
-            }
-        } else {
-            Err(crate::Error::NoSuchHeader(
-                uni::RECOGNIZER_HEADER_RECOGNITION_TIMEOUT,
-            ))
-        }
+
+    /// Body part by its Content-ID, e.g. for `session:` grammar references.
+    pub fn part(&self, content_id: &str) -> Option<&Part> {
+        self.parts().iter().find(|part| part.has_id(content_id))
     }
 }
 
+fn extract_sensitivity(request: *const uni::mrcp_message_t) -> crate::Result<f64> {
+    extract(
+        request,
+        uni::RECOGNIZER_HEADER_SENSITIVITY_LEVEL,
+        |header| Ok(header.sensitivity_level as _),
+    )
+}
+
+fn extract_noinput_timeout(request: *const uni::mrcp_message_t) -> crate::Result<usize> {
+    extract(request, uni::RECOGNIZER_HEADER_NO_INPUT_TIMEOUT, |header| {
+        Ok(header.no_input_timeout)
+    })
+}
+
+fn extract_recognition_timeout(request: *const uni::mrcp_message_t) -> crate::Result<usize> {
+    extract(
+        request,
+        uni::RECOGNIZER_HEADER_RECOGNITION_TIMEOUT,
+        |header| Ok(header.recognition_timeout),
+    )
+}
+
 fn extract_start_input_timers(request: *const uni::mrcp_message_t) -> crate::Result<bool> {
-    if request.is_null() {
-        return Err(crate::Error::NullRequest);
-    }
-    unsafe {
-        if inline_mrcp_resource_header_property_check(
-            request,
-            uni::RECOGNIZER_HEADER_START_INPUT_TIMERS as _,
-        ) == uni::TRUE
-        {
-            let recog_header =
-                inline_mrcp_resource_header_get(request) as *mut uni::mrcp_recog_header_t;
-            if recog_header.is_null() {
-                Err(crate::Error::NoSuchHeader(
-                    uni::RECOGNIZER_HEADER_START_INPUT_TIMERS,
-                ))
-            } else {
-                Ok((*recog_header).start_input_timers == uni::TRUE)
-            }
-        } else {
-            Err(crate::Error::NoSuchHeader(
-                uni::RECOGNIZER_HEADER_START_INPUT_TIMERS,
-            ))
-        }
-    }
+    extract(
+        request,
+        uni::RECOGNIZER_HEADER_START_INPUT_TIMERS,
+        |header| Ok(header.start_input_timers == uni::TRUE),
+    )
 }
 
 fn extract_speech_complete_timeout(request: *const uni::mrcp_message_t) -> crate::Result<usize> {
+    extract(
+        request,
+        uni::RECOGNIZER_HEADER_SPEECH_COMPLETE_TIMEOUT,
+        |header| Ok(header.speech_complete_timeout),
+    )
+}
+
+fn extract<T>(
+    request: *const uni::mrcp_message_t,
+    id: u32,
+    read: impl FnOnce(&uni::mrcp_recog_header_t) -> crate::Result<T>,
+) -> crate::Result<T> {
     if request.is_null() {
         return Err(crate::Error::NullRequest);
     }
     unsafe {
-        if inline_mrcp_resource_header_property_check(
-            request,
-            uni::RECOGNIZER_HEADER_SPEECH_COMPLETE_TIMEOUT as _,
-        ) == uni::TRUE
-        {
+        if inline_mrcp_resource_header_property_check(request, id as _) == uni::TRUE {
             let recog_header =
-                inline_mrcp_resource_header_get(request) as *mut uni::mrcp_recog_header_t;
-            if recog_header.is_null() {
-                Err(crate::Error::NoSuchHeader(
-                    uni::RECOGNIZER_HEADER_SPEECH_COMPLETE_TIMEOUT,
-                ))
-            } else {
-                match (*recog_header).speech_complete_timeout {
-                    0..=1 => Ok(1000),
-                    timeout @ 2..=4 => Ok(timeout * 1000),
-                    5..=20 => Ok(1200),
-                    value => Ok(value),
-                }
+                inline_mrcp_resource_header_get(request) as *const uni::mrcp_recog_header_t;
+            if !recog_header.is_null() {
+                return read(&*recog_header);
             }
-        } else {
-            Err(crate::Error::NoSuchHeader(
-                uni::RECOGNIZER_HEADER_SPEECH_COMPLETE_TIMEOUT,
-            ))
         }
     }
+    Err(crate::Error::NoSuchHeader(id))
 }
@@ -12,28 +12,111 @@
 //    See the License for the specific language governing permissions and
 //    limitations under the License.
 
-use super::apt_str_to_string;
+use super::{
+    apt_str_to_string, extract_body, extract_content_id, extract_content_type, extract_generic,
+    extract_parts, extract_raw_body, ContentType, Part, ProsodyRate, ProsodyVolume, SpeakContent,
+    SpeechLength, SynthDefaults, VoiceGender,
+};
 use crate::{
-    inline_mrcp_generic_header_get, inline_mrcp_generic_header_property_check,
-    inline_mrcp_resource_header_get, inline_mrcp_resource_header_property_check, uni,
+    inline_mrcp_resource_header_get, inline_mrcp_resource_header_property_check,
+    language::LanguageTag,
+    text::{Lang, Normalizer},
+    uni,
 };
-use std::collections::HashMap;
 
 #[derive(Debug)]
 pub struct SynthHeaders {
     pub content_length: crate::Result<usize>,
     pub voice_name: crate::Result<String>,
+    pub voice_gender: crate::Result<VoiceGender>,
+    pub voice_age: crate::Result<usize>,
+    pub voice_variant: crate::Result<usize>,
+    pub prosody_volume: crate::Result<ProsodyVolume>,
+    pub prosody_rate: crate::Result<ProsodyRate>,
+    pub speech_language: crate::Result<LanguageTag>,
+    pub kill_on_barge_in: crate::Result<bool>,
+    pub speaker_profile: crate::Result<String>,
+    pub jump_size: crate::Result<SpeechLength>,
+    pub speak_length: crate::Result<SpeechLength>,
+    pub speak_restart: crate::Result<bool>,
+    pub load_lexicon: crate::Result<bool>,
+    pub lexicon_search_order: crate::Result<String>,
+    pub audio_fetch_hint: crate::Result<String>,
+    pub fetch_timeout: crate::Result<usize>,
+    pub content_type: crate::Result<ContentType>,
+    pub content_id: crate::Result<String>,
+    /// Body decoded with the charset of the content type.
     pub body: crate::Result<String>,
-    pub vendor_specific: HashMap<String, String>,
+    pub content: crate::Result<SpeakContent>,
+    /// Parts of a multipart body, or the whole body as a single part.
+    pub parts: crate::Result<Vec<Part>>,
+    pub vendor_specific: super::VendorParams,
+    pub(super) defaults: SynthDefaults,
 }
 
 impl SynthHeaders {
     pub fn new(request: *const uni::mrcp_message_t) -> Self {
+        Self::with_defaults(request, &SynthDefaults::default())
+    }
+
+    pub fn with_defaults(request: *const uni::mrcp_message_t, defaults: &SynthDefaults) -> Self {
         Self {
             content_length: extract_content_length(request),
             voice_name: extract_voice_name(request),
+            voice_gender: extract(request, uni::SYNTHESIZER_HEADER_VOICE_GENDER, |header| {
+                VoiceGender::from_raw(header.voice_param.gender)
+            }),
+            voice_age: extract(request, uni::SYNTHESIZER_HEADER_VOICE_AGE, |header| {
+                Ok(header.voice_param.age)
+            }),
+            voice_variant: extract(request, uni::SYNTHESIZER_HEADER_VOICE_VARIANT, |header| {
+                Ok(header.voice_param.variant)
+            }),
+            prosody_volume: extract(request, uni::SYNTHESIZER_HEADER_PROSODY_VOLUME, |header| {
+                ProsodyVolume::from_raw(&header.prosody_param.volume)
+            }),
+            prosody_rate: extract(request, uni::SYNTHESIZER_HEADER_PROSODY_RATE, |header| {
+                ProsodyRate::from_raw(&header.prosody_param.rate)
+            }),
+            speech_language: extract(request, uni::SYNTHESIZER_HEADER_SPEECH_LANGUAGE, |header| {
+                apt_str_to_string(&header.speech_language)?.parse()
+            }),
+            kill_on_barge_in: extract_kill_on_barge_in(request),
+            speaker_profile: extract(request, uni::SYNTHESIZER_HEADER_SPEAKER_PROFILE, |header| {
+                apt_str_to_string(&header.speaker_profile)
+            }),
+            jump_size: extract(request, uni::SYNTHESIZER_HEADER_JUMP_SIZE, |header| {
+                SpeechLength::from_raw(&header.jump_size, uni::SYNTHESIZER_HEADER_JUMP_SIZE)
+            }),
+            speak_length: extract(request, uni::SYNTHESIZER_HEADER_SPEAK_LENGTH, |header| {
+                SpeechLength::from_raw(&header.speak_length, uni::SYNTHESIZER_HEADER_SPEAK_LENGTH)
+            }),
+            speak_restart: extract(request, uni::SYNTHESIZER_HEADER_SPEAK_RESTART, |header| {
+                Ok(header.speak_restart == uni::TRUE)
+            }),
+            load_lexicon: extract(request, uni::SYNTHESIZER_HEADER_LOAD_LEXICON, |header| {
+                Ok(header.load_lexicon == uni::TRUE)
+            }),
+            lexicon_search_order: extract(
+                request,
+                uni::SYNTHESIZER_HEADER_LEXICON_SEARCH_ORDER,
+                |header| apt_str_to_string(&header.lexicon_search_order),
+            ),
+            audio_fetch_hint: extract(
+                request,
+                uni::SYNTHESIZER_HEADER_AUDIO_FETCH_HINT,
+                |header| apt_str_to_string(&header.audio_fetch_hint),
+            ),
+            fetch_timeout: extract_generic(request, uni::GENERIC_HEADER_FETCH_TIMEOUT, |header| {
+                Ok(header.fetch_timeout)
+            }),
+            content_type: extract_content_type(request),
+            content_id: extract_content_id(request),
             body: extract_body(request),
+            content: extract_content(request),
+            parts: extract_parts(request),
             vendor_specific: super::extract_vendor_specific_parameters(request),
+            defaults: defaults.clone(),
         }
     }
 
@@ -41,74 +124,117 @@ impl SynthHeaders {
         *self.content_length.as_ref().unwrap_or(&0)
     }
 
+    pub fn defaults(&self) -> &SynthDefaults {
+        &self.defaults
+    }
+
     pub fn voice_name(&self) -> &str {
         match &self.voice_name {
             Ok(voice) => voice.as_str(),
-            _ => "",
+            _ => self.defaults.voice_name.as_str(),
         }
     }
 
+    /// Gain in dB requested by prosody-volume, 0.0 if absent.
+    pub fn volume_gain_db(&self) -> f64 {
+        self.prosody_volume
+            .as_ref()
+            .map(ProsodyVolume::gain_db)
+            .unwrap_or(0.0)
+    }
+
+    /// Rate multiplier requested by prosody-rate, 1.0 if absent.
+    pub fn rate_multiplier(&self) -> f64 {
+        self.prosody_rate
+            .as_ref()
+            .map(ProsodyRate::multiplier)
+            .unwrap_or(1.0)
+    }
+
+    /// Kill-on-barge-in is true unless the request says otherwise.
+    pub fn kill_on_barge_in(&self) -> bool {
+        *self.kill_on_barge_in.as_ref().unwrap_or(&true)
+    }
+
     pub fn body(&self) -> Option<&str> {
         self.body.as_deref().ok()
     }
-}
 
-fn extract_content_length(request: *const uni::mrcp_message_t) -> crate::Result<usize> {
-    if request.is_null() {
-        return Err(crate::Error::NullRequest);
+    pub fn content(&self) -> Option<&SpeakContent> {
+        self.content.as_ref().ok()
     }
-    unsafe {
-        if inline_mrcp_generic_header_property_check(
-            request,
-            uni::GENERIC_HEADER_CONTENT_LENGTH as _,
-        ) == uni::TRUE
-        {
-            let generic_header = inline_mrcp_generic_header_get(request);
-            if generic_header.is_null() {
-                Err(crate::Error::NoSuchHeader(
-                    uni::GENERIC_HEADER_CONTENT_LENGTH,
-                ))
-            } else {
-                Ok((*generic_header).content_length)
-            }
-        } else {
-            Err(crate::Error::NoSuchHeader(
-                uni::GENERIC_HEADER_CONTENT_LENGTH,
-            ))
-        }
+
+    pub fn parts(&self) -> &[Part] {
+        self.parts.as_deref().unwrap_or_default()
+    }
+
+    /// Body part by its Content-ID, e.g. for `cid:` references in SSML.
+    pub fn part(&self, content_id: &str) -> Option<&Part> {
+        self.parts().iter().find(|part| part.has_id(content_id))
+    }
+
+    /// Content with numbers, dates and the like spelled out in the speech language,
+    /// or in the default language of the normalizer.
+    pub fn normalized_content(
+        &self,
+        normalizer: &Normalizer,
+    ) -> Option<crate::Result<SpeakContent>> {
+        let content = self.content()?;
+        let lang = self
+            .speech_language
+            .as_ref()
+            .ok()
+            .and_then(|tag| Lang::from_tag(&tag.primary))
+            .unwrap_or(normalizer.lang());
+        Some(normalizer.normalize_content(content, lang))
     }
 }
 
+fn extract_content_length(request: *const uni::mrcp_message_t) -> crate::Result<usize> {
+    extract_generic(request, uni::GENERIC_HEADER_CONTENT_LENGTH, |header| {
+        Ok(header.content_length)
+    })
+}
+
 fn extract_voice_name(request: *const uni::mrcp_message_t) -> crate::Result<String> {
+    extract(request, uni::SYNTHESIZER_HEADER_VOICE_NAME, |header| {
+        apt_str_to_string(&header.voice_param.name)
+    })
+}
+
+fn extract_content(request: *const uni::mrcp_message_t) -> crate::Result<SpeakContent> {
+    let content_type = match extract_content_type(request) {
+        Ok(content_type) => Some(content_type),
+        Err(crate::Error::NoSuchHeader(_)) => None,
+        Err(err) => return Err(err),
+    };
+    SpeakContent::decode(content_type.as_ref(), extract_raw_body(request)?)
+}
+
+pub(super) fn extract_kill_on_barge_in(request: *const uni::mrcp_message_t) -> crate::Result<bool> {
+    extract(
+        request,
+        uni::SYNTHESIZER_HEADER_KILL_ON_BARGE_IN,
+        |header| Ok(header.kill_on_barge_in == uni::TRUE),
+    )
+}
+
+fn extract<T>(
+    request: *const uni::mrcp_message_t,
+    id: u32,
+    read: impl FnOnce(&uni::mrcp_synth_header_t) -> crate::Result<T>,
+) -> crate::Result<T> {
     if request.is_null() {
         return Err(crate::Error::NullRequest);
     }
     unsafe {
-        if inline_mrcp_resource_header_property_check(
-            request,
-            uni::SYNTHESIZER_HEADER_VOICE_NAME as _,
-        ) == uni::TRUE
-        {
+        if inline_mrcp_resource_header_property_check(request, id as _) == uni::TRUE {
             let synth_header =
-                inline_mrcp_resource_header_get(request) as *mut uni::mrcp_synth_header_t;
-            if synth_header.is_null() {
-                Err(crate::Error::NoSuchHeader(
-                    uni::SYNTHESIZER_HEADER_VOICE_NAME,
-                ))
-            } else {
-                apt_str_to_string(&(*synth_header).voice_param.name)
+                inline_mrcp_resource_header_get(request) as *const uni::mrcp_synth_header_t;
+            if !synth_header.is_null() {
+                return read(&*synth_header);
             }
-        } else {
-            Err(crate::Error::NoSuchHeader(
-                uni::SYNTHESIZER_HEADER_VOICE_NAME,
-            ))
         }
     }
-}
-
-fn extract_body(request: *const uni::mrcp_message_t) -> crate::Result<String> {
-    if request.is_null() {
-        return Err(crate::Error::NullRequest);
-    }
-    unsafe { apt_str_to_string(&(*request).body) }
+    Err(crate::Error::NoSuchHeader(id))
 }
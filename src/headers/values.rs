@@ -0,0 +1,97 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::apt_str_to_string;
+use crate::uni;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoiceGender {
+    Male,
+    Female,
+    Neutral,
+}
+
+impl VoiceGender {
+    pub(super) fn from_raw(gender: uni::mrcp_voice_gender_e) -> crate::Result<Self> {
+        match gender {
+            uni::VOICE_GENDER_MALE => Ok(VoiceGender::Male),
+            uni::VOICE_GENDER_FEMALE => Ok(VoiceGender::Female),
+            uni::VOICE_GENDER_NEUTRAL => Ok(VoiceGender::Neutral),
+            _ => Err(crate::Error::InvalidHeaderValue(
+                uni::SYNTHESIZER_HEADER_VOICE_GENDER,
+            )),
+        }
+    }
+
+    pub(super) fn to_raw(self) -> uni::mrcp_voice_gender_e {
+        match self {
+            VoiceGender::Male => uni::VOICE_GENDER_MALE,
+            VoiceGender::Female => uni::VOICE_GENDER_FEMALE,
+            VoiceGender::Neutral => uni::VOICE_GENDER_NEUTRAL,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeechUnit {
+    Second,
+    Word,
+    Sentence,
+    Paragraph,
+}
+
+/// Value of jump-size and speak-length headers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpeechLength {
+    /// Marker name to jump to.
+    Text(String),
+    /// Negative length means moving backward.
+    Numeric { length: i64, unit: SpeechUnit },
+}
+
+impl SpeechLength {
+    pub(super) fn from_raw(
+        value: &uni::mrcp_speech_length_value_t,
+        id: u32,
+    ) -> crate::Result<Self> {
+        unsafe {
+            match value.type_ {
+                uni::SPEECH_LENGTH_TYPE_TEXT => {
+                    Ok(SpeechLength::Text(apt_str_to_string(&value.value.tag)?))
+                }
+                sign @ (uni::SPEECH_LENGTH_TYPE_NUMERIC_POSITIVE
+                | uni::SPEECH_LENGTH_TYPE_NUMERIC_NEGATIVE) => {
+                    let numeric = value.value.numeric;
+                    let unit = match numeric.unit {
+                        uni::SPEECH_UNIT_SECOND => SpeechUnit::Second,
+                        uni::SPEECH_UNIT_WORD => SpeechUnit::Word,
+                        uni::SPEECH_UNIT_SENTENCE => SpeechUnit::Sentence,
+                        uni::SPEECH_UNIT_PARAGRAPH => SpeechUnit::Paragraph,
+                        _ => return Err(crate::Error::InvalidHeaderValue(id)),
+                    };
+                    let length = numeric.length as i64;
+                    Ok(SpeechLength::Numeric {
+                        length: if sign == uni::SPEECH_LENGTH_TYPE_NUMERIC_NEGATIVE {
+                            -length
+                        } else {
+                            length
+                        },
+                        unit,
+                    })
+                }
+                _ => Err(crate::Error::InvalidHeaderValue(id)),
+            }
+        }
+    }
+}
@@ -0,0 +1,461 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::assign;
+use crate::{inline_mrcp_generic_header_prepare, inline_mrcp_generic_header_property_add, uni};
+use std::{cell::RefCell, collections::BTreeSet, str::FromStr};
+
+/// Vendor-specific params in the order they were sent, a key may have several values.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VendorParams {
+    entries: Vec<(String, String)>,
+    skipped: Vec<SkippedParam>,
+}
+
+/// A param that was not valid UTF-8.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedParam {
+    pub key: Vec<u8>,
+    pub value: Vec<u8>,
+}
+
+impl SkippedParam {
+    pub fn key_lossy(&self) -> std::borrow::Cow<'_, str> {
+        String::from_utf8_lossy(&self.key)
+    }
+
+    pub fn value_lossy(&self) -> std::borrow::Cow<'_, str> {
+        String::from_utf8_lossy(&self.value)
+    }
+}
+
+impl VendorParams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn push(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.entries.push((key.into(), value.into()));
+    }
+
+    /// Replaces all the values of the key with the single one.
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        let key = key.into();
+        self.remove(&key);
+        self.entries.push((key, value.into()));
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        self.entries.retain(|(name, _)| name != key);
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.entries.iter().any(|(name, _)| name == key)
+    }
+
+    /// The last value of the key, as it would override the previous ones.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Distinct keys in the order of their first appearance.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        let mut seen = BTreeSet::new();
+        self.entries
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .filter(move |name| seen.insert(*name))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    pub fn skipped(&self) -> &[SkippedParam] {
+        &self.skipped
+    }
+
+    pub(super) fn push_skipped(&mut self, key: &[u8], value: &[u8]) {
+        self.skipped.push(SkippedParam {
+            key: key.to_vec(),
+            value: value.to_vec(),
+        });
+    }
+
+    /// Params of `self` completed with the keys of `other` that `self` lacks.
+    pub fn inherit(self, other: &VendorParams) -> VendorParams {
+        let mut entries = other
+            .entries
+            .iter()
+            .filter(|(name, _)| !self.contains_key(name))
+            .cloned()
+            .collect::<Vec<_>>();
+        entries.extend(self.entries);
+        VendorParams {
+            entries,
+            skipped: self.skipped,
+        }
+    }
+
+    /// Sets the params as the vendor-specific-parameters header of an outgoing message.
+    /// Skipped params are not written.
+    pub unsafe fn apply(&self, message: *mut uni::mrcp_message_t) -> crate::Result<()> {
+        if message.is_null() {
+            return Err(crate::Error::NullRequest);
+        }
+        if self.entries.is_empty() {
+            return Ok(());
+        }
+        let generic_header = inline_mrcp_generic_header_prepare(message);
+        if generic_header.is_null() {
+            return Err(crate::Error::NoSuchHeader(
+                uni::GENERIC_HEADER_VENDOR_SPECIFIC_PARAMS,
+            ));
+        }
+        let pool = (*message).pool;
+        let pairs = uni::apt_pair_array_create(self.entries.len() as _, pool);
+        for (name, value) in &self.entries {
+            let mut apt_name = std::mem::zeroed::<uni::apt_str_t>();
+            let mut apt_value = std::mem::zeroed::<uni::apt_str_t>();
+            assign(&mut apt_name, name, pool);
+            assign(&mut apt_value, value, pool);
+            uni::apt_pair_array_append(pairs, &apt_name, &apt_value, pool);
+        }
+        (*generic_header).vendor_specific_params = pairs;
+        inline_mrcp_generic_header_property_add(
+            message,
+            uni::GENERIC_HEADER_VENDOR_SPECIFIC_PARAMS as _,
+        );
+        Ok(())
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for VendorParams {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut params = VendorParams::new();
+        params.extend(iter);
+        params
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> Extend<(K, V)> for VendorParams {
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (key, value) in iter {
+            self.push(key, value);
+        }
+    }
+}
+
+impl IntoIterator for VendorParams {
+    type Item = (String, String);
+    type IntoIter = std::vec::IntoIter<(String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VendorParamError {
+    Unknown(String),
+    Missing(String),
+    Malformed { key: String, value: String },
+    Custom(String),
+}
+
+impl std::fmt::Display for VendorParamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VendorParamError::Unknown(key) => write!(f, "unknown vendor-specific param {key}"),
+            VendorParamError::Missing(key) => write!(f, "missing vendor-specific param {key}"),
+            VendorParamError::Malformed { key, value } => {
+                write!(f, "malformed vendor-specific param {key}={value}")
+            }
+            VendorParamError::Custom(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+/// Vendor-specific params whose keys start with the prefix, e.g. `com.optimalcity.`.
+#[derive(Debug)]
+pub struct VendorNamespace<'a> {
+    params: &'a VendorParams,
+    prefix: &'a str,
+    used: RefCell<BTreeSet<String>>,
+    errors: RefCell<Vec<VendorParamError>>,
+}
+
+/// A type that can be read from the params of a vendor namespace.
+pub trait FromVendorParams: Sized {
+    fn from_vendor_params(namespace: &VendorNamespace) -> Self;
+}
+
+impl<'a> VendorNamespace<'a> {
+    pub fn new(params: &'a VendorParams, prefix: &'a str) -> Self {
+        Self {
+            params,
+            prefix,
+            used: RefCell::new(BTreeSet::new()),
+            errors: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Parses the value of `name`, a malformed value is recorded as an error.
+    pub fn get<T: FromStr>(&self, name: &str) -> Option<T> {
+        let key = format!("{}{name}", self.prefix);
+        let value = self.params.get(&key)?;
+        self.mark_used(name);
+        match value.trim().parse() {
+            Ok(parsed) => Some(parsed),
+            Err(_) => {
+                self.errors.borrow_mut().push(VendorParamError::Malformed {
+                    key,
+                    value: value.to_owned(),
+                });
+                None
+            }
+        }
+    }
+
+    pub fn get_or<T: FromStr>(&self, name: &str, default: T) -> T {
+        self.get(name).unwrap_or(default)
+    }
+
+    /// Like `get` but an absent param is recorded as an error too.
+    pub fn require<T: FromStr>(&self, name: &str) -> Option<T> {
+        let key = format!("{}{name}", self.prefix);
+        if !self.params.contains_key(&key) {
+            self.errors
+                .borrow_mut()
+                .push(VendorParamError::Missing(key));
+            return None;
+        }
+        self.get(name)
+    }
+
+    /// Params of the namespace with the prefix stripped.
+    pub fn entries(&self) -> impl Iterator<Item = (&'a str, &'a str)> + '_ {
+        self.params
+            .iter()
+            .filter_map(|(key, value)| key.strip_prefix(self.prefix).map(|name| (name, value)))
+    }
+
+    /// Reports all the errors met, including params of the namespace that were never read.
+    pub fn finish(self) -> crate::Result<()> {
+        let used = self.used.into_inner();
+        let mut errors = self.errors.into_inner();
+        let mut unknown = self
+            .params
+            .keys()
+            .filter(|key| key.starts_with(self.prefix) && !used.contains(*key))
+            .map(ToOwned::to_owned)
+            .collect::<Vec<_>>();
+        unknown.sort();
+        errors.extend(unknown.into_iter().map(VendorParamError::Unknown));
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(crate::Error::VendorParams(errors))
+        }
+    }
+
+    fn mark_used(&self, name: &str) {
+        self.used
+            .borrow_mut()
+            .insert(format!("{}{name}", self.prefix));
+    }
+}
+
+/// Reads `T` from the vendor-specific params with the given key prefix.
+pub fn from_vendor_params<T: FromVendorParams>(
+    params: &VendorParams,
+    prefix: &str,
+) -> crate::Result<T> {
+    let namespace = VendorNamespace::new(params, prefix);
+    let value = T::from_vendor_params(&namespace);
+    namespace.finish()?;
+    Ok(value)
+}
+
+#[cfg(feature = "serde")]
+pub use de::from_vendor_params_serde;
+
+#[cfg(feature = "serde")]
+mod de {
+    use super::{VendorNamespace, VendorParamError, VendorParams};
+    use serde::de::{self, value::MapDeserializer, IntoDeserializer, Visitor};
+
+    /// Deserializes `T` from the vendor-specific params with the given key prefix.
+    /// Field names are the keys with the prefix stripped, e.g. `profanity-filter`.
+    pub fn from_vendor_params_serde<T: de::DeserializeOwned>(
+        params: &VendorParams,
+        prefix: &str,
+    ) -> crate::Result<T> {
+        let namespace = VendorNamespace::new(params, prefix);
+        let entries = namespace
+            .entries()
+            .map(|(name, value)| {
+                let value = Value {
+                    namespace: &namespace,
+                    name,
+                    value,
+                };
+                (name, value)
+            })
+            .collect::<Vec<_>>();
+        let value = T::deserialize(MapDeserializer::<_, Error>::new(entries.into_iter()))
+            .map_err(|e| crate::Error::VendorParams(vec![e.0]))?;
+        namespace.finish()?;
+        Ok(value)
+    }
+
+    #[derive(Debug)]
+    struct Error(VendorParamError);
+
+    impl std::fmt::Display for Error {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            self.0.fmt(f)
+        }
+    }
+
+    impl std::error::Error for Error {}
+
+    impl de::Error for Error {
+        fn custom<T: std::fmt::Display>(msg: T) -> Self {
+            Error(VendorParamError::Custom(msg.to_string()))
+        }
+
+        fn unknown_field(field: &str, _expected: &'static [&'static str]) -> Self {
+            Error(VendorParamError::Unknown(field.to_owned()))
+        }
+
+        fn missing_field(field: &'static str) -> Self {
+            Error(VendorParamError::Missing(field.to_owned()))
+        }
+    }
+
+    /// A param value, unless it is ignored by the target type the param is counted as known.
+    #[derive(Clone, Copy)]
+    struct Value<'a> {
+        namespace: &'a VendorNamespace<'a>,
+        name: &'a str,
+        value: &'a str,
+    }
+
+    impl Value<'_> {
+        fn parse<T: std::str::FromStr>(&self) -> Result<T, Error> {
+            self.namespace.mark_used(self.name);
+            self.value.trim().parse().map_err(|_| {
+                Error(VendorParamError::Malformed {
+                    key: format!("{}{}", self.namespace.prefix, self.name),
+                    value: self.value.to_owned(),
+                })
+            })
+        }
+    }
+
+    impl<'de> IntoDeserializer<'de, Error> for Value<'de> {
+        type Deserializer = Self;
+
+        fn into_deserializer(self) -> Self {
+            self
+        }
+    }
+
+    macro_rules! deserialize_parsed {
+        ($($method:ident => $visit:ident,)*) => {
+            $(
+                fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                    visitor.$visit(self.parse()?)
+                }
+            )*
+        };
+    }
+
+    impl<'de> de::Deserializer<'de> for Value<'de> {
+        type Error = Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            self.namespace.mark_used(self.name);
+            visitor.visit_borrowed_str(self.value)
+        }
+
+        fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            visitor.visit_unit()
+        }
+
+        deserialize_parsed! {
+            deserialize_bool => visit_bool,
+            deserialize_i8 => visit_i8,
+            deserialize_i16 => visit_i16,
+            deserialize_i32 => visit_i32,
+            deserialize_i64 => visit_i64,
+            deserialize_u8 => visit_u8,
+            deserialize_u16 => visit_u16,
+            deserialize_u32 => visit_u32,
+            deserialize_u64 => visit_u64,
+            deserialize_f32 => visit_f32,
+            deserialize_f64 => visit_f64,
+            deserialize_char => visit_char,
+        }
+
+        fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            visitor.visit_some(self)
+        }
+
+        fn deserialize_enum<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            _variants: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, Error> {
+            self.namespace.mark_used(self.name);
+            visitor.visit_enum(self.value.into_deserializer())
+        }
+
+        fn deserialize_newtype_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            visitor: V,
+        ) -> Result<V::Value, Error> {
+            visitor.visit_newtype_struct(self)
+        }
+
+        serde::forward_to_deserialize_any! {
+            i128 u128 str string bytes byte_buf unit unit_struct seq tuple
+            tuple_struct map struct identifier
+        }
+    }
+}
@@ -0,0 +1,280 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use crate::{engine, uni};
+
+/// BCP 47 language tag, e.g. `ru-RU` or `sr-Latn-RS`. Subtags are kept in canonical case.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LanguageTag {
+    pub primary: String,
+    pub script: Option<String>,
+    pub region: Option<String>,
+    /// Variants, extensions and private use subtags.
+    pub rest: Vec<String>,
+}
+
+impl LanguageTag {
+    /// The tag itself followed by the tags made by dropping the last subtag one by one,
+    /// e.g. `ru-Cyrl-RU`, `ru-Cyrl`, `ru`.
+    pub fn fallbacks(&self) -> impl Iterator<Item = LanguageTag> {
+        let mut next = Some(self.clone());
+        std::iter::from_fn(move || {
+            let current = next.take()?;
+            next = current.parent();
+            Some(current)
+        })
+    }
+
+    fn parent(&self) -> Option<LanguageTag> {
+        let mut parent = self.clone();
+        if parent.rest.pop().is_some() {
+            return Some(parent);
+        }
+        if parent.region.take().is_some() {
+            return Some(parent);
+        }
+        if parent.script.take().is_some() {
+            return Some(parent);
+        }
+        None
+    }
+}
+
+impl std::str::FromStr for LanguageTag {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        let invalid = || crate::Error::InvalidLanguageTag(s.to_owned());
+        let mut subtags = s.trim().split(['-', '_']).peekable();
+        let primary = subtags
+            .next()
+            .filter(|primary| {
+                (2..=8).contains(&primary.len()) && primary.chars().all(|c| c.is_ascii_alphabetic())
+            })
+            .ok_or_else(invalid)?
+            .to_ascii_lowercase();
+        let script = subtags
+            .next_if(|script| script.len() == 4 && script.chars().all(|c| c.is_ascii_alphabetic()))
+            .map(|script| {
+                let mut chars = script.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_ascii_uppercase())
+                    .into_iter()
+                    .chain(chars.map(|c| c.to_ascii_lowercase()))
+                    .collect()
+            });
+        let region = subtags
+            .next_if(|region| {
+                (region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic()))
+                    || (region.len() == 3 && region.chars().all(|c| c.is_ascii_digit()))
+            })
+            .map(|region| region.to_ascii_uppercase());
+        let rest = subtags
+            .map(|subtag| {
+                if (1..=8).contains(&subtag.len())
+                    && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+                {
+                    Ok(subtag.to_ascii_lowercase())
+                } else {
+                    Err(invalid())
+                }
+            })
+            .collect::<crate::Result<_>>()?;
+        Ok(Self {
+            primary,
+            script,
+            region,
+            rest,
+        })
+    }
+}
+
+impl std::fmt::Display for LanguageTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.primary)?;
+        for subtag in self
+            .script
+            .iter()
+            .chain(self.region.iter())
+            .chain(self.rest.iter())
+        {
+            write!(f, "-{subtag}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Maps language tags to backend models or voices.
+/// A tag is looked up with fallbacks, e.g. `ru-RU` → `ru` → default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LanguageRoutes {
+    routes: Vec<(LanguageTag, String)>,
+    default: Option<String>,
+}
+
+impl LanguageRoutes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads routes from the engine param in the form `ru-RU=model-ru;en=model-en;*=general`.
+    /// Absent param gives an empty table.
+    pub fn from_engine(engine: *const uni::mrcp_engine_t, key: &[u8]) -> crate::Result<Self> {
+        engine::get_param_or(engine, key, Self::default())
+    }
+
+    pub fn insert(&mut self, tag: LanguageTag, target: impl Into<String>) {
+        let target = target.into();
+        match self.routes.iter_mut().find(|(known, _)| *known == tag) {
+            Some((_, known_target)) => *known_target = target,
+            None => self.routes.push((tag, target)),
+        }
+    }
+
+    pub fn set_default(&mut self, target: impl Into<String>) {
+        self.default = Some(target.into());
+    }
+
+    pub fn default_target(&self) -> Option<&str> {
+        self.default.as_deref()
+    }
+
+    pub fn route(&self, tag: &LanguageTag) -> Option<&str> {
+        tag.fallbacks()
+            .find_map(|candidate| {
+                self.routes
+                    .iter()
+                    .find(|(known, _)| *known == candidate)
+                    .map(|(_, target)| target.as_str())
+            })
+            .or(self.default_target())
+    }
+
+    /// Routes the speech-language of a request, an absent or malformed one goes to the default.
+    pub fn route_header(&self, tag: &crate::Result<LanguageTag>) -> Option<&str> {
+        match tag {
+            Ok(tag) => self.route(tag),
+            Err(_) => self.default_target(),
+        }
+    }
+}
+
+impl std::str::FromStr for LanguageRoutes {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        let mut routes = Self::new();
+        for route in s.split([';', ',']).map(str::trim).filter(|r| !r.is_empty()) {
+            let (tag, target) = route
+                .split_once('=')
+                .ok_or_else(|| crate::Error::InvalidParamValue(route.to_owned()))?;
+            match tag.trim() {
+                "*" => routes.set_default(target.trim()),
+                tag => routes.insert(tag.parse()?, target.trim()),
+            }
+        }
+        Ok(routes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(tag: &str) -> LanguageTag {
+        tag.parse().unwrap()
+    }
+
+    #[test]
+    fn tags_are_canonical() {
+        let parsed = tag("SR_latn_rs-Ekavsk");
+        assert_eq!(parsed.primary, "sr");
+        assert_eq!(parsed.script.as_deref(), Some("Latn"));
+        assert_eq!(parsed.region.as_deref(), Some("RS"));
+        assert_eq!(parsed.rest, ["ekavsk"]);
+        assert_eq!(parsed.to_string(), "sr-Latn-RS-ekavsk");
+        assert_eq!(tag("es-419").region.as_deref(), Some("419"));
+        assert_eq!(tag(" ru-ru ").to_string(), "ru-RU");
+    }
+
+    #[test]
+    fn invalid_tags() {
+        for invalid in ["", "r", "ru1", "ru--RU", "ru-RU-toolongsubtag", "русский"] {
+            assert!(
+                matches!(
+                    invalid.parse::<LanguageTag>(),
+                    Err(crate::Error::InvalidLanguageTag(_))
+                ),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn fallbacks() {
+        let fallbacks: Vec<String> = tag("ru-Cyrl-RU-x-test")
+            .fallbacks()
+            .map(|tag| tag.to_string())
+            .collect();
+        assert_eq!(
+            fallbacks,
+            [
+                "ru-Cyrl-RU-x-test",
+                "ru-Cyrl-RU-x",
+                "ru-Cyrl-RU",
+                "ru-Cyrl",
+                "ru"
+            ]
+        );
+        assert_eq!(tag("en").fallbacks().count(), 1);
+    }
+
+    #[test]
+    fn routes() {
+        let routes: LanguageRoutes = "ru-RU=model-ru; en = model-en, *=default".parse().unwrap();
+        assert_eq!(routes.route(&tag("ru-ru")), Some("model-ru"));
+        assert_eq!(routes.route(&tag("en-GB")), Some("model-en"));
+        assert_eq!(routes.route(&tag("ru")), Some("default"));
+        assert_eq!(routes.route(&tag("de-DE")), Some("default"));
+        assert_eq!(routes.route_header(&Ok(tag("en"))), Some("model-en"));
+        assert_eq!(
+            routes.route_header(&Err(crate::Error::NullRequest)),
+            Some("default")
+        );
+    }
+
+    #[test]
+    fn routes_without_default() {
+        let mut routes: LanguageRoutes = "ru=a;ru-RU=b".parse().unwrap();
+        assert_eq!(routes.route(&tag("ru-BY")), Some("a"));
+        assert_eq!(routes.route(&tag("fr")), None);
+        routes.insert(tag("RU"), "c");
+        assert_eq!(routes.route(&tag("ru-BY")), Some("c"));
+        assert_eq!(routes.route(&tag("ru-RU")), Some("b"));
+        assert_eq!("".parse::<LanguageRoutes>().unwrap(), LanguageRoutes::new());
+    }
+
+    #[test]
+    fn invalid_routes() {
+        assert!(matches!(
+            "ru-RU".parse::<LanguageRoutes>(),
+            Err(crate::Error::InvalidParamValue(_))
+        ));
+        assert!(matches!(
+            "1=model".parse::<LanguageRoutes>(),
+            Err(crate::Error::InvalidLanguageTag(_))
+        ));
+    }
+}
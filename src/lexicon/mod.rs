@@ -0,0 +1,214 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use std::collections::HashMap;
+
+mod pls;
+mod store;
+pub use store::{LexiconSet, LexiconStore};
+
+pub const APPLICATION_PLS: &str = "application/pls+xml";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pronunciation {
+    Phoneme {
+        ph: String,
+        alphabet: String,
+    },
+    /// Text to be spoken instead of the grapheme.
+    Alias(String),
+}
+
+/// Lexeme of a lexicon, the preferred pronunciation goes first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lexeme {
+    pub graphemes: Vec<String>,
+    pub pronunciations: Vec<Pronunciation>,
+    pub role: Option<String>,
+}
+
+impl Lexeme {
+    pub fn preferred(&self) -> Option<&Pronunciation> {
+        self.pronunciations.first()
+    }
+
+    /// The first alias, the only kind of pronunciation plain text can carry.
+    pub fn alias(&self) -> Option<&str> {
+        self.pronunciations
+            .iter()
+            .find_map(|pronunciation| match pronunciation {
+                Pronunciation::Alias(alias) => Some(alias.as_str()),
+                Pronunciation::Phoneme { .. } => None,
+            })
+    }
+}
+
+/// Pronunciation lexicon with lookup by grapheme. Graphemes are matched by their words
+/// regardless of case and punctuation between them, `ё` matches `е`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lexicon {
+    /// Default alphabet of phonemes, e.g. `ipa`.
+    pub alphabet: String,
+    pub lang: Option<String>,
+    lexemes: Vec<Lexeme>,
+    index: HashMap<String, usize>,
+    max_words: usize,
+}
+
+impl Lexicon {
+    pub fn new(alphabet: impl Into<String>, lang: Option<String>) -> Self {
+        Self {
+            alphabet: alphabet.into(),
+            lang,
+            ..Default::default()
+        }
+    }
+
+    /// Parses a W3C Pronunciation Lexicon Specification document.
+    pub fn parse(source: &str) -> crate::Result<Self> {
+        pls::parse(source)
+    }
+
+    /// Adds the lexeme, graphemes defined earlier keep their lexeme.
+    pub fn add(&mut self, lexeme: Lexeme) {
+        let index = self.lexemes.len();
+        for grapheme in &lexeme.graphemes {
+            let key = key(grapheme);
+            if key.is_empty() {
+                continue;
+            }
+            self.max_words = self.max_words.max(key.split(' ').count());
+            self.index.entry(key).or_insert(index);
+        }
+        self.lexemes.push(lexeme);
+    }
+
+    pub fn lookup(&self, grapheme: &str) -> Option<&Lexeme> {
+        self.get(&key(grapheme))
+    }
+
+    pub fn lexemes(&self) -> &[Lexeme] {
+        &self.lexemes
+    }
+
+    pub fn len(&self) -> usize {
+        self.lexemes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lexemes.is_empty()
+    }
+
+    /// Whether the lexicon is meant for the language, by the primary subtag.
+    pub fn matches_lang(&self, lang: Option<&str>) -> bool {
+        let primary = |tag: &str| tag.split(['-', '_']).next().unwrap_or_default().to_owned();
+        match (self.lang.as_deref(), lang) {
+            (Some(own), Some(lang)) => primary(own).eq_ignore_ascii_case(&primary(lang)),
+            _ => true,
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&Lexeme> {
+        self.index.get(key).map(|index| &self.lexemes[*index])
+    }
+}
+
+impl std::str::FromStr for Lexicon {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        Self::parse(s)
+    }
+}
+
+/// Lookup key: lowercase words separated by single spaces.
+fn key(grapheme: &str) -> String {
+    words(grapheme)
+        .map(|(start, end)| normalize(&grapheme[start..end]))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn normalize(word: &str) -> String {
+    word.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| if c == 'ё' { 'е' } else { c })
+        .collect()
+}
+
+/// Byte ranges of the alphanumeric runs of the text.
+fn words(text: &str) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let mut chars = text.char_indices().peekable();
+    std::iter::from_fn(move || {
+        let (start, _) = chars.find(|(_, c)| c.is_alphanumeric())?;
+        let mut end = text.len();
+        while let Some(&(index, c)) = chars.peek() {
+            if !c.is_alphanumeric() {
+                end = index;
+                break;
+            }
+            chars.next();
+        }
+        Some((start, end))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alias(graphemes: &[&str], alias: &str) -> Lexeme {
+        Lexeme {
+            graphemes: graphemes.iter().map(|&g| g.to_owned()).collect(),
+            pronunciations: vec![Pronunciation::Alias(alias.to_owned())],
+            role: None,
+        }
+    }
+
+    #[test]
+    fn lookup_ignores_case_punctuation_and_yo() {
+        let mut lexicon = Lexicon::new("ipa", Some("ru-RU".to_owned()));
+        lexicon.add(alias(&["Ёлка"], "ель"));
+        lexicon.add(alias(&["New-York city"], "Нью-Йорк"));
+        assert_eq!(lexicon.lookup("елка").unwrap().alias(), Some("ель"));
+        assert_eq!(lexicon.lookup("ЁЛКА").unwrap().alias(), Some("ель"));
+        assert_eq!(
+            lexicon.lookup("new  york, City").unwrap().alias(),
+            Some("Нью-Йорк")
+        );
+        assert!(lexicon.lookup("york").is_none());
+    }
+
+    #[test]
+    fn earlier_graphemes_win() {
+        let mut lexicon = Lexicon::default();
+        lexicon.add(alias(&["SQL"], "sequel"));
+        lexicon.add(alias(&["sql", "psql"], "ess queue ell"));
+        assert_eq!(lexicon.lookup("sql").unwrap().alias(), Some("sequel"));
+        assert_eq!(
+            lexicon.lookup("psql").unwrap().alias(),
+            Some("ess queue ell")
+        );
+        assert_eq!(lexicon.len(), 2);
+    }
+
+    #[test]
+    fn languages() {
+        let lexicon = Lexicon::new("ipa", Some("en-US".to_owned()));
+        assert!(lexicon.matches_lang(Some("en_GB")));
+        assert!(lexicon.matches_lang(None));
+        assert!(!lexicon.matches_lang(Some("ru-RU")));
+        assert!(Lexicon::default().matches_lang(Some("ru-RU")));
+    }
+}
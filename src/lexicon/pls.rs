@@ -0,0 +1,165 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::{Lexeme, Lexicon, Pronunciation};
+use crate::ssml::{Element, Node};
+
+pub(super) fn parse(source: &str) -> crate::Result<Lexicon> {
+    let root = crate::ssml::parse_xml(source).map_err(|err| match err {
+        crate::Error::Ssml(err) => invalid(&err.to_string()),
+        err => err,
+    })?;
+    if local_name(&root.name) != "lexicon" {
+        return Err(invalid("root is not a lexicon"));
+    }
+    let mut lexicon = Lexicon::new(
+        root.attr("alphabet").unwrap_or("ipa"),
+        root.attr("xml:lang").map(ToOwned::to_owned),
+    );
+    for element in children(&root, "lexeme") {
+        let lexeme = lexeme(element, &lexicon.alphabet)?;
+        lexicon.add(lexeme);
+    }
+    Ok(lexicon)
+}
+
+fn lexeme(element: &Element, alphabet: &str) -> crate::Result<Lexeme> {
+    let graphemes: Vec<String> = children(element, "grapheme")
+        .map(text)
+        .filter(|grapheme| !grapheme.is_empty())
+        .collect();
+    if graphemes.is_empty() {
+        return Err(invalid("lexeme without grapheme"));
+    }
+    let mut pronunciations = Vec::new();
+    for child in element.children.iter().filter_map(Node::as_element) {
+        let pronunciation = match local_name(&child.name) {
+            "phoneme" => Pronunciation::Phoneme {
+                ph: text(child),
+                alphabet: child.attr("alphabet").unwrap_or(alphabet).to_owned(),
+            },
+            "alias" => Pronunciation::Alias(text(child)),
+            _ => continue,
+        };
+        pronunciations.push((child.attr("prefer") == Some("true"), pronunciation));
+    }
+    if pronunciations.is_empty() {
+        return Err(invalid(&format!(
+            "no pronunciation for \"{}\"",
+            graphemes[0]
+        )));
+    }
+    pronunciations.sort_by_key(|(prefer, _)| !prefer);
+    Ok(Lexeme {
+        graphemes,
+        pronunciations: pronunciations
+            .into_iter()
+            .map(|(_, pronunciation)| pronunciation)
+            .collect(),
+        role: element.attr("role").map(ToOwned::to_owned),
+    })
+}
+
+fn children<'a>(element: &'a Element, name: &'a str) -> impl Iterator<Item = &'a Element> {
+    element
+        .children
+        .iter()
+        .filter_map(Node::as_element)
+        .filter(move |child| local_name(&child.name) == name)
+}
+
+/// Name without the namespace prefix.
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+fn text(element: &Element) -> String {
+    let mut text = String::new();
+    for child in &element.children {
+        if let Node::Text { text: part, .. } = child {
+            text.push_str(part);
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn invalid(reason: &str) -> crate::Error {
+    crate::Error::InvalidLexicon(reason.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<lexicon version="1.0" xmlns="http://www.w3.org/2005/01/pronunciation-lexicon"
+    alphabet="x-sampa" xml:lang="en-US">
+  <lexeme>
+    <grapheme>W3C</grapheme>
+    <alias>World Wide Web Consortium</alias>
+  </lexeme>
+  <lexeme role="noun">
+    <grapheme>tomato</grapheme>
+    <grapheme>tomatoes</grapheme>
+    <phoneme>t@meItoU</phoneme>
+    <phoneme alphabet="ipa" prefer="true">təˈmɑːtəʊ</phoneme>
+  </lexeme>
+</lexicon>"#;
+
+    #[test]
+    fn aliases_and_phonemes() {
+        let lexicon = parse(PLS).unwrap();
+        assert_eq!(lexicon.alphabet, "x-sampa");
+        assert_eq!(lexicon.lang.as_deref(), Some("en-US"));
+        assert_eq!(lexicon.len(), 2);
+        assert_eq!(
+            lexicon.lookup("w3c").unwrap().preferred(),
+            Some(&Pronunciation::Alias(
+                "World Wide Web Consortium".to_owned()
+            ))
+        );
+        let tomato = lexicon.lookup("Tomatoes").unwrap();
+        assert_eq!(tomato.graphemes, ["tomato", "tomatoes"]);
+        assert_eq!(tomato.role.as_deref(), Some("noun"));
+        assert_eq!(tomato.alias(), None);
+        assert_eq!(
+            tomato.pronunciations,
+            [
+                Pronunciation::Phoneme {
+                    ph: "təˈmɑːtəʊ".to_owned(),
+                    alphabet: "ipa".to_owned()
+                },
+                Pronunciation::Phoneme {
+                    ph: "t@meItoU".to_owned(),
+                    alphabet: "x-sampa".to_owned()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_lexicons() {
+        for source in [
+            "<lexicon><lexeme>",
+            "<speak>Hello</speak>",
+            "<lexicon><lexeme><alias>x</alias></lexeme></lexicon>",
+            "<lexicon><lexeme><grapheme>x</grapheme></lexeme></lexicon>",
+        ] {
+            assert!(
+                matches!(parse(source), Err(crate::Error::InvalidLexicon(_))),
+                "{source}"
+            );
+        }
+    }
+}
@@ -0,0 +1,431 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::{normalize, words, Lexeme, Lexicon, Pronunciation, APPLICATION_PLS};
+use crate::{
+    headers::{strip_id, SynthHeaders},
+    ssml::{Document, Element, ElementKind, Node, Span},
+};
+
+/// Lexicons of a channel by the Content-ID they were defined with.
+#[derive(Debug, Clone, Default)]
+pub struct LexiconStore {
+    /// Lexicons in the order of definition.
+    lexicons: Vec<(String, Lexicon)>,
+}
+
+impl LexiconStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the lexicon, replacing the one with the same id.
+    pub fn insert(&mut self, id: impl Into<String>, lexicon: Lexicon) -> Option<Lexicon> {
+        let id = id.into();
+        let replaced = self.remove(&id);
+        self.lexicons.push((id, lexicon));
+        replaced
+    }
+
+    /// Removes the lexicon by its id, which may be a `session:` URI.
+    pub fn remove(&mut self, id: &str) -> Option<Lexicon> {
+        let id = strip_id(id);
+        let index = self.lexicons.iter().position(|(known, _)| known == id)?;
+        Some(self.lexicons.remove(index).1)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Lexicon> {
+        let id = strip_id(id);
+        self.lexicons
+            .iter()
+            .find(|(known, _)| known == id)
+            .map(|(_, lexicon)| lexicon)
+    }
+
+    pub fn clear(&mut self) {
+        self.lexicons.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lexicons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lexicons.is_empty()
+    }
+
+    /// Handles DEFINE-LEXICON: loads the PLS parts of the body under their Content-ID,
+    /// or unloads the lexicon if load-lexicon is false. Nothing is loaded if a part fails.
+    pub fn define(&mut self, headers: &SynthHeaders) -> crate::Result<()> {
+        if matches!(headers.load_lexicon, Ok(false)) {
+            let id = headers
+                .content_id
+                .as_deref()
+                .map_err(|_| invalid("no content-id to unload"))?;
+            self.remove(id);
+            return Ok(());
+        }
+        let parts = match &headers.parts {
+            Ok(parts) if parts.is_empty() => return Err(invalid("empty body")),
+            Ok(parts) => parts,
+            Err(err) => return Err(invalid(&err.to_string())),
+        };
+        let mut defined = Vec::with_capacity(parts.len());
+        for part in parts {
+            let media_type = part.content_type.media_type.as_str();
+            if !matches!(media_type, APPLICATION_PLS | "application/xml" | "text/xml") {
+                return Err(crate::Error::UnsupportedContentType(media_type.to_owned()));
+            }
+            let id = part
+                .content_id
+                .clone()
+                .ok_or_else(|| invalid("no content-id"))?;
+            defined.push((id, Lexicon::parse(&part.text()?)?));
+        }
+        for (id, lexicon) in defined {
+            self.insert(id, lexicon);
+        }
+        Ok(())
+    }
+
+    /// Lexicons in the order of the lexicon-search-order header, e.g.
+    /// `<session:streets@example.com> <session:brands@example.com>`.
+    /// Lexicons it does not list follow, the most recently defined first.
+    pub fn select(&self, search_order: Option<&str>) -> LexiconSet<'_> {
+        let mut lexicons: Vec<&Lexicon> = Vec::with_capacity(self.lexicons.len());
+        for id in search_order
+            .unwrap_or_default()
+            .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+            .filter(|id| !id.is_empty())
+        {
+            if let Some(lexicon) = self.get(id) {
+                if !lexicons.iter().any(|known| std::ptr::eq(*known, lexicon)) {
+                    lexicons.push(lexicon);
+                }
+            }
+        }
+        for (_, lexicon) in self.lexicons.iter().rev() {
+            if !lexicons.iter().any(|known| std::ptr::eq(*known, lexicon)) {
+                lexicons.push(lexicon);
+            }
+        }
+        LexiconSet {
+            lexicons,
+            lang: None,
+        }
+    }
+
+    pub fn lookup(&self, grapheme: &str) -> Option<&Lexeme> {
+        self.select(None).lookup(grapheme)
+    }
+}
+
+/// Ordered lexicons to look graphemes up in, the first lexicon having a grapheme wins.
+#[derive(Debug, Clone, Default)]
+pub struct LexiconSet<'a> {
+    lexicons: Vec<&'a Lexicon>,
+    lang: Option<String>,
+}
+
+impl<'a> LexiconSet<'a> {
+    pub fn new(lexicons: impl IntoIterator<Item = &'a Lexicon>) -> Self {
+        Self {
+            lexicons: lexicons.into_iter().collect(),
+            lang: None,
+        }
+    }
+
+    /// Language of the text, lexicons for other languages are skipped.
+    /// The `xml:lang` of SSML elements takes precedence.
+    pub fn lang(mut self, lang: Option<&str>) -> Self {
+        self.lang = lang.map(ToOwned::to_owned);
+        self
+    }
+
+    pub fn lookup(&self, grapheme: &str) -> Option<&'a Lexeme> {
+        self.lexicons
+            .iter()
+            .filter(|lexicon| lexicon.matches_lang(self.lang.as_deref()))
+            .find_map(|lexicon| lexicon.lookup(grapheme))
+    }
+
+    /// Replaces graphemes with their aliases, phonemes can not be put in plain text.
+    pub fn apply_text(&self, text: &str) -> String {
+        let mut applied = String::with_capacity(text.len());
+        let mut last = 0;
+        for (start, end, lexeme) in self.find(text, self.lang.as_deref()) {
+            if let Some(alias) = lexeme.alias() {
+                applied.push_str(&text[last..start]);
+                applied.push_str(alias);
+                last = end;
+            }
+        }
+        applied.push_str(&text[last..]);
+        applied
+    }
+
+    /// Wraps graphemes in `sub` or `phoneme` elements by their preferred pronunciation.
+    /// Text already inside `sub`, `phoneme` or `say-as` is left as is.
+    pub fn apply_document(&self, document: &mut Document) {
+        let lang = document
+            .lang()
+            .map(ToOwned::to_owned)
+            .or_else(|| self.lang.clone());
+        self.apply_element(&mut document.root, lang.as_deref());
+    }
+
+    /// Parses, applies and serializes the SSML document.
+    pub fn apply_ssml(&self, source: &str) -> crate::Result<String> {
+        let mut document = Document::parse(source)?;
+        self.apply_document(&mut document);
+        Ok(document.to_ssml())
+    }
+
+    fn apply_element(&self, element: &mut Element, lang: Option<&str>) {
+        if matches!(
+            element.kind,
+            ElementKind::Sub
+                | ElementKind::Phoneme
+                | ElementKind::SayAs
+                | ElementKind::Desc
+                | ElementKind::Lexicon
+                | ElementKind::Meta
+                | ElementKind::Metadata
+        ) {
+            return;
+        }
+        let own_lang = element.lang().map(ToOwned::to_owned);
+        let lang = own_lang.as_deref().or(lang);
+        let mut children = Vec::with_capacity(element.children.len());
+        for child in std::mem::take(&mut element.children) {
+            match child {
+                Node::Element(mut child) => {
+                    self.apply_element(&mut child, lang);
+                    children.push(Node::Element(child));
+                }
+                Node::Text { text, span } => self.split_text(text, span, lang, &mut children),
+            }
+        }
+        element.children = children;
+    }
+
+    fn split_text(&self, text: String, span: Span, lang: Option<&str>, nodes: &mut Vec<Node>) {
+        let found = self.find(&text, lang);
+        if found.is_empty() {
+            nodes.push(Node::Text { text, span });
+            return;
+        }
+        let mut last = 0;
+        for (start, end, lexeme) in found {
+            let Some(pronunciation) = lexeme.preferred() else {
+                continue;
+            };
+            if start > last {
+                nodes.push(Node::Text {
+                    text: text[last..start].to_owned(),
+                    span,
+                });
+            }
+            let mut element = match pronunciation {
+                Pronunciation::Alias(alias) => {
+                    let mut element = Element::new(ElementKind::Sub, "sub");
+                    element.set_attr("alias", alias.as_str());
+                    element
+                }
+                Pronunciation::Phoneme { ph, alphabet } => {
+                    let mut element = Element::new(ElementKind::Phoneme, "phoneme");
+                    element.set_attr("alphabet", alphabet.as_str());
+                    element.set_attr("ph", ph.as_str());
+                    element
+                }
+            };
+            element.span = span;
+            element.children.push(Node::Text {
+                text: text[start..end].to_owned(),
+                span,
+            });
+            nodes.push(Node::Element(element));
+            last = end;
+        }
+        if last < text.len() {
+            nodes.push(Node::Text {
+                text: text[last..].to_owned(),
+                span,
+            });
+        }
+    }
+
+    /// Byte ranges of the longest graphemes found in the text with their lexemes.
+    fn find(&self, text: &str, lang: Option<&str>) -> Vec<(usize, usize, &'a Lexeme)> {
+        let lexicons: Vec<&Lexicon> = self
+            .lexicons
+            .iter()
+            .copied()
+            .filter(|lexicon| lexicon.matches_lang(lang))
+            .collect();
+        let max_words = lexicons
+            .iter()
+            .map(|lexicon| lexicon.max_words)
+            .max()
+            .unwrap_or_default();
+        let words: Vec<(usize, usize)> = words(text).collect();
+        let keys: Vec<String> = words
+            .iter()
+            .map(|(start, end)| normalize(&text[*start..*end]))
+            .collect();
+        let mut found = Vec::new();
+        let mut index = 0;
+        while index < words.len() {
+            let longest = max_words.min(words.len() - index);
+            let matched = (1..=longest).rev().find_map(|count| {
+                let key = keys[index..index + count].join(" ");
+                lexicons
+                    .iter()
+                    .find_map(|lexicon| lexicon.get(&key))
+                    .map(|lexeme| (count, lexeme))
+            });
+            match matched {
+                Some((count, lexeme)) => {
+                    found.push((words[index].0, words[index + count - 1].1, lexeme));
+                    index += count;
+                }
+                None => index += 1,
+            }
+        }
+        found
+    }
+}
+
+fn invalid(reason: &str) -> crate::Error {
+    crate::Error::InvalidLexicon(reason.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headers::{ContentType, Part};
+
+    const STREETS: &str = "<lexicon version=\"1.0\" alphabet=\"ipa\">\
+                           <lexeme><grapheme>ул</grapheme><alias>улица</alias></lexeme>\
+                           <lexeme><grapheme>Ёж</grapheme><phoneme>jɵʂ</phoneme></lexeme>\
+                           </lexicon>";
+
+    fn lexicon(lang: &str, grapheme: &str, pronunciation: &str) -> String {
+        format!(
+            "<lexicon version=\"1.0\" alphabet=\"ipa\" xml:lang=\"{lang}\">\
+             <lexeme><grapheme>{grapheme}</grapheme>{pronunciation}</lexeme></lexicon>"
+        )
+    }
+
+    fn store() -> LexiconStore {
+        let mut store = LexiconStore::new();
+        let streets = lexicon("ru-RU", "ул", "<alias>улица</alias>");
+        let brands = lexicon("ru-RU", "ул", "<alias>Ульяновск</alias>");
+        store.insert("streets@example.com", Lexicon::parse(&streets).unwrap());
+        store.insert("brands@example.com", Lexicon::parse(&brands).unwrap());
+        store
+    }
+
+    fn headers() -> SynthHeaders {
+        SynthHeaders::new(std::ptr::null())
+    }
+
+    #[test]
+    fn search_order() {
+        let store = store();
+        assert_eq!(store.lookup("ул").unwrap().alias(), Some("Ульяновск"));
+        let set = store.select(Some("<session:streets@example.com>"));
+        assert_eq!(set.lookup("Ул").unwrap().alias(), Some("улица"));
+        let set = store.select(Some(
+            "<session:missing@example.com>, <session:brands@example.com>",
+        ));
+        assert_eq!(set.lookup("ул").unwrap().alias(), Some("Ульяновск"));
+        assert!(store
+            .select(None)
+            .lang(Some("en-US"))
+            .lookup("ул")
+            .is_none());
+    }
+
+    #[test]
+    fn define_and_unload() {
+        let mut store = LexiconStore::new();
+        let mut headers = headers();
+        headers.parts = Ok(vec![Part::new(
+            ContentType::new(APPLICATION_PLS),
+            Some("streets@example.com".to_owned()),
+            lexicon("ru-RU", "ул", "<alias>улица</alias>").into_bytes(),
+        )]);
+        store.define(&headers).unwrap();
+        assert!(store.get("session:streets@example.com").is_some());
+
+        headers.load_lexicon = Ok(false);
+        headers.content_id = Ok("<session:streets@example.com>".to_owned());
+        store.define(&headers).unwrap();
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn define_loads_all_parts_or_none() {
+        let mut store = LexiconStore::new();
+        let mut headers = headers();
+        headers.parts = Ok(vec![
+            Part::new(
+                ContentType::new(APPLICATION_PLS),
+                Some("good".to_owned()),
+                lexicon("en", "a", "<alias>b</alias>").into_bytes(),
+            ),
+            Part::new(
+                ContentType::new("text/plain"),
+                Some("bad".to_owned()),
+                b"hello".to_vec(),
+            ),
+        ]);
+        assert!(matches!(
+            store.define(&headers),
+            Err(crate::Error::UnsupportedContentType(_))
+        ));
+        assert!(store.is_empty());
+        headers.parts = Ok(Vec::new());
+        assert!(matches!(
+            store.define(&headers),
+            Err(crate::Error::InvalidLexicon(_))
+        ));
+    }
+
+    #[test]
+    fn apply_text_uses_aliases() {
+        let mut store = LexiconStore::new();
+        store.insert("a", Lexicon::parse(STREETS).unwrap());
+        assert_eq!(
+            store.select(None).apply_text("ул. Ленина, еж"),
+            "улица. Ленина, еж"
+        );
+    }
+
+    #[test]
+    fn apply_ssml_wraps_graphemes() {
+        let mut store = LexiconStore::new();
+        store.insert("a", Lexicon::parse(STREETS).unwrap());
+        let ssml = store
+            .select(None)
+            .apply_ssml("<speak>Еж на ул. Ленина <sub alias=\"улица\">ул</sub></speak>")
+            .unwrap();
+        assert_eq!(
+            ssml,
+            "<speak><phoneme alphabet=\"ipa\" ph=\"jɵʂ\">Еж</phoneme> на \
+             <sub alias=\"улица\">ул</sub>. Ленина <sub alias=\"улица\">ул</sub></speak>"
+        );
+    }
+}
@@ -13,9 +13,15 @@
 //    limitations under the License.
 
 #![allow(clippy::missing_safety_doc)]
+pub mod audio;
 pub mod engine;
 mod error;
 pub mod headers;
+pub mod language;
+pub mod lexicon;
+pub mod ssml;
+pub mod synthesizer;
+pub mod text;
 pub mod uni;
 
 pub use error::{Error, Result};
@@ -61,6 +67,21 @@ pub unsafe fn inline_mpf_codec_capabilities_add(
     capabilities: *mut uni::mpf_codec_capabilities_t,
     sample_rates: std::os::raw::c_int,
     codec_name: *const i8,
+) -> uni::apt_bool_t {
+    // In version 1.8.0 was introduced 'frame_duration' codec property. 10 ms was hardcoded in earlier versions
+    inline_mpf_codec_capabilities_add_with_frame_duration(
+        capabilities,
+        sample_rates,
+        codec_name,
+        uni::CODEC_FRAME_TIME_BASE as _,
+    )
+}
+
+pub unsafe fn inline_mpf_codec_capabilities_add_with_frame_duration(
+    capabilities: *mut uni::mpf_codec_capabilities_t,
+    sample_rates: std::os::raw::c_int,
+    codec_name: *const i8,
+    frame_duration: uni::apr_size_t,
 ) -> uni::apt_bool_t {
     let attribs = uni::apr_array_push((*capabilities).attrib_arr) as *mut uni::mpf_codec_attribs_t;
     inline_apt_string_assign(
@@ -70,10 +91,28 @@ pub unsafe fn inline_mpf_codec_capabilities_add(
     );
     (*attribs).sample_rates = sample_rates;
     (*attribs).bits_per_sample = 0;
-    (*attribs).frame_duration = uni::CODEC_FRAME_TIME_BASE as _; // In version 1.8.0 was introduced 'frame_duration' codec property. 10 ms was hardcoded in earlier versions
+    (*attribs).frame_duration = frame_duration;
     uni::TRUE
 }
 
+pub unsafe fn inline_mrcp_engine_source_stream_codec_get(
+    channel: *const uni::mrcp_engine_channel_t,
+) -> *const uni::mpf_codec_descriptor_t {
+    if channel.is_null() || (*channel).termination.is_null() {
+        return std::ptr::null();
+    }
+    uni::mpf_termination_source_stream_codec_get((*channel).termination)
+}
+
+pub unsafe fn inline_mrcp_engine_sink_stream_codec_get(
+    channel: *const uni::mrcp_engine_channel_t,
+) -> *const uni::mpf_codec_descriptor_t {
+    if channel.is_null() || (*channel).termination.is_null() {
+        return std::ptr::null();
+    }
+    uni::mpf_termination_sink_stream_codec_get((*channel).termination)
+}
+
 pub unsafe fn inline_apt_string_assign(
     str: *mut uni::apt_str_t,
     src: *const i8,
@@ -138,6 +177,24 @@ pub unsafe fn inline_mrcp_resource_header_property_check(
     )
 }
 
+pub unsafe fn inline_mrcp_resource_header_property_add(
+    message: *mut uni::mrcp_message_t,
+    id: uni::apr_size_t,
+) -> uni::apt_bool_t {
+    uni::mrcp_header_property_add(
+        &mut (*message).header as _,
+        id + uni::GENERIC_HEADER_COUNT as usize,
+        (*message).pool,
+    )
+}
+
+pub unsafe fn inline_mrcp_generic_header_property_add(
+    message: *mut uni::mrcp_message_t,
+    id: uni::apr_size_t,
+) -> uni::apt_bool_t {
+    uni::mrcp_header_property_add(&mut (*message).header as _, id, (*message).pool)
+}
+
 pub unsafe fn inline_mrcp_resource_header_prepare(
     mrcp_message: *mut uni::mrcp_message_t,
 ) -> *mut libc::c_void {
@@ -0,0 +1,427 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use crate::{
+    headers::{RateLabel, VolumeLabel},
+    uni,
+};
+
+mod parser;
+mod render;
+mod rewrite;
+pub use rewrite::{
+    Action, ClampProsody, RewriteRule, Rewriter, SayAsRule, StripUnsupported, VoiceMap,
+};
+mod write;
+pub use write::escape_text;
+
+/// Byte range in the source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ElementKind {
+    Speak,
+    Paragraph,
+    Sentence,
+    Break,
+    Prosody,
+    SayAs,
+    Voice,
+    Mark,
+    Audio,
+    Desc,
+    Sub,
+    Phoneme,
+    Emphasis,
+    Lang,
+    Lexicon,
+    Meta,
+    Metadata,
+    /// Extension or misspelled element, see `Element::name`.
+    Unknown,
+}
+
+impl ElementKind {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "speak" => ElementKind::Speak,
+            "p" | "paragraph" => ElementKind::Paragraph,
+            "s" | "sentence" => ElementKind::Sentence,
+            "break" => ElementKind::Break,
+            "prosody" => ElementKind::Prosody,
+            "say-as" => ElementKind::SayAs,
+            "voice" => ElementKind::Voice,
+            "mark" => ElementKind::Mark,
+            "audio" => ElementKind::Audio,
+            "desc" => ElementKind::Desc,
+            "sub" => ElementKind::Sub,
+            "phoneme" => ElementKind::Phoneme,
+            "emphasis" => ElementKind::Emphasis,
+            "lang" => ElementKind::Lang,
+            "lexicon" => ElementKind::Lexicon,
+            "meta" => ElementKind::Meta,
+            "metadata" => ElementKind::Metadata,
+            _ => ElementKind::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Element {
+    pub kind: ElementKind,
+    pub name: String,
+    /// Attributes in document order with entities resolved.
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Node>,
+    pub span: Span,
+}
+
+impl Element {
+    pub fn new(kind: ElementKind, name: impl Into<String>) -> Self {
+        Self {
+            kind,
+            name: name.into(),
+            attributes: Vec::new(),
+            children: Vec::new(),
+            span: Span::default(),
+        }
+    }
+
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(known, _)| known == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_attr(&mut self, name: &str, value: impl Into<String>) {
+        let value = value.into();
+        match self.attributes.iter_mut().find(|(known, _)| known == name) {
+            Some((_, known_value)) => *known_value = value,
+            None => self.attributes.push((name.to_owned(), value)),
+        }
+    }
+
+    pub fn remove_attr(&mut self, name: &str) -> Option<String> {
+        let index = self
+            .attributes
+            .iter()
+            .position(|(known, _)| known == name)?;
+        Some(self.attributes.remove(index).1)
+    }
+
+    /// Value of `xml:lang`, which the speak, voice, lang, p and s elements may carry.
+    pub fn lang(&self) -> Option<&str> {
+        self.attr("xml:lang")
+    }
+
+    /// Text to be spoken for the element, see `Document::plain_text`.
+    pub fn plain_text(&self) -> String {
+        render::plain_text(self)
+    }
+
+    /// Duration of a break element in milliseconds if the time is given.
+    pub fn break_time_millis(&self) -> Option<usize> {
+        self.attr("time").and_then(parse_time_millis)
+    }
+
+    /// Elements in document order, the element itself first.
+    pub fn descendants(&self) -> impl Iterator<Item = &Element> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let element = stack.pop()?;
+            stack.extend(element.children.iter().rev().filter_map(Node::as_element));
+            Some(element)
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    Text { text: String, span: Span },
+    Element(Element),
+}
+
+impl Node {
+    pub fn as_element(&self) -> Option<&Element> {
+        match self {
+            Node::Element(element) => Some(element),
+            Node::Text { .. } => None,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Node::Text { span, .. } => *span,
+            Node::Element(element) => element.span,
+        }
+    }
+}
+
+/// Parsed SSML document, the root is the speak element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Document {
+    pub root: Element,
+    source: String,
+}
+
+impl Document {
+    pub fn parse(source: &str) -> crate::Result<Self> {
+        parser::parse(source)
+    }
+
+    /// Text the document was parsed from, spans refer to it.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Serializes the document back to SSML.
+    pub fn to_ssml(&self) -> String {
+        self.root.to_string()
+    }
+
+    pub fn lang(&self) -> Option<&str> {
+        self.root.lang()
+    }
+
+    /// Text to be spoken for backends without SSML support.
+    pub fn plain_text(&self) -> String {
+        render::plain_text(&self.root)
+    }
+
+    /// Marks with the share of the spoken text before them, from 0.0 to 1.0,
+    /// for estimating their audio offsets when the backend does not report them.
+    pub fn mark_positions(&self) -> Vec<(String, f64)> {
+        render::mark_positions(&self.root)
+    }
+
+    /// Names of mark elements in document order.
+    pub fn marks(&self) -> impl Iterator<Item = &str> {
+        self.root
+            .descendants()
+            .filter(|element| element.kind == ElementKind::Mark)
+            .filter_map(|element| element.attr("name"))
+    }
+}
+
+/// Parses a well-formed XML document with any root element, e.g. a PLS lexicon.
+pub(crate) fn parse_xml(source: &str) -> crate::Result<Element> {
+    parser::parse_element(source)
+}
+
+impl std::str::FromStr for Document {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        Self::parse(s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    UnexpectedEof,
+    UnexpectedChar(char),
+    InvalidName,
+    MismatchedTag,
+    DuplicateAttribute,
+    UnknownEntity,
+    NotSpeak,
+    TrailingContent,
+    TooDeep,
+    /// Element the target dialect does not support.
+    UnsupportedElement,
+    /// Attribute value that can not be interpreted.
+    InvalidValue,
+}
+
+/// Classification of SSML parse failures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseFailure {
+    /// Not well-formed XML.
+    Malformed,
+    /// Well-formed XML which is not an SSML document.
+    Structure,
+    /// Values or elements that can not be synthesized.
+    Content,
+}
+
+impl ParseErrorKind {
+    pub fn failure(&self) -> ParseFailure {
+        match self {
+            ParseErrorKind::UnexpectedEof
+            | ParseErrorKind::UnexpectedChar(_)
+            | ParseErrorKind::InvalidName
+            | ParseErrorKind::MismatchedTag
+            | ParseErrorKind::DuplicateAttribute
+            | ParseErrorKind::UnknownEntity
+            | ParseErrorKind::TrailingContent => ParseFailure::Malformed,
+            ParseErrorKind::NotSpeak | ParseErrorKind::TooDeep => ParseFailure::Structure,
+            ParseErrorKind::UnsupportedElement | ParseErrorKind::InvalidValue => {
+                ParseFailure::Content
+            }
+        }
+    }
+}
+
+/// SSML syntax error with the position where it was found, line and column start from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl ParseError {
+    pub(crate) fn new(kind: ParseErrorKind, source: &str, offset: usize) -> Self {
+        let before = &source[..offset];
+        Self {
+            kind,
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before.chars().rev().take_while(|c| *c != '\n').count() + 1,
+        }
+    }
+
+    pub fn failure(&self) -> ParseFailure {
+        self.kind.failure()
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} at {}:{}", self.kind, self.line, self.column)
+    }
+}
+
+/// Parses SSML time designations such as `250ms`, `1.5s` or `2`, which is taken as seconds.
+pub fn parse_time_millis(time: &str) -> Option<usize> {
+    let time = time.trim();
+    let (number, scale) = if let Some(number) = time.strip_suffix("ms") {
+        (number, 1.0)
+    } else if let Some(number) = time.strip_suffix('s') {
+        (number, 1000.0)
+    } else {
+        (time, 1000.0)
+    };
+    let number: f64 = number.trim().parse().ok()?;
+    if !number.is_finite() || number < 0.0 {
+        return None;
+    }
+    Some((number * scale).round() as usize)
+}
+
+/// Completion cause of a SPEAK request that failed with the error: errors of SSML,
+/// content type and charset give parse-failure, failed fetches give uri-failure,
+/// the others give error.
+pub fn completion_cause(error: &crate::Error) -> uni::mrcp_synth_completion_cause_e {
+    match error {
+        crate::Error::Ssml(_)
+        | crate::Error::Utf(_)
+        | crate::Error::InvalidText(_)
+        | crate::Error::UnsupportedCharset(_)
+        | crate::Error::InvalidContentType(_)
+        | crate::Error::InvalidMultipart(_)
+        | crate::Error::UnsupportedContentType(_) => {
+            uni::SYNTHESIZER_COMPLETION_CAUSE_PARSE_FAILURE
+        }
+        crate::Error::InvalidLexicon(_) => uni::SYNTHESIZER_COMPLETION_CAUSE_LEXICON_LOAD_FAILURE,
+        crate::Error::FetchFailure(_) => uni::SYNTHESIZER_COMPLETION_CAUSE_URI_FAILURE,
+        _ => uni::SYNTHESIZER_COMPLETION_CAUSE_ERROR,
+    }
+}
+
+/// Gain in dB of the SSML volume attribute: a label, `+6dB`, a number from 0 to 100
+/// or a signed relative change on that scale.
+pub fn volume_gain_db(volume: &str) -> Option<f64> {
+    let volume = volume.trim();
+    if let Ok(label) = volume.parse::<VolumeLabel>() {
+        return Some(label.gain_db());
+    }
+    if let Some(db) = volume.strip_suffix("dB") {
+        return db.trim().parse().ok().filter(|db: &f64| db.is_finite());
+    }
+    let number: f64 = volume.parse().ok().filter(|n: &f64| n.is_finite())?;
+    let amplitude = if volume.starts_with(['+', '-']) {
+        (100.0 + number) / 100.0
+    } else {
+        number / 100.0
+    };
+    Some(if amplitude <= 0.0 {
+        f64::NEG_INFINITY
+    } else {
+        20.0 * amplitude.log10()
+    })
+}
+
+/// Multiplier of the SSML rate attribute: a label, a percentage, a signed relative change
+/// like `+10%` or a plain multiplier.
+pub fn rate_multiplier(rate: &str) -> Option<f64> {
+    let rate = rate.trim();
+    if let Ok(label) = rate.parse::<RateLabel>() {
+        return Some(label.multiplier());
+    }
+    let relative = rate.starts_with(['+', '-']);
+    let (number, scale) = match rate.strip_suffix('%') {
+        Some(percent) => (percent.trim(), 100.0),
+        None => (rate, 1.0),
+    };
+    let number: f64 = number.parse().ok().filter(|n: &f64| n.is_finite())?;
+    let multiplier = if relative {
+        1.0 + number / scale
+    } else {
+        number / scale
+    };
+    Some(multiplier.max(0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.unwrap();
+        assert!((actual - expected).abs() < 0.01, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn volume_attributes() {
+        assert_eq!(volume_gain_db("loud"), Some(6.0));
+        assert_eq!(volume_gain_db(" +6dB "), Some(6.0));
+        assert_eq!(volume_gain_db("-3.5dB"), Some(-3.5));
+        assert_close(volume_gain_db("50"), -6.02);
+        assert_eq!(volume_gain_db("100"), Some(0.0));
+        assert_close(volume_gain_db("+100"), 6.02);
+        assert_eq!(volume_gain_db("-100"), Some(f64::NEG_INFINITY));
+        assert_eq!(volume_gain_db("silent"), Some(f64::NEG_INFINITY));
+        assert_eq!(volume_gain_db("loudly"), None);
+        assert_eq!(volume_gain_db("infdB"), None);
+    }
+
+    #[test]
+    fn rate_attributes() {
+        assert_eq!(rate_multiplier("fast"), Some(1.25));
+        assert_close(rate_multiplier("+10%"), 1.1);
+        assert_close(rate_multiplier("-25%"), 0.75);
+        assert_eq!(rate_multiplier("150%"), Some(1.5));
+        assert_eq!(rate_multiplier("0.8"), Some(0.8));
+        assert_eq!(rate_multiplier("-2"), Some(0.0));
+        assert_eq!(rate_multiplier("-150%"), Some(0.0));
+        assert_eq!(rate_multiplier("quick"), None);
+    }
+}
@@ -0,0 +1,432 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::{Document, Element, ElementKind, Node, ParseError, ParseErrorKind, Span};
+
+const MAX_DEPTH: usize = 64;
+
+pub(super) fn parse(source: &str) -> crate::Result<Document> {
+    let mut parser = Parser { source, pos: 0 };
+    parser
+        .parse()
+        .map_err(|(kind, offset)| crate::Error::Ssml(ParseError::new(kind, source, offset)))
+}
+
+/// Parses an XML document with any root element.
+pub(super) fn parse_element(source: &str) -> crate::Result<Element> {
+    let mut parser = Parser { source, pos: 0 };
+    parser
+        .root()
+        .map(|(root, _)| root)
+        .map_err(|(kind, offset)| crate::Error::Ssml(ParseError::new(kind, source, offset)))
+}
+
+type Result<T> = std::result::Result<T, (ParseErrorKind, usize)>;
+
+struct Parser<'a> {
+    source: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn parse(&mut self) -> Result<Document> {
+        let (root, start) = self.root()?;
+        if root.kind != ElementKind::Speak {
+            return Err((ParseErrorKind::NotSpeak, start));
+        }
+        Ok(Document {
+            root,
+            source: self.source.to_owned(),
+        })
+    }
+
+    /// The root element and its offset.
+    fn root(&mut self) -> Result<(Element, usize)> {
+        self.skip_misc()?;
+        if !self.rest().starts_with('<') {
+            return Err(self.unexpected());
+        }
+        let start = self.pos;
+        let root = self.element(0)?;
+        self.skip_misc()?;
+        if self.pos < self.source.len() {
+            return Err((ParseErrorKind::TrailingContent, self.pos));
+        }
+        Ok((root, start))
+    }
+
+    fn rest(&self) -> &str {
+        &self.source[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn unexpected(&self) -> (ParseErrorKind, usize) {
+        match self.peek() {
+            Some(c) => (ParseErrorKind::UnexpectedChar(c), self.pos),
+            None => (ParseErrorKind::UnexpectedEof, self.pos),
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<()> {
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Skips everything up to and including `end`.
+    fn skip_past(&mut self, end: &str) -> Result<()> {
+        match self.rest().find(end) {
+            Some(offset) => {
+                self.pos += offset + end.len();
+                Ok(())
+            }
+            None => Err((ParseErrorKind::UnexpectedEof, self.source.len())),
+        }
+    }
+
+    /// Skips whitespace, comments, processing instructions and the doctype.
+    fn skip_misc(&mut self) -> Result<()> {
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<!DOCTYPE") {
+                self.skip_doctype()?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn skip_doctype(&mut self) -> Result<()> {
+        let mut in_subset = false;
+        for (offset, c) in self.rest().char_indices() {
+            match c {
+                '[' => in_subset = true,
+                ']' => in_subset = false,
+                '>' if !in_subset => {
+                    self.pos += offset + 1;
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+        Err((ParseErrorKind::UnexpectedEof, self.source.len()))
+    }
+
+    fn name(&mut self) -> Result<&str> {
+        let start = self.pos;
+        let rest = self.rest();
+        let len = rest
+            .char_indices()
+            .find(|&(offset, c)| {
+                !(c.is_alphabetic()
+                    || c == '_'
+                    || c == ':'
+                    || (offset > 0 && (c.is_ascii_digit() || c == '-' || c == '.')))
+            })
+            .map(|(offset, _)| offset)
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(match self.peek() {
+                Some(_) => (ParseErrorKind::InvalidName, start),
+                None => (ParseErrorKind::UnexpectedEof, start),
+            });
+        }
+        self.pos += len;
+        Ok(&self.source[start..self.pos])
+    }
+
+    fn element(&mut self, depth: usize) -> Result<Element> {
+        let start = self.pos;
+        if depth >= MAX_DEPTH {
+            return Err((ParseErrorKind::TooDeep, start));
+        }
+        self.expect("<")?;
+        let name = self.name()?.to_owned();
+        let mut element = Element::new(ElementKind::from_name(&name), name);
+        loop {
+            let before_space = self.pos;
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.pos += 2;
+                element.span = Span {
+                    start,
+                    end: self.pos,
+                };
+                return Ok(element);
+            }
+            if self.rest().starts_with('>') {
+                self.pos += 1;
+                break;
+            }
+            if self.pos == before_space {
+                return Err(self.unexpected());
+            }
+            let attr_start = self.pos;
+            let attr = self.name()?.to_owned();
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let value = self.attribute_value()?;
+            if element.attr(&attr).is_some() {
+                return Err((ParseErrorKind::DuplicateAttribute, attr_start));
+            }
+            element.attributes.push((attr, value));
+        }
+        self.content(&mut element, depth)?;
+        self.skip_whitespace();
+        self.expect(">")?;
+        element.span = Span {
+            start,
+            end: self.pos,
+        };
+        Ok(element)
+    }
+
+    /// Reads children up to and including the name of the end tag.
+    fn content(&mut self, element: &mut Element, depth: usize) -> Result<()> {
+        loop {
+            let rest = self.rest();
+            if rest.is_empty() {
+                return Err((ParseErrorKind::UnexpectedEof, self.pos));
+            } else if rest.starts_with("</") {
+                let end_start = self.pos;
+                self.pos += 2;
+                if self.name()? != element.name {
+                    return Err((ParseErrorKind::MismatchedTag, end_start));
+                }
+                return Ok(());
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<![CDATA[") {
+                let start = self.pos;
+                self.pos += "<![CDATA[".len();
+                let text_start = self.pos;
+                self.skip_past("]]>")?;
+                let text = &self.source[text_start..self.pos - "]]>".len()];
+                push_text(element, text.to_owned(), start, self.pos);
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with('<') {
+                let child = self.element(depth + 1)?;
+                element.children.push(Node::Element(child));
+            } else {
+                let start = self.pos;
+                let len = rest.find('<').unwrap_or(rest.len());
+                self.pos += len;
+                let text = unescape(&self.source[start..self.pos], start)?;
+                push_text(element, text, start, self.pos);
+            }
+        }
+    }
+
+    fn attribute_value(&mut self) -> Result<String> {
+        let quote = match self.peek() {
+            Some(quote @ ('"' | '\'')) => quote,
+            _ => return Err(self.unexpected()),
+        };
+        self.pos += 1;
+        let start = self.pos;
+        let len = self
+            .rest()
+            .find(quote)
+            .ok_or((ParseErrorKind::UnexpectedEof, self.source.len()))?;
+        let raw = &self.source[start..start + len];
+        if let Some(offset) = raw.find('<') {
+            return Err((ParseErrorKind::UnexpectedChar('<'), start + offset));
+        }
+        self.pos += len + 1;
+        unescape(raw, start)
+    }
+}
+
+fn push_text(element: &mut Element, text: String, start: usize, end: usize) {
+    if let Some(Node::Text {
+        text: last,
+        span: last_span,
+    }) = element.children.last_mut()
+    {
+        if last_span.end == start {
+            last.push_str(&text);
+            last_span.end = end;
+            return;
+        }
+    }
+    element.children.push(Node::Text {
+        text,
+        span: Span { start, end },
+    });
+}
+
+/// Resolves the predefined and numeric character references.
+fn unescape(raw: &str, offset: usize) -> Result<String> {
+    let mut text = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(amp) = rest.find('&') {
+        text.push_str(&rest[..amp]);
+        let entity_offset = offset + (raw.len() - rest.len()) + amp;
+        let unknown = (ParseErrorKind::UnknownEntity, entity_offset);
+        let end = rest[amp..].find(';').ok_or(unknown)?;
+        let entity = &rest[amp + 1..amp + end];
+        let c = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32)
+                .ok_or(unknown)?,
+        };
+        text.push(c);
+        rest = &rest[amp + end + 1..];
+    }
+    text.push_str(rest);
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SSML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<speak version="1.0" xmlns="http://www.w3.org/2001/10/synthesis" xml:lang="ru-RU">
+  <!-- greeting -->
+  <p><s>Hello &amp; <emphasis level="strong">welcome</emphasis></s></p>
+  <break time="500ms"/>
+  <say-as interpret-as="date" format="dmy">12.05.2024</say-as>
+  <mark name="end"/><![CDATA[a < b]]>
+</speak>"#;
+
+    fn error_kind(source: &str) -> ParseErrorKind {
+        match parse(source) {
+            Err(crate::Error::Ssml(error)) => error.kind,
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn document() {
+        let document = parse(SSML).unwrap();
+        assert_eq!(document.lang(), Some("ru-RU"));
+        assert_eq!(
+            document.plain_text().split_whitespace().collect::<Vec<_>>(),
+            ["Hello", "&", "welcome", "12.05.2024", "a", "<", "b"]
+        );
+        assert_eq!(document.marks().collect::<Vec<_>>(), ["end"]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let document = parse(SSML).unwrap();
+        let ssml = document.to_ssml();
+        let reparsed = parse(&ssml).unwrap();
+        assert_eq!(reparsed.to_ssml(), ssml);
+        assert_eq!(reparsed.lang(), Some("ru-RU"));
+        assert_eq!(
+            reparsed.plain_text().split_whitespace().collect::<Vec<_>>(),
+            document.plain_text().split_whitespace().collect::<Vec<_>>()
+        );
+        assert_eq!(reparsed.marks().collect::<Vec<_>>(), ["end"]);
+    }
+
+    #[test]
+    fn elements_and_entities() {
+        let document = parse(SSML).unwrap();
+        let kinds: Vec<ElementKind> = document.root.descendants().map(|e| e.kind).collect();
+        assert!(kinds.contains(&ElementKind::SayAs));
+        assert!(kinds.contains(&ElementKind::Break));
+        let emphasis = document
+            .root
+            .descendants()
+            .find(|element| element.kind == ElementKind::Emphasis)
+            .unwrap();
+        assert_eq!(emphasis.attr("level"), Some("strong"));
+        assert_eq!(emphasis.plain_text(), "welcome");
+        assert!(document.plain_text().contains("Hello & welcome"));
+        assert!(document.plain_text().contains("a < b"));
+    }
+
+    #[test]
+    fn spans_refer_to_the_source() {
+        let document = parse(SSML).unwrap();
+        let say_as = document
+            .root
+            .descendants()
+            .find(|element| element.kind == ElementKind::SayAs)
+            .unwrap();
+        let Span { start, end } = say_as.span;
+        assert!(document.source()[start..end].starts_with("<say-as"));
+        assert!(document.source()[start..end].ends_with("</say-as>"));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            error_kind("<speak><p></speak>"),
+            ParseErrorKind::MismatchedTag
+        );
+        assert_eq!(error_kind("<voice>text</voice>"), ParseErrorKind::NotSpeak);
+        assert_eq!(
+            error_kind("<speak>&nbsp;</speak>"),
+            ParseErrorKind::UnknownEntity
+        );
+        assert_eq!(
+            error_kind("<speak a='1' a='2'/>"),
+            ParseErrorKind::DuplicateAttribute
+        );
+        assert_eq!(
+            error_kind("<speak/><speak/>"),
+            ParseErrorKind::TrailingContent
+        );
+        assert_eq!(error_kind("<speak>"), ParseErrorKind::UnexpectedEof);
+        let deep = format!("<speak>{}{}</speak>", "<p>".repeat(100), "</p>".repeat(100));
+        assert_eq!(error_kind(&deep), ParseErrorKind::TooDeep);
+    }
+
+    #[test]
+    fn error_position() {
+        let Err(crate::Error::Ssml(error)) = parse("<speak>\n  <p>text</s>\n</speak>") else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(error.kind, ParseErrorKind::MismatchedTag);
+        assert_eq!(error.line, 2);
+    }
+
+    #[test]
+    fn any_root_element() {
+        let lexicon = parse_element("<lexicon><lexeme/></lexicon>").unwrap();
+        assert_eq!(lexicon.name, "lexicon");
+        assert_eq!(lexicon.children.len(), 1);
+    }
+}
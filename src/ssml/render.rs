@@ -0,0 +1,92 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::{Element, ElementKind, Node};
+
+/// Renders the text to be spoken: `sub` is replaced by its alias, `audio` by its fallback
+/// content, paragraphs are put on separate lines and whitespace is collapsed.
+pub(super) fn plain_text(root: &Element) -> String {
+    let mut text = String::new();
+    render(root, &mut text);
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Marks with the share of the spoken text before them, from 0.0 to 1.0.
+pub(super) fn mark_positions(root: &Element) -> Vec<(String, f64)> {
+    let mut text = String::new();
+    let mut marks = Vec::new();
+    render_with_marks(root, &mut text, &mut marks);
+    let total = text.chars().filter(|c| !c.is_whitespace()).count();
+    marks
+        .into_iter()
+        .map(|(name, before)| {
+            let share = if total == 0 {
+                0.0
+            } else {
+                before as f64 / total as f64
+            };
+            (name, share)
+        })
+        .collect()
+}
+
+fn render(element: &Element, text: &mut String) {
+    render_with_marks(element, text, &mut Vec::new())
+}
+
+/// Renders the element and records the marks with the count of non-whitespace characters before them.
+fn render_with_marks(element: &Element, text: &mut String, marks: &mut Vec<(String, usize)>) {
+    match element.kind {
+        ElementKind::Mark => {
+            if let Some(name) = element.attr("name") {
+                let before = text.chars().filter(|c| !c.is_whitespace()).count();
+                marks.push((name.to_owned(), before));
+            }
+            return;
+        }
+        ElementKind::Desc | ElementKind::Lexicon | ElementKind::Meta | ElementKind::Metadata => {
+            return
+        }
+        ElementKind::Break => {
+            text.push(' ');
+            return;
+        }
+        ElementKind::Sub => {
+            if let Some(alias) = element.attr("alias") {
+                text.push_str(alias);
+                return;
+            }
+        }
+        _ => {}
+    }
+    let block = match element.kind {
+        ElementKind::Paragraph => "\n",
+        ElementKind::Sentence => " ",
+        _ => "",
+    };
+    text.push_str(block);
+    for child in &element.children {
+        match child {
+            Node::Text { text: child, .. } => {
+                text.push_str(&child.replace('\n', " "));
+            }
+            Node::Element(child) => render_with_marks(child, text, marks),
+        }
+    }
+    text.push_str(block);
+}
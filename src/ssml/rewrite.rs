@@ -0,0 +1,492 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::{Document, Element, ElementKind, Node, ParseError, ParseErrorKind};
+
+/// What to do with an element after a rule has looked at it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Keep,
+    /// Replace the element with its children.
+    Unwrap,
+    /// Drop the element with its children.
+    Remove,
+}
+
+/// Rewrites one element, the rules see the elements top-down.
+pub trait RewriteRule: Send + Sync {
+    fn rewrite(&self, element: &mut Element) -> Result<Action, ParseErrorKind>;
+}
+
+impl<F> RewriteRule for F
+where
+    F: Fn(&mut Element) -> Result<Action, ParseErrorKind> + Send + Sync,
+{
+    fn rewrite(&self, element: &mut Element) -> Result<Action, ParseErrorKind> {
+        self(element)
+    }
+}
+
+/// Adapts SSML to the dialect of a backend by a chain of rules.
+/// The first rule that does not keep an element decides its fate.
+#[derive(Default)]
+pub struct Rewriter {
+    rules: Vec<Box<dyn RewriteRule>>,
+    xml_declaration: bool,
+}
+
+impl Rewriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn rule(mut self, rule: impl RewriteRule + 'static) -> Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Prepends `<?xml version="1.0" encoding="UTF-8"?>` to the output.
+    pub fn xml_declaration(mut self, enabled: bool) -> Self {
+        self.xml_declaration = enabled;
+        self
+    }
+
+    /// Parses, rewrites and serializes the document.
+    pub fn rewrite(&self, source: &str) -> crate::Result<String> {
+        let mut document = Document::parse(source)?;
+        self.rewrite_document(&mut document)?;
+        let ssml = document.to_ssml();
+        Ok(if self.xml_declaration {
+            format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>{ssml}")
+        } else {
+            ssml
+        })
+    }
+
+    /// Applies the rules, the root can only be changed in place.
+    pub fn rewrite_document(&self, document: &mut Document) -> crate::Result<()> {
+        let result = self
+            .apply(&mut document.root)
+            .and_then(|_| self.rewrite_children(&mut document.root));
+        result.map_err(|(kind, offset)| {
+            crate::Error::Ssml(ParseError::new(kind, &document.source, offset))
+        })
+    }
+
+    fn apply(&self, element: &mut Element) -> Result<Action, (ParseErrorKind, usize)> {
+        for rule in &self.rules {
+            match rule.rewrite(element) {
+                Ok(Action::Keep) => {}
+                Ok(action) => return Ok(action),
+                Err(kind) => return Err((kind, element.span.start)),
+            }
+        }
+        Ok(Action::Keep)
+    }
+
+    fn rewrite_children(&self, element: &mut Element) -> Result<(), (ParseErrorKind, usize)> {
+        let mut children = Vec::with_capacity(element.children.len());
+        for child in std::mem::take(&mut element.children) {
+            let Node::Element(mut child) = child else {
+                children.push(child);
+                continue;
+            };
+            match self.apply(&mut child)? {
+                Action::Keep => {
+                    self.rewrite_children(&mut child)?;
+                    children.push(Node::Element(child));
+                }
+                Action::Unwrap => {
+                    self.rewrite_children(&mut child)?;
+                    children.append(&mut child.children);
+                }
+                Action::Remove => {}
+            }
+        }
+        element.children = children;
+        Ok(())
+    }
+}
+
+/// Unwraps elements the backend does not support, or removes them if their content
+/// is not meant to be spoken. In strict mode they are reported as errors instead.
+#[derive(Debug, Clone)]
+pub struct StripUnsupported {
+    supported: Vec<ElementKind>,
+    strict: bool,
+}
+
+impl StripUnsupported {
+    pub fn new(supported: impl IntoIterator<Item = ElementKind>) -> Self {
+        Self {
+            supported: supported.into_iter().collect(),
+            strict: false,
+        }
+    }
+
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+}
+
+impl RewriteRule for StripUnsupported {
+    fn rewrite(&self, element: &mut Element) -> Result<Action, ParseErrorKind> {
+        if element.kind == ElementKind::Speak || self.supported.contains(&element.kind) {
+            return Ok(Action::Keep);
+        }
+        if self.strict {
+            return Err(ParseErrorKind::UnsupportedElement);
+        }
+        Ok(match element.kind {
+            ElementKind::Desc
+            | ElementKind::Mark
+            | ElementKind::Break
+            | ElementKind::Lexicon
+            | ElementKind::Meta
+            | ElementKind::Metadata => Action::Remove,
+            _ => Action::Unwrap,
+        })
+    }
+}
+
+/// Converts `say-as` variants, keys and values are `interpret-as` optionally followed
+/// by `:format`, e.g. `date:mdy`. Unsupported variants are unwrapped if a list of
+/// supported ones is given.
+#[derive(Debug, Clone, Default)]
+pub struct SayAsRule {
+    map: Vec<(String, String)>,
+    supported: Option<Vec<String>>,
+}
+
+impl SayAsRule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn map(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.map.push((from.into(), to.into()));
+        self
+    }
+
+    pub fn supported<S: Into<String>>(mut self, supported: impl IntoIterator<Item = S>) -> Self {
+        self.supported = Some(supported.into_iter().map(Into::into).collect());
+        self
+    }
+}
+
+impl RewriteRule for SayAsRule {
+    fn rewrite(&self, element: &mut Element) -> Result<Action, ParseErrorKind> {
+        if element.kind != ElementKind::SayAs {
+            return Ok(Action::Keep);
+        }
+        let interpret_as = element
+            .attr("interpret-as")
+            .ok_or(ParseErrorKind::InvalidValue)?
+            .to_ascii_lowercase();
+        let format = element.attr("format").map(str::to_ascii_lowercase);
+        let target = self.map.iter().find_map(|(from, to)| {
+            let matches = match from.split_once(':') {
+                Some((from, from_format)) => {
+                    from.eq_ignore_ascii_case(&interpret_as)
+                        && format
+                            .as_deref()
+                            .is_some_and(|format| from_format.eq_ignore_ascii_case(format))
+                }
+                None => from.eq_ignore_ascii_case(&interpret_as),
+            };
+            matches.then_some(to.as_str())
+        });
+        let interpret_as = match target {
+            Some(target) => {
+                let (interpret_as, format) = match target.split_once(':') {
+                    Some((interpret_as, format)) => (interpret_as, Some(format)),
+                    None => (target, None),
+                };
+                element.set_attr("interpret-as", interpret_as);
+                if let Some(format) = format {
+                    element.set_attr("format", format);
+                }
+                interpret_as.to_ascii_lowercase()
+            }
+            None => interpret_as,
+        };
+        match &self.supported {
+            Some(supported)
+                if !supported
+                    .iter()
+                    .any(|known| known.eq_ignore_ascii_case(&interpret_as)) =>
+            {
+                Ok(Action::Unwrap)
+            }
+            _ => Ok(Action::Keep),
+        }
+    }
+}
+
+/// Limits prosody volume and rate and writes them in a single form, `+3.0dB` and `150%`.
+#[derive(Debug, Clone)]
+pub struct ClampProsody {
+    volume_db: (f64, f64),
+    rate: (f64, f64),
+}
+
+impl Default for ClampProsody {
+    fn default() -> Self {
+        Self {
+            volume_db: (-12.0, 12.0),
+            rate: (0.5, 2.0),
+        }
+    }
+}
+
+impl ClampProsody {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn volume_db(mut self, min: f64, max: f64) -> Self {
+        self.volume_db = (min, max);
+        self
+    }
+
+    pub fn rate(mut self, min: f64, max: f64) -> Self {
+        self.rate = (min, max);
+        self
+    }
+}
+
+impl RewriteRule for ClampProsody {
+    fn rewrite(&self, element: &mut Element) -> Result<Action, ParseErrorKind> {
+        if element.kind != ElementKind::Prosody {
+            return Ok(Action::Keep);
+        }
+        if let Some(volume) = element.attr("volume") {
+            let gain = super::volume_gain_db(volume).ok_or(ParseErrorKind::InvalidValue)?;
+            let volume = if gain == f64::NEG_INFINITY {
+                "silent".to_owned()
+            } else {
+                format!("{:+.1}dB", gain.clamp(self.volume_db.0, self.volume_db.1))
+            };
+            element.set_attr("volume", volume);
+        }
+        if let Some(rate) = element.attr("rate") {
+            let rate = super::rate_multiplier(rate).ok_or(ParseErrorKind::InvalidValue)?;
+            element.set_attr(
+                "rate",
+                format!("{:.0}%", 100.0 * rate.clamp(self.rate.0, self.rate.1)),
+            );
+        }
+        Ok(Action::Keep)
+    }
+}
+
+/// Maps voice names to the voices of the backend, case-insensitively.
+/// Unknown names are replaced with the default voice if there is one.
+#[derive(Debug, Clone, Default)]
+pub struct VoiceMap {
+    voices: Vec<(String, String)>,
+    default: Option<String>,
+}
+
+impl VoiceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn voice(mut self, name: impl Into<String>, backend: impl Into<String>) -> Self {
+        self.voices.push((name.into(), backend.into()));
+        self
+    }
+
+    pub fn default_voice(mut self, backend: impl Into<String>) -> Self {
+        self.default = Some(backend.into());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.voices
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name.trim()))
+            .map(|(_, backend)| backend.as_str())
+            .or(self.default.as_deref())
+    }
+}
+
+impl RewriteRule for VoiceMap {
+    fn rewrite(&self, element: &mut Element) -> Result<Action, ParseErrorKind> {
+        if element.kind != ElementKind::Voice {
+            return Ok(Action::Keep);
+        }
+        if let Some(backend) = element.attr("name").and_then(|name| self.get(name)) {
+            let backend = backend.to_owned();
+            element.set_attr("name", backend);
+        }
+        Ok(Action::Keep)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ssml::{completion_cause, ParseFailure},
+        uni,
+    };
+
+    fn rewrite(rewriter: Rewriter, source: &str) -> String {
+        rewriter.rewrite(source).unwrap()
+    }
+
+    #[test]
+    fn unsupported_elements() {
+        let rewriter = Rewriter::new().rule(StripUnsupported::new([
+            ElementKind::Paragraph,
+            ElementKind::Break,
+        ]));
+        assert_eq!(
+            rewrite(
+                rewriter,
+                "<speak><p>Hi <emphasis>there</emphasis><mark name=\"a\"/>\
+                 <audio src=\"x.wav\">beep<desc>a beep</desc></audio><break/></p></speak>"
+            ),
+            "<speak><p>Hi therebeep<break/></p></speak>"
+        );
+    }
+
+    #[test]
+    fn strict_unsupported_elements() {
+        let rewriter = Rewriter::new().rule(StripUnsupported::new([]).strict());
+        assert_eq!(rewrite(rewriter, "<speak>Hi</speak>"), "<speak>Hi</speak>");
+        let rewriter = Rewriter::new().rule(StripUnsupported::new([]).strict());
+        let Err(crate::Error::Ssml(err)) = rewriter.rewrite("<speak>\n  Hi <s>there</s></speak>")
+        else {
+            panic!("expected an SSML error");
+        };
+        assert_eq!(err.kind, ParseErrorKind::UnsupportedElement);
+        assert_eq!((err.line, err.column), (2, 6));
+        assert_eq!(err.failure(), ParseFailure::Content);
+    }
+
+    #[test]
+    fn say_as_mapping() {
+        let rule = SayAsRule::new()
+            .map("date:mdy", "date:dmy")
+            .map("cardinal", "number")
+            .supported(["date", "number"]);
+        assert_eq!(
+            rewrite(
+                Rewriter::new().rule(rule),
+                "<speak><say-as interpret-as=\"date\" format=\"MDY\">1/2/2000</say-as> \
+                 <say-as interpret-as=\"date\" format=\"ymd\">2000/1/2</say-as> \
+                 <say-as interpret-as=\"Cardinal\">12</say-as> \
+                 <say-as interpret-as=\"telephone\">555</say-as></speak>"
+            ),
+            "<speak><say-as interpret-as=\"date\" format=\"dmy\">1/2/2000</say-as> \
+             <say-as interpret-as=\"date\" format=\"ymd\">2000/1/2</say-as> \
+             <say-as interpret-as=\"number\">12</say-as> 555</speak>"
+        );
+        let rewriter = Rewriter::new().rule(SayAsRule::new());
+        assert!(rewriter
+            .rewrite("<speak><say-as>12</say-as></speak>")
+            .is_err());
+    }
+
+    #[test]
+    fn prosody_is_clamped() {
+        let rewriter = Rewriter::new().rule(ClampProsody::new());
+        assert_eq!(
+            rewrite(
+                rewriter,
+                "<speak><prosody volume=\"+20dB\" rate=\"1.5\">a</prosody>\
+                 <prosody volume=\"silent\" rate=\"x-slow\">b</prosody>\
+                 <prosody volume=\"soft\" rate=\"400%\">c</prosody></speak>"
+            ),
+            "<speak><prosody volume=\"+12.0dB\" rate=\"150%\">a</prosody>\
+             <prosody volume=\"silent\" rate=\"50%\">b</prosody>\
+             <prosody volume=\"-6.0dB\" rate=\"200%\">c</prosody></speak>"
+        );
+        let rewriter = Rewriter::new().rule(ClampProsody::new());
+        let err = rewriter
+            .rewrite("<speak><prosody rate=\"quick\">a</prosody></speak>")
+            .unwrap_err();
+        assert_eq!(
+            completion_cause(&err),
+            uni::SYNTHESIZER_COMPLETION_CAUSE_PARSE_FAILURE
+        );
+    }
+
+    #[test]
+    fn voices() {
+        let voices = VoiceMap::new().voice("Alice", "en-US-alice-neural");
+        assert_eq!(voices.get(" alice "), Some("en-US-alice-neural"));
+        assert_eq!(voices.get("bob"), None);
+        let rewriter = Rewriter::new()
+            .rule(voices.default_voice("en-US-default"))
+            .xml_declaration(true);
+        assert_eq!(
+            rewrite(
+                rewriter,
+                "<speak><voice name=\"ALICE\">a</voice><voice name=\"bob\">b</voice>\
+                 <voice gender=\"female\">c</voice></speak>"
+            ),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <speak><voice name=\"en-US-alice-neural\">a</voice>\
+             <voice name=\"en-US-default\">b</voice><voice gender=\"female\">c</voice></speak>"
+        );
+    }
+
+    #[test]
+    fn first_deciding_rule_wins() {
+        let rewriter = Rewriter::new()
+            .rule(|element: &mut Element| {
+                Ok(match element.kind {
+                    ElementKind::Sub => Action::Remove,
+                    _ => Action::Keep,
+                })
+            })
+            .rule(StripUnsupported::new([]));
+        assert_eq!(
+            rewrite(
+                rewriter,
+                "<speak>a <sub alias=\"b\">c</sub> <emphasis>d</emphasis></speak>"
+            ),
+            "<speak>a  d</speak>"
+        );
+    }
+
+    #[test]
+    fn failures_and_completion_causes() {
+        let failure = |source| match Document::parse(source) {
+            Err(crate::Error::Ssml(err)) => err.failure(),
+            other => panic!("expected an SSML error, got {other:?}"),
+        };
+        assert_eq!(failure("<speak>a</spek>"), ParseFailure::Malformed);
+        assert_eq!(failure("<speak>&nbsp;</speak>"), ParseFailure::Malformed);
+        assert_eq!(failure("<html>a</html>"), ParseFailure::Structure);
+        let err = Document::parse("<speak>").unwrap_err();
+        assert_eq!(
+            completion_cause(&err),
+            uni::SYNTHESIZER_COMPLETION_CAUSE_PARSE_FAILURE
+        );
+        assert_eq!(
+            completion_cause(&crate::Error::UnsupportedCharset("x".to_owned())),
+            uni::SYNTHESIZER_COMPLETION_CAUSE_PARSE_FAILURE
+        );
+        assert_eq!(
+            completion_cause(&crate::Error::NullRequest),
+            uni::SYNTHESIZER_COMPLETION_CAUSE_ERROR
+        );
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::{Element, Node};
+use std::fmt::{self, Write};
+
+impl Element {
+    /// Start tag of the element without its content.
+    pub fn start_tag(&self) -> String {
+        StartTag(self).to_string() + ">"
+    }
+
+    pub fn end_tag(&self) -> String {
+        format!("</{}>", self.name)
+    }
+}
+
+/// Start tag without the closing `>`.
+struct StartTag<'a>(&'a Element);
+
+impl fmt::Display for StartTag<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}", self.0.name)?;
+        for (name, value) in &self.0.attributes {
+            write!(f, " {name}=\"")?;
+            escape(f, value, true)?;
+            f.write_char('"')?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", StartTag(self))?;
+        if self.children.is_empty() {
+            return f.write_str("/>");
+        }
+        f.write_char('>')?;
+        for child in &self.children {
+            write!(f, "{child}")?;
+        }
+        write!(f, "</{}>", self.name)
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Node::Text { text, .. } => escape(f, text, false),
+            Node::Element(element) => element.fmt(f),
+        }
+    }
+}
+
+/// Text escaped for element content.
+pub fn escape_text(text: &str) -> String {
+    struct Escaped<'a>(&'a str);
+    impl fmt::Display for Escaped<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            escape(f, self.0, false)
+        }
+    }
+    Escaped(text).to_string()
+}
+
+fn escape(f: &mut fmt::Formatter<'_>, text: &str, attribute: bool) -> fmt::Result {
+    for c in text.chars() {
+        match c {
+            '&' => f.write_str("&amp;")?,
+            '<' => f.write_str("&lt;")?,
+            '>' => f.write_str("&gt;")?,
+            '"' if attribute => f.write_str("&quot;")?,
+            c => f.write_char(c)?,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,464 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+use super::MarkerTracker;
+use crate::{
+    audio::AudioSource,
+    headers::{SynthDefaults, SynthHeaders, SynthParams},
+    inline_mrcp_engine_channel_message_send, inline_mrcp_generic_header_get,
+    inline_mrcp_generic_header_prepare, inline_mrcp_generic_header_property_add,
+    inline_mrcp_generic_header_property_check, inline_mrcp_resource_header_prepare,
+    inline_mrcp_resource_header_property_add,
+    lexicon::LexiconStore,
+    uni,
+};
+use std::collections::VecDeque;
+
+/// MRCP request kept by the channel until it is completed.
+/// The message lives in the pool of its session, so it may be passed between threads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Request(*const uni::mrcp_message_t);
+
+unsafe impl Send for Request {}
+
+impl Request {
+    pub fn as_ptr(&self) -> *const uni::mrcp_message_t {
+        self.0
+    }
+
+    pub fn id(&self) -> uni::mrcp_request_id {
+        unsafe { (*self.0).start_line.request_id }
+    }
+}
+
+/// What the plugin has to do with its backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Synthesize the SPEAK request into the audio source.
+    Start(Request),
+    /// Abort synthesis of the request, its audio has been dropped already.
+    Cancel(Request),
+    /// Apply the CONTROL request (jump-size, speak-length, prosody) to the active SPEAK.
+    Control { speak: Request, control: Request },
+}
+
+/// Messages to send to the client and commands for the backend, in order.
+#[derive(Debug, Default)]
+pub struct Dispatch {
+    pub messages: Vec<*mut uni::mrcp_message_t>,
+    pub commands: Vec<Command>,
+}
+
+impl Dispatch {
+    /// Sends the messages with `mrcp_engine_channel_message_send` and returns the commands.
+    pub fn send(self, channel: *mut uni::mrcp_engine_channel_t) -> Vec<Command> {
+        for message in self.messages {
+            if !message.is_null() {
+                unsafe { inline_mrcp_engine_channel_message_send(channel, message) };
+            }
+        }
+        self.commands
+    }
+
+    fn message(&mut self, message: *mut uni::mrcp_message_t) {
+        if !message.is_null() {
+            self.messages.push(message);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SynthState {
+    Idle,
+    Speaking,
+    Paused,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Speak {
+    request: Request,
+    kill_on_barge_in: bool,
+}
+
+/// Synthesizer channel state: the active SPEAK, the queue of pending ones and the channel params.
+#[derive(Debug)]
+pub struct SynthChannel {
+    params: SynthParams,
+    active: Option<Speak>,
+    pending: VecDeque<Speak>,
+    paused: bool,
+    markers: MarkerTracker,
+    lexicons: LexiconStore,
+}
+
+impl Default for SynthChannel {
+    fn default() -> Self {
+        Self::new(SynthDefaults::default())
+    }
+}
+
+impl SynthChannel {
+    pub fn new(defaults: SynthDefaults) -> Self {
+        Self {
+            params: SynthParams::new(defaults),
+            active: None,
+            pending: VecDeque::new(),
+            paused: false,
+            markers: MarkerTracker::new(),
+            lexicons: LexiconStore::new(),
+        }
+    }
+
+    pub fn params(&self) -> &SynthParams {
+        &self.params
+    }
+
+    pub fn state(&self) -> SynthState {
+        match (self.active, self.paused) {
+            (None, _) => SynthState::Idle,
+            (Some(_), false) => SynthState::Speaking,
+            (Some(_), true) => SynthState::Paused,
+        }
+    }
+
+    pub fn active(&self) -> Option<Request> {
+        self.active.map(|speak| speak.request)
+    }
+
+    pub fn pending(&self) -> impl Iterator<Item = Request> + '_ {
+        self.pending.iter().map(|speak| speak.request)
+    }
+
+    /// Marks of the active request, fill it when synthesis starts.
+    pub fn markers_mut(&mut self) -> &mut MarkerTracker {
+        &mut self.markers
+    }
+
+    /// Lexicons defined by DEFINE-LEXICON, apply them to the text of the SPEAK
+    /// with the lexicon-search-order of the request.
+    pub fn lexicons(&self) -> &LexiconStore {
+        &self.lexicons
+    }
+
+    pub fn lexicons_mut(&mut self) -> &mut LexiconStore {
+        &mut self.lexicons
+    }
+
+    /// Handles a request of the synthesizer resource.
+    /// Unknown methods give `None` and are left to the plugin.
+    pub fn process_request(
+        &mut self,
+        request: *const uni::mrcp_message_t,
+        source: &mut AudioSource,
+    ) -> Option<Dispatch> {
+        if request.is_null() {
+            return None;
+        }
+        let mut dispatch = Dispatch::default();
+        let method = unsafe { (*request).start_line.method_id } as u32;
+        match method {
+            uni::SYNTHESIZER_SET_PARAMS => {
+                self.params.set_params(request);
+                dispatch.message(response(request));
+            }
+            uni::SYNTHESIZER_GET_PARAMS => {
+                dispatch.message(self.params.get_params_response(request));
+            }
+            uni::SYNTHESIZER_SPEAK => self.speak(request, source, &mut dispatch),
+            uni::SYNTHESIZER_STOP => {
+                let stopped = self.stop(requested_ids(request), source, &mut dispatch);
+                dispatch.message(response_with_ids(request, &stopped));
+            }
+            uni::SYNTHESIZER_PAUSE | uni::SYNTHESIZER_RESUME => {
+                let Some(active) = self.active else {
+                    dispatch.message(failure(request, uni::MRCP_STATUS_CODE_METHOD_NOT_VALID));
+                    return Some(dispatch);
+                };
+                self.paused = method == uni::SYNTHESIZER_PAUSE;
+                if self.paused {
+                    source.pause();
+                } else {
+                    source.resume();
+                }
+                dispatch.message(response_with_ids(request, &[active.request]));
+            }
+            uni::SYNTHESIZER_BARGE_IN_OCCURRED => {
+                let stopped = match self.active {
+                    Some(active) if active.kill_on_barge_in => {
+                        self.stop(None, source, &mut dispatch)
+                    }
+                    _ => Vec::new(),
+                };
+                dispatch.message(response_with_ids(request, &stopped));
+            }
+            uni::SYNTHESIZER_CONTROL => {
+                let Some(active) = self.active else {
+                    dispatch.message(failure(request, uni::MRCP_STATUS_CODE_METHOD_NOT_VALID));
+                    return Some(dispatch);
+                };
+                let response = response_with_ids(request, &[active.request]);
+                self.markers.set_header(response);
+                dispatch.message(response);
+                dispatch.commands.push(Command::Control {
+                    speak: active.request,
+                    control: Request(request),
+                });
+            }
+            uni::SYNTHESIZER_DEFINE_LEXICON => {
+                if self.active.is_some() {
+                    dispatch.message(failure(request, uni::MRCP_STATUS_CODE_METHOD_NOT_VALID));
+                    return Some(dispatch);
+                }
+                let headers = SynthHeaders::new(request);
+                dispatch.message(match self.lexicons.define(&headers) {
+                    Ok(()) => response(request),
+                    Err(_) => lexicon_failure(request),
+                });
+            }
+            _ => return None,
+        }
+        Some(dispatch)
+    }
+
+    /// Reports reached marks and completes the active request when its audio has been played.
+    /// Call it from the media thread after reading a frame.
+    pub fn poll(&mut self, source: &mut AudioSource) -> Dispatch {
+        let mut dispatch = Dispatch::default();
+        let Some(active) = self.active else {
+            return dispatch;
+        };
+        for event in self.markers.poll(active.request.as_ptr(), source) {
+            dispatch.message(event);
+        }
+        if source.is_completed() {
+            self.complete_into(
+                uni::SYNTHESIZER_COMPLETION_CAUSE_NORMAL,
+                source,
+                &mut dispatch,
+            );
+        }
+        dispatch
+    }
+
+    /// Completes the active request with the cause, e.g. an error of the backend,
+    /// and starts the next pending one.
+    pub fn complete(
+        &mut self,
+        cause: uni::mrcp_synth_completion_cause_e,
+        source: &mut AudioSource,
+    ) -> Dispatch {
+        let mut dispatch = Dispatch::default();
+        self.complete_into(cause, source, &mut dispatch);
+        dispatch
+    }
+
+    fn complete_into(
+        &mut self,
+        cause: uni::mrcp_synth_completion_cause_e,
+        source: &mut AudioSource,
+        dispatch: &mut Dispatch,
+    ) {
+        let Some(active) = self.active.take() else {
+            return;
+        };
+        let event = speak_complete(active.request.as_ptr(), cause);
+        self.markers.set_header(event);
+        dispatch.message(event);
+        self.start_next(source, dispatch);
+    }
+
+    fn speak(
+        &mut self,
+        request: *const uni::mrcp_message_t,
+        source: &mut AudioSource,
+        dispatch: &mut Dispatch,
+    ) {
+        let speak = Speak {
+            request: Request(request),
+            kill_on_barge_in: self.params.kill_on_barge_in(request),
+        };
+        let response = response(request);
+        if self.active.is_none() {
+            set_request_state(response, uni::MRCP_REQUEST_STATE_INPROGRESS);
+            dispatch.message(response);
+            self.activate(speak, source, dispatch);
+        } else {
+            set_request_state(response, uni::MRCP_REQUEST_STATE_PENDING);
+            dispatch.message(response);
+            self.pending.push_back(speak);
+        }
+    }
+
+    fn activate(&mut self, speak: Speak, source: &mut AudioSource, dispatch: &mut Dispatch) {
+        source.reset();
+        self.markers.reset();
+        self.paused = false;
+        self.active = Some(speak);
+        dispatch.commands.push(Command::Start(speak.request));
+    }
+
+    fn start_next(&mut self, source: &mut AudioSource, dispatch: &mut Dispatch) {
+        match self.pending.pop_front() {
+            Some(next) => self.activate(next, source, dispatch),
+            None => {
+                source.reset();
+                self.paused = false;
+            }
+        }
+    }
+
+    /// Stops the requests with the given ids or all of them, no SPEAK-COMPLETE is sent for them.
+    fn stop(
+        &mut self,
+        ids: Option<Vec<uni::mrcp_request_id>>,
+        source: &mut AudioSource,
+        dispatch: &mut Dispatch,
+    ) -> Vec<Request> {
+        let selected = |speak: &Speak| match &ids {
+            Some(ids) => ids.contains(&speak.request.id()),
+            None => true,
+        };
+        let mut stopped = Vec::new();
+        self.pending.retain(|speak| {
+            let stop = selected(speak);
+            if stop {
+                stopped.push(speak.request);
+            }
+            !stop
+        });
+        if let Some(active) = self.active.filter(|active| selected(active)) {
+            stopped.insert(0, active.request);
+            self.active = None;
+            dispatch.commands.push(Command::Cancel(active.request));
+            self.start_next(source, dispatch);
+        }
+        stopped
+    }
+}
+
+fn requested_ids(request: *const uni::mrcp_message_t) -> Option<Vec<uni::mrcp_request_id>> {
+    unsafe {
+        if inline_mrcp_generic_header_property_check(
+            request,
+            uni::GENERIC_HEADER_ACTIVE_REQUEST_ID_LIST as _,
+        ) != uni::TRUE
+        {
+            return None;
+        }
+        let generic_header = inline_mrcp_generic_header_get(request);
+        if generic_header.is_null() {
+            return None;
+        }
+        let list = &(*generic_header).active_request_id_list;
+        Some(list.ids[..list.count.min(list.ids.len())].to_vec())
+    }
+}
+
+fn response(request: *const uni::mrcp_message_t) -> *mut uni::mrcp_message_t {
+    unsafe { uni::mrcp_response_create(request, (*request).pool) }
+}
+
+fn failure(
+    request: *const uni::mrcp_message_t,
+    status_code: uni::mrcp_status_code_e,
+) -> *mut uni::mrcp_message_t {
+    let response = response(request);
+    if !response.is_null() {
+        unsafe { (*response).start_line.status_code = status_code };
+    }
+    response
+}
+
+/// Failed DEFINE-LEXICON with the lexicon-load-failure completion cause.
+fn lexicon_failure(request: *const uni::mrcp_message_t) -> *mut uni::mrcp_message_t {
+    let response = failure(request, uni::MRCP_STATUS_CODE_METHOD_FAILED);
+    if response.is_null() {
+        return response;
+    }
+    unsafe {
+        let synth_header =
+            inline_mrcp_resource_header_prepare(response) as *mut uni::mrcp_synth_header_t;
+        if !synth_header.is_null() {
+            (*synth_header).completion_cause =
+                uni::SYNTHESIZER_COMPLETION_CAUSE_LEXICON_LOAD_FAILURE;
+            inline_mrcp_resource_header_property_add(
+                response,
+                uni::SYNTHESIZER_HEADER_COMPLETION_CAUSE as _,
+            );
+        }
+    }
+    response
+}
+
+/// Response carrying the active-request-id-list header, omitted if there are no requests.
+fn response_with_ids(
+    request: *const uni::mrcp_message_t,
+    requests: &[Request],
+) -> *mut uni::mrcp_message_t {
+    let response = response(request);
+    if response.is_null() || requests.is_empty() {
+        return response;
+    }
+    unsafe {
+        let generic_header = inline_mrcp_generic_header_prepare(response);
+        if generic_header.is_null() {
+            return response;
+        }
+        let list = &mut (*generic_header).active_request_id_list;
+        let count = requests.len().min(list.ids.len());
+        for (id, request) in list.ids.iter_mut().zip(requests) {
+            *id = request.id();
+        }
+        list.count = count;
+        inline_mrcp_generic_header_property_add(
+            response,
+            uni::GENERIC_HEADER_ACTIVE_REQUEST_ID_LIST as _,
+        );
+    }
+    response
+}
+
+fn speak_complete(
+    request: *const uni::mrcp_message_t,
+    cause: uni::mrcp_synth_completion_cause_e,
+) -> *mut uni::mrcp_message_t {
+    unsafe {
+        let event = uni::mrcp_event_create(
+            request,
+            uni::SYNTHESIZER_SPEAK_COMPLETE as _,
+            (*request).pool,
+        );
+        if event.is_null() {
+            return event;
+        }
+        set_request_state(event, uni::MRCP_REQUEST_STATE_COMPLETE);
+        let synth_header =
+            inline_mrcp_resource_header_prepare(event) as *mut uni::mrcp_synth_header_t;
+        if !synth_header.is_null() {
+            (*synth_header).completion_cause = cause;
+            inline_mrcp_resource_header_property_add(
+                event,
+                uni::SYNTHESIZER_HEADER_COMPLETION_CAUSE as _,
+            );
+        }
+        event
+    }
+}
+
+fn set_request_state(message: *mut uni::mrcp_message_t, state: uni::mrcp_request_state_e) {
+    if !message.is_null() {
+        unsafe { (*message).start_line.request_state = state };
+    }
+}
@@ -0,0 +1,231 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+use crate::{
+    audio::AudioSource, inline_apt_string_assign_n, inline_mrcp_resource_header_prepare,
+    inline_mrcp_resource_header_property_add, ssml::Document, uni,
+};
+use std::{collections::VecDeque, time::SystemTime};
+
+/// Seconds between 1900-01-01 and 1970-01-01.
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+/// Associates SSML marks with audio offsets and reports the ones reached by playback.
+#[derive(Debug, Clone, Default)]
+pub struct MarkerTracker {
+    /// Pending marks ordered by offset in samples.
+    pending: VecDeque<(usize, String)>,
+    last: Option<String>,
+}
+
+impl MarkerTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a mark at the offset in samples from the start of the audio, as reported by the backend.
+    /// The offset refers to the audio as written, before post-processing.
+    pub fn add(&mut self, name: impl Into<String>, offset: usize) {
+        let index = self.pending.partition_point(|(known, _)| *known <= offset);
+        self.pending.insert(index, (offset, name.into()));
+    }
+
+    /// Estimates the offsets of the marks of the document from their place in the text,
+    /// given the total duration of the audio in samples.
+    pub fn estimate(&mut self, document: &Document, total: usize) {
+        for (name, share) in document.mark_positions() {
+            self.add(name, (share * total as f64).round() as usize);
+        }
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// The last mark reached, it goes to the speech-marker header of the following events.
+    pub fn last(&self) -> Option<&str> {
+        self.last.as_deref()
+    }
+
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.last = None;
+    }
+
+    /// Takes the marks at or before the playback position in samples.
+    pub fn reached(&mut self, position: usize) -> Vec<String> {
+        let mut reached = Vec::new();
+        while self
+            .pending
+            .front()
+            .is_some_and(|(offset, _)| *offset <= position)
+        {
+            if let Some((_, name)) = self.pending.pop_front() {
+                reached.push(name);
+            }
+        }
+        if let Some(last) = reached.last() {
+            self.last = Some(last.clone());
+        }
+        reached
+    }
+
+    /// Creates SPEECH-MARKER events for the marks reached by the source.
+    /// The events are to be sent in order by `mrcp_engine_channel_message_send`.
+    pub fn poll(
+        &mut self,
+        request: *const uni::mrcp_message_t,
+        source: &AudioSource,
+    ) -> Vec<*mut uni::mrcp_message_t> {
+        if request.is_null() {
+            return Vec::new();
+        }
+        self.reached(source.input_position())
+            .iter()
+            .map(|name| speech_marker_event(request, name))
+            .filter(|event| !event.is_null())
+            .collect()
+    }
+
+    /// Sets the speech-marker header with the last mark reached, e.g. for SPEAK-COMPLETE.
+    pub fn set_header(&self, message: *mut uni::mrcp_message_t) {
+        if !message.is_null() {
+            set_speech_marker(message, self.last().unwrap_or_default());
+        }
+    }
+}
+
+/// Value of the speech-marker header: `timestamp=<NTP seconds>;<name>`, the name may be empty.
+pub fn speech_marker(name: &str) -> String {
+    let unix = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let timestamp = unix + NTP_UNIX_OFFSET;
+    if name.is_empty() {
+        format!("timestamp={timestamp}")
+    } else {
+        format!("timestamp={timestamp};{name}")
+    }
+}
+
+/// Creates an IN-PROGRESS SPEECH-MARKER event for the mark.
+pub fn speech_marker_event(
+    request: *const uni::mrcp_message_t,
+    name: &str,
+) -> *mut uni::mrcp_message_t {
+    if request.is_null() {
+        return std::ptr::null_mut();
+    }
+    unsafe {
+        let event = uni::mrcp_event_create(
+            request,
+            uni::SYNTHESIZER_SPEECH_MARKER as _,
+            (*request).pool,
+        );
+        if event.is_null() {
+            return event;
+        }
+        set_speech_marker(event, name);
+        (*event).start_line.request_state = uni::MRCP_REQUEST_STATE_INPROGRESS;
+        event
+    }
+}
+
+fn set_speech_marker(message: *mut uni::mrcp_message_t, name: &str) {
+    unsafe {
+        let synth_header =
+            inline_mrcp_resource_header_prepare(message) as *mut uni::mrcp_synth_header_t;
+        if synth_header.is_null() {
+            return;
+        }
+        let value = speech_marker(name);
+        inline_apt_string_assign_n(
+            &mut (*synth_header).speech_marker,
+            value.as_ptr() as _,
+            value.len(),
+            (*message).pool,
+        );
+        inline_mrcp_resource_header_property_add(
+            message,
+            uni::SYNTHESIZER_HEADER_SPEECH_MARKER as _,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marks_are_ordered_by_offset() {
+        let mut tracker = MarkerTracker::new();
+        tracker.add("c", 300);
+        tracker.add("a", 100);
+        tracker.add("b", 300);
+        tracker.add("d", 400);
+        assert_eq!(tracker.pending(), 4);
+        assert_eq!(tracker.last(), None);
+        assert!(tracker.reached(99).is_empty());
+        assert_eq!(tracker.reached(100), ["a"]);
+        assert_eq!(tracker.last(), Some("a"));
+        assert!(tracker.reached(299).is_empty());
+        assert_eq!(tracker.last(), Some("a"));
+        assert_eq!(tracker.reached(350), ["c", "b"]);
+        assert_eq!(tracker.last(), Some("b"));
+        assert_eq!(tracker.pending(), 1);
+        tracker.reset();
+        assert_eq!(tracker.pending(), 0);
+        assert_eq!(tracker.last(), None);
+    }
+
+    #[test]
+    fn estimated_offsets() {
+        let document = Document::parse(
+            "<speak><mark name=\"start\"/>abcd <mark name=\"middle\"/>ef\
+             <break/>gh<mark name=\"end\"/></speak>",
+        )
+        .unwrap();
+        let mut tracker = MarkerTracker::new();
+        tracker.estimate(&document, 8000);
+        assert_eq!(tracker.reached(0), ["start"]);
+        assert!(tracker.reached(3999).is_empty());
+        assert_eq!(tracker.reached(4000), ["middle"]);
+        assert_eq!(tracker.reached(8000), ["end"]);
+        assert_eq!(tracker.last(), Some("end"));
+    }
+
+    #[test]
+    fn estimated_offsets_without_text() {
+        let document = Document::parse("<speak><mark name=\"only\"/></speak>").unwrap();
+        let mut tracker = MarkerTracker::new();
+        tracker.estimate(&document, 1000);
+        assert_eq!(tracker.reached(0), ["only"]);
+    }
+
+    #[test]
+    fn speech_marker_values() {
+        let value = speech_marker("greeting");
+        let (timestamp, name) = value.split_once(';').unwrap();
+        let seconds: u64 = timestamp
+            .strip_prefix("timestamp=")
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(seconds > NTP_UNIX_OFFSET);
+        assert_eq!(name, "greeting");
+        assert!(!speech_marker("").contains(';'));
+    }
+}
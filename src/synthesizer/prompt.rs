@@ -0,0 +1,450 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use crate::{
+    audio::{decode_audio, FrameFormat},
+    headers::{ContentType, SpeakContent, SynthHeaders},
+    ssml::{Document, Element, ElementKind, Node},
+};
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Audio fetched by URI with its media type, if known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchedAudio {
+    pub content_type: Option<ContentType>,
+    pub data: Vec<u8>,
+}
+
+/// Fetches audio of URIs other than `file:` and `cid:`, e.g. over HTTP.
+pub trait AudioFetcher: Send + Sync {
+    fn fetch(&self, uri: &str, timeout: Option<Duration>) -> crate::Result<FetchedAudio>;
+}
+
+impl<F> AudioFetcher for F
+where
+    F: Fn(&str, Option<Duration>) -> crate::Result<FetchedAudio> + Send + Sync,
+{
+    fn fetch(&self, uri: &str, timeout: Option<Duration>) -> crate::Result<FetchedAudio> {
+        self(uri, timeout)
+    }
+}
+
+/// Part of a SPEAK request in playback order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptPart {
+    /// Decoded audio in the format of the source.
+    Audio { uri: String, samples: Vec<i16> },
+    /// Content to be synthesized by the backend.
+    Speech(SpeakContent),
+}
+
+/// Loads audio prompts of SPEAK requests: URIs of `text/uri-list` bodies and sources
+/// of SSML `audio` elements. `file:` URIs are read from disk under the prompt root,
+/// `cid:` URIs refer to body parts and the others are left to the fetcher.
+pub struct PromptLoader {
+    format: FrameFormat,
+    root: Option<PathBuf>,
+    fetcher: Option<Box<dyn AudioFetcher>>,
+}
+
+impl PromptLoader {
+    /// Files are only read if they resolve under `root`, relative paths are taken from it.
+    /// Without a root `file:` URIs fail.
+    pub fn new(format: FrameFormat, root: Option<PathBuf>) -> Self {
+        Self {
+            format,
+            root,
+            fetcher: None,
+        }
+    }
+
+    pub fn fetcher(mut self, fetcher: impl AudioFetcher + 'static) -> Self {
+        self.fetcher = Some(Box::new(fetcher));
+        self
+    }
+
+    /// Fetches the audio within the fetch-timeout of the request and decodes it.
+    pub fn load(&self, uri: &str, headers: &SynthHeaders) -> crate::Result<Vec<i16>> {
+        let fetched = self.fetch(uri, headers)?;
+        decode_audio(&fetched.data, fetched.content_type.as_ref(), self.format)
+    }
+
+    /// Splits the content into audio and speech. An `audio` element that fails to load
+    /// is replaced with its alternate content, a URI of a URI list fails the request.
+    pub fn prepare(
+        &self,
+        content: &SpeakContent,
+        headers: &SynthHeaders,
+    ) -> crate::Result<Vec<PromptPart>> {
+        match content {
+            SpeakContent::PlainText(_) => Ok(vec![PromptPart::Speech(content.clone())]),
+            SpeakContent::UriList(uris) => uris
+                .iter()
+                .map(|uri| {
+                    Ok(PromptPart::Audio {
+                        uri: uri.clone(),
+                        samples: self.load(uri, headers)?,
+                    })
+                })
+                .collect(),
+            SpeakContent::Ssml(source) => {
+                let document = Document::parse(source)?;
+                Ok(self
+                    .split(document.root, headers)
+                    .into_iter()
+                    .filter_map(|part| match part {
+                        Split::Speech(element) => has_speech(&element)
+                            .then(|| PromptPart::Speech(SpeakContent::Ssml(element.to_string()))),
+                        Split::Audio(part) => Some(part),
+                    })
+                    .collect())
+            }
+            SpeakContent::Multipart(multipart) => {
+                let root = multipart
+                    .root()
+                    .ok_or_else(|| crate::Error::InvalidMultipart("no root part".to_owned()))?;
+                self.prepare(&root.content()?, headers)
+            }
+        }
+    }
+
+    fn fetch(&self, uri: &str, headers: &SynthHeaders) -> crate::Result<FetchedAudio> {
+        let failure =
+            |reason: &dyn std::fmt::Display| crate::Error::FetchFailure(format!("{uri}: {reason}"));
+        if uri.starts_with("cid:") {
+            let part = headers.part(uri).ok_or_else(|| failure(&"no such part"))?;
+            return Ok(FetchedAudio {
+                content_type: Some(part.content_type.clone()),
+                data: part.body.clone(),
+            });
+        }
+        if uri.starts_with("file:") {
+            let path = file_path(uri).ok_or_else(|| failure(&"not a local file"))?;
+            let path = self.resolve(&path).map_err(|err| failure(&err))?;
+            let data = std::fs::read(&path).map_err(|err| failure(&err))?;
+            return Ok(FetchedAudio {
+                content_type: guess_content_type(&path.to_string_lossy()),
+                data,
+            });
+        }
+        let fetcher = self
+            .fetcher
+            .as_ref()
+            .ok_or_else(|| failure(&"no fetcher for the scheme"))?;
+        let timeout = headers
+            .fetch_timeout
+            .as_ref()
+            .ok()
+            .map(|millis| Duration::from_millis(*millis as u64));
+        let mut fetched = fetcher.fetch(uri, timeout).map_err(|err| match err {
+            crate::Error::FetchFailure(_) => err,
+            err => failure(&err),
+        })?;
+        if fetched.content_type.is_none() {
+            fetched.content_type = guess_content_type(uri);
+        }
+        Ok(fetched)
+    }
+
+    /// Canonical path of the file if it is under the prompt root.
+    fn resolve(&self, path: &Path) -> std::io::Result<PathBuf> {
+        let root = self.root.as_ref().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::PermissionDenied, "no prompt root")
+        })?;
+        let root = root.canonicalize()?;
+        let resolved = root.join(path).canonicalize()?;
+        if !resolved.starts_with(&root) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "outside of the prompt root",
+            ));
+        }
+        Ok(resolved)
+    }
+
+    /// Splits the element at the `audio` elements that load, the speech between them
+    /// keeps the ancestors of its nodes.
+    fn split(&self, mut element: Element, headers: &SynthHeaders) -> Vec<Split> {
+        let mut nodes: VecDeque<Node> = std::mem::take(&mut element.children).into();
+        let mut splits = Vec::new();
+        let mut shell = element.clone();
+        while let Some(node) = nodes.pop_front() {
+            let child = match node {
+                Node::Element(child) => child,
+                text => {
+                    shell.children.push(text);
+                    continue;
+                }
+            };
+            if child.kind == ElementKind::Audio {
+                let uri = child.attr("src").unwrap_or_default().to_owned();
+                match self.load(&uri, headers) {
+                    Ok(samples) => {
+                        splits.push(Split::Speech(std::mem::replace(
+                            &mut shell,
+                            element.clone(),
+                        )));
+                        splits.push(Split::Audio(PromptPart::Audio { uri, samples }));
+                    }
+                    // The alternate content is spoken in place of the audio.
+                    Err(_) => {
+                        for node in child.children.into_iter().rev() {
+                            if node.as_element().map(|element| element.kind)
+                                != Some(ElementKind::Desc)
+                            {
+                                nodes.push_front(node);
+                            }
+                        }
+                    }
+                }
+                continue;
+            }
+            if !child
+                .descendants()
+                .any(|element| element.kind == ElementKind::Audio)
+            {
+                shell.children.push(Node::Element(child));
+                continue;
+            }
+            for split in self.split(child, headers) {
+                match split {
+                    Split::Speech(part) if part.children.is_empty() => {}
+                    Split::Speech(part) => shell.children.push(Node::Element(part)),
+                    audio => {
+                        splits.push(Split::Speech(std::mem::replace(
+                            &mut shell,
+                            element.clone(),
+                        )));
+                        splits.push(audio);
+                    }
+                }
+            }
+        }
+        splits.push(Split::Speech(shell));
+        splits
+    }
+}
+
+enum Split {
+    Speech(Element),
+    Audio(PromptPart),
+}
+
+/// Whether anything of the element is to be spoken or reported.
+fn has_speech(element: &Element) -> bool {
+    !element.plain_text().trim().is_empty()
+        || element
+            .descendants()
+            .any(|element| matches!(element.kind, ElementKind::Mark | ElementKind::Break))
+}
+
+/// Path of a `file:` URI, absolute or relative to the prompt root.
+fn file_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file:")?;
+    let path = match rest.strip_prefix("//") {
+        Some(authority) => {
+            let start = authority.find('/')?;
+            match &authority[..start] {
+                "" | "localhost" => &authority[start..],
+                _ => return None,
+            }
+        }
+        None => rest,
+    };
+    Some(PathBuf::from(percent_decode(path)))
+}
+
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| path.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Media type by the extension of the path, raw linear PCM has none.
+fn guess_content_type(path: &str) -> Option<ContentType> {
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let extension = path.rsplit_once('.')?.1.to_ascii_lowercase();
+    let media_type = match extension.as_str() {
+        "wav" | "wave" => "audio/wav",
+        "ul" | "ulaw" | "mu" | "pcmu" => "audio/pcmu",
+        "al" | "alaw" | "pcma" => "audio/pcma",
+        _ => return None,
+    };
+    Some(ContentType::new(media_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::FrameDuration;
+
+    /// Prompt root with `beep.ul` in it and `secret.ul` next to it.
+    fn prompt_root(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("prompt-{}-{name}", std::process::id()));
+        let root = dir.join("root");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("beep.ul"), [0xFF, 0x80]).unwrap();
+        std::fs::write(dir.join("secret.ul"), [0x00]).unwrap();
+        root
+    }
+
+    fn loader(root: Option<PathBuf>) -> PromptLoader {
+        PromptLoader::new(FrameFormat::new(8000, FrameDuration::Ms20), root)
+    }
+
+    fn headers() -> SynthHeaders {
+        SynthHeaders::new(std::ptr::null())
+    }
+
+    #[test]
+    fn file_paths() {
+        assert_eq!(file_path("file:///etc/passwd"), Some("/etc/passwd".into()));
+        assert_eq!(file_path("file://localhost/x.wav"), Some("/x.wav".into()));
+        assert_eq!(file_path("file:../x.wav"), Some("../x.wav".into()));
+        assert_eq!(file_path("file://otherhost/x"), None);
+        assert_eq!(file_path("http://host/x.wav"), None);
+        assert_eq!(
+            file_path("file:%2e%2e/x%20y.wav"),
+            Some("../x y.wav".into())
+        );
+    }
+
+    #[test]
+    fn percent_decoding() {
+        assert_eq!(percent_decode("a%2Fb%2fc"), "a/b/c");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+    }
+
+    #[test]
+    fn files_are_read_only_under_the_root() {
+        let root = prompt_root("resolve");
+        let loader = loader(Some(root.clone()));
+        let beep = root.canonicalize().unwrap().join("beep.ul");
+        assert_eq!(loader.resolve(Path::new("beep.ul")).unwrap(), beep);
+        assert_eq!(loader.resolve(&beep).unwrap(), beep);
+        assert!(loader.resolve(Path::new("/etc/passwd")).is_err());
+        assert!(loader.resolve(Path::new("../secret.ul")).is_err());
+        assert!(loader.resolve(&root.join("../secret.ul")).is_err());
+        assert_eq!(loader.load("file:beep.ul", &headers()).unwrap(), [0, 32124]);
+        for uri in [
+            "file:///etc/passwd",
+            "file:../secret.ul",
+            "file:%2e%2e/secret.ul",
+            "file://otherhost/beep.ul",
+        ] {
+            assert!(
+                matches!(
+                    loader.load(uri, &headers()),
+                    Err(crate::Error::FetchFailure(_))
+                ),
+                "{uri}"
+            );
+        }
+    }
+
+    #[test]
+    fn no_root_no_files() {
+        let root = prompt_root("no-root");
+        let uri = format!("file://{}", root.join("beep.ul").display());
+        assert!(loader(None).load(&uri, &headers()).is_err());
+    }
+
+    #[test]
+    fn other_schemes_go_to_the_fetcher() {
+        let fetching = loader(None).fetcher(|uri: &str, _: Option<Duration>| {
+            Ok(FetchedAudio {
+                content_type: None,
+                data: uri.as_bytes()[..2].to_vec(),
+            })
+        });
+        let fetched = fetching.fetch("http:foo.ul", &headers()).unwrap();
+        assert_eq!(fetched.data, b"ht");
+        assert_eq!(fetched.content_type, Some(ContentType::new("audio/pcmu")));
+        assert!(fetching.fetch("/etc/passwd", &headers()).is_ok());
+        assert!(loader(None).fetch("http:foo", &headers()).is_err());
+    }
+
+    #[test]
+    fn audio_splits_the_speech() {
+        let root = prompt_root("split");
+        let content = SpeakContent::Ssml(
+            "<speak>Hello <audio src=\"file:beep.ul\">beep</audio> world</speak>".to_owned(),
+        );
+        let parts = loader(Some(root)).prepare(&content, &headers()).unwrap();
+        assert_eq!(parts.len(), 3);
+        assert!(
+            matches!(&parts[0], PromptPart::Speech(SpeakContent::Ssml(ssml)) if ssml.contains("Hello"))
+        );
+        assert_eq!(
+            parts[1],
+            PromptPart::Audio {
+                uri: "file:beep.ul".to_owned(),
+                samples: vec![0, 32124],
+            }
+        );
+        assert!(
+            matches!(&parts[2], PromptPart::Speech(SpeakContent::Ssml(ssml)) if ssml.contains("world"))
+        );
+    }
+
+    #[test]
+    fn failed_audio_falls_back_to_its_content() {
+        let root = prompt_root("fallback");
+        let content = SpeakContent::Ssml(
+            "<speak>Hello <audio src=\"file:missing.ul\">beep<desc>a beep</desc></audio> world</speak>"
+                .to_owned(),
+        );
+        let parts = loader(Some(root)).prepare(&content, &headers()).unwrap();
+        let [PromptPart::Speech(SpeakContent::Ssml(ssml))] = parts.as_slice() else {
+            panic!("expected a single speech part, got {parts:?}");
+        };
+        let plain = Document::parse(ssml).unwrap().plain_text();
+        assert_eq!(
+            plain.split_whitespace().collect::<Vec<_>>(),
+            ["Hello", "beep", "world"]
+        );
+    }
+
+    #[test]
+    fn uri_list_fails_on_a_missing_prompt() {
+        let root = prompt_root("uri-list");
+        let loader = loader(Some(root));
+        let list = SpeakContent::UriList(vec!["file:beep.ul".to_owned()]);
+        assert_eq!(loader.prepare(&list, &headers()).unwrap().len(), 1);
+        let missing = SpeakContent::UriList(vec!["file:missing.ul".to_owned()]);
+        assert!(loader.prepare(&missing, &headers()).is_err());
+    }
+}
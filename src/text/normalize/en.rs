@@ -0,0 +1,319 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::Currency;
+
+const ONES: [&str; 20] = [
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+const TENS: [&str; 10] = [
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+const SCALES: [&str; 4] = ["thousand", "million", "billion", "trillion"];
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+pub(super) fn cardinal(n: u64) -> String {
+    if n == 0 {
+        return ONES[0].to_owned();
+    }
+    if n > super::MAX_SPOKEN {
+        return digits(&n.to_string());
+    }
+    let mut groups = Vec::new();
+    let mut rest = n;
+    while rest > 0 {
+        groups.push(rest % 1000);
+        rest /= 1000;
+    }
+    let mut words = Vec::new();
+    for (scale, group) in groups.iter().enumerate().rev() {
+        if *group == 0 {
+            continue;
+        }
+        words.push(triad(*group));
+        if scale > 0 {
+            if let Some(name) = SCALES.get(scale - 1) {
+                words.push((*name).to_owned());
+            }
+        }
+    }
+    words.join(" ")
+}
+
+fn triad(n: u64) -> String {
+    let (hundreds, rest) = ((n / 100) as usize, (n % 100) as usize);
+    let mut words = Vec::new();
+    if hundreds > 0 {
+        words.push(format!("{} hundred", ONES[hundreds]));
+    }
+    if rest >= 20 {
+        words.push(match rest % 10 {
+            0 => TENS[rest / 10].to_owned(),
+            units => format!("{}-{}", TENS[rest / 10], ONES[units]),
+        });
+    } else if rest > 0 {
+        words.push(ONES[rest].to_owned());
+    }
+    words.join(" ")
+}
+
+/// The cardinal with its last word made ordinal, e.g. `twenty-first`.
+pub(super) fn ordinal(n: u64) -> String {
+    if n > super::MAX_SPOKEN {
+        return digits(&n.to_string());
+    }
+    let cardinal = cardinal(n);
+    let split = cardinal.rfind([' ', '-']).map_or(0, |index| index + 1);
+    let (head, last) = cardinal.split_at(split);
+    let last = match last {
+        "one" => "first".to_owned(),
+        "two" => "second".to_owned(),
+        "three" => "third".to_owned(),
+        "five" => "fifth".to_owned(),
+        "eight" => "eighth".to_owned(),
+        "nine" => "ninth".to_owned(),
+        "twelve" => "twelfth".to_owned(),
+        word if word.ends_with('y') => format!("{}ieth", word.trim_end_matches('y')),
+        word => format!("{word}th"),
+    };
+    format!("{head}{last}")
+}
+
+/// Whether the written suffix fits the number, e.g. `1st`, `22nd`, `13th`.
+pub(super) fn ordinal_suffix_matches(n: u64, suffix: &str) -> bool {
+    let expected = match (n % 100, n % 10) {
+        (11..=13, _) => "th",
+        (_, 1) => "st",
+        (_, 2) => "nd",
+        (_, 3) => "rd",
+        _ => "th",
+    };
+    suffix.eq_ignore_ascii_case(expected)
+}
+
+pub(super) fn digits(digits: &str) -> String {
+    digits
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .map(|digit| ONES[digit as usize])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `three point one four`.
+pub(super) fn decimal(integer: u64, fraction: &str) -> String {
+    format!("{} point {}", cardinal(integer), digits(fraction))
+}
+
+/// `nineteen ninety-nine`, `two thousand five`, `twenty twenty-four`.
+pub(super) fn year(year: u64) -> String {
+    let (century, rest) = (year / 100, year % 100);
+    if !(1000..10000).contains(&year) || (2000..2010).contains(&year) {
+        return cardinal(year);
+    }
+    if century % 10 == 0 && rest == 0 {
+        return cardinal(year);
+    }
+    match rest {
+        0 => format!("{} hundred", cardinal(century)),
+        1..=9 => format!("{} oh {}", cardinal(century), ONES[rest as usize]),
+        _ => format!("{} {}", cardinal(century), cardinal(rest)),
+    }
+}
+
+/// `May twelfth, twenty twenty-four`.
+pub(super) fn date(day: Option<u32>, month: Option<u32>, year: Option<u64>) -> String {
+    let mut spoken = String::new();
+    if let Some(month) = month {
+        spoken.push_str(MONTHS[(month - 1) as usize]);
+    }
+    if let Some(day) = day {
+        if !spoken.is_empty() {
+            spoken.push(' ');
+        }
+        spoken.push_str(&ordinal(day.into()));
+    }
+    if let Some(year) = year {
+        if day.is_some() {
+            spoken.push(',');
+        }
+        if !spoken.is_empty() {
+            spoken.push(' ');
+        }
+        spoken.push_str(&self::year(year));
+    }
+    spoken
+}
+
+/// `fifteen thirty`, `nine oh five`, `nine o'clock`.
+pub(super) fn time(hours: u32, minutes: u32, seconds: Option<u32>) -> String {
+    let mut spoken = match minutes {
+        0 if seconds.is_none() => format!("{} o'clock", cardinal(hours.into())),
+        0..=9 => format!("{} oh {}", cardinal(hours.into()), ONES[minutes as usize]),
+        _ => format!("{} {}", cardinal(hours.into()), cardinal(minutes.into())),
+    };
+    if let Some(seconds) = seconds {
+        spoken.push_str(&format!(
+            " and {} {}",
+            cardinal(seconds.into()),
+            if seconds == 1 { "second" } else { "seconds" }
+        ));
+    }
+    spoken
+}
+
+/// Units are given by their digits, more than fit in words are read by digits.
+pub(super) fn money(currency: Currency, units: &str, cents: u64) -> String {
+    let (unit, units_name, cent, cents_name) = match currency {
+        Currency::Ruble => ("ruble", "rubles", "kopeck", "kopecks"),
+        Currency::Dollar => ("dollar", "dollars", "cent", "cents"),
+        Currency::Euro => ("euro", "euros", "cent", "cents"),
+        Currency::Pound => ("pound", "pounds", "penny", "pence"),
+    };
+    let mut spoken = match units.parse() {
+        Ok(1) => format!("one {unit}"),
+        Ok(units) if units <= super::MAX_SPOKEN => format!("{} {units_name}", cardinal(units)),
+        _ => format!("{} {units_name}", digits(units)),
+    };
+    if cents > 0 {
+        spoken.push_str(&format!(
+            " and {} {}",
+            cardinal(cents),
+            if cents == 1 { cent } else { cents_name }
+        ));
+    }
+    spoken
+}
+
+/// Phone numbers are read digit by digit with a pause between groups.
+pub(super) fn phone(plus: bool, groups: &[&str]) -> String {
+    let spoken = groups
+        .iter()
+        .map(|group| digits(group))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if plus {
+        format!("plus {spoken}")
+    } else {
+        spoken
+    }
+}
+
+pub(super) fn month(name: &str) -> Option<u32> {
+    let name = name.trim_end_matches('.');
+    MONTHS
+        .iter()
+        .position(|month| {
+            month.eq_ignore_ascii_case(name)
+                || (name.len() == 3 && month[..3].eq_ignore_ascii_case(name))
+        })
+        .map(|index| index as u32 + 1)
+}
+
+pub(super) const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("e.g.", "for example"),
+    ("i.e.", "that is"),
+    ("etc.", "et cetera"),
+    ("vs.", "versus"),
+    ("approx.", "approximately"),
+    ("Dr.", "Doctor"),
+    ("Mr.", "Mister"),
+    ("Mrs.", "Missus"),
+    ("Ms.", "Miz"),
+    ("Jr.", "Junior"),
+    ("Sr.", "Senior"),
+    ("St.", "Street"),
+    ("Ave.", "Avenue"),
+    ("Rd.", "Road"),
+    ("Blvd.", "Boulevard"),
+    ("Apt.", "Apartment"),
+    ("No.", "Number"),
+    ("km", "kilometers"),
+    ("kg", "kilograms"),
+];
+
+/// Abbreviations whose period may also end the sentence, others precede a name or a number.
+pub(super) const SENTENCE_FINAL: &[&str] = &["etc.", "Jr.", "Sr.", "St.", "Ave.", "Rd.", "Blvd."];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cardinals() {
+        assert_eq!(cardinal(0), "zero");
+        assert_eq!(cardinal(115), "one hundred fifteen");
+        assert_eq!(cardinal(2_000_021), "two million twenty-one");
+    }
+
+    #[test]
+    fn ordinals() {
+        assert_eq!(ordinal(1), "first");
+        assert_eq!(ordinal(12), "twelfth");
+        assert_eq!(ordinal(40), "fortieth");
+        assert_eq!(ordinal(103), "one hundred third");
+        assert!(ordinal_suffix_matches(11, "th"));
+        assert!(ordinal_suffix_matches(21, "ST"));
+        assert!(!ordinal_suffix_matches(12, "nd"));
+    }
+
+    #[test]
+    fn years() {
+        assert_eq!(year(1999), "nineteen ninety-nine");
+        assert_eq!(year(2005), "two thousand five");
+        assert_eq!(year(2024), "twenty twenty-four");
+    }
+
+    #[test]
+    fn money_and_phone() {
+        assert_eq!(
+            money(Currency::Dollar, "3", 50),
+            "three dollars and fifty cents"
+        );
+        assert_eq!(money(Currency::Pound, "1", 1), "one pound and one penny");
+        assert_eq!(phone(true, &["1", "800"]), "plus one, eight zero zero");
+    }
+}
@@ -0,0 +1,373 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use crate::{
+    headers::SpeakContent,
+    ssml::{Document, Element, ElementKind, Node},
+};
+
+mod en;
+mod ru;
+mod rules;
+
+/// Largest number spoken in words, longer numbers are read by digits.
+const MAX_SPOKEN: u64 = 999_999_999_999_999;
+
+/// Language with built-in normalization rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    Russian,
+    English,
+}
+
+impl Lang {
+    /// By the primary subtag of a language tag, e.g. `ru-RU`.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.split(['-', '_']).next()?;
+        if primary.eq_ignore_ascii_case("ru") {
+            Some(Self::Russian)
+        } else if primary.eq_ignore_ascii_case("en") {
+            Some(Self::English)
+        } else {
+            None
+        }
+    }
+
+    pub fn cardinal(self, n: u64) -> String {
+        match self {
+            Self::Russian => ru::cardinal(n, ru::Gender::Masculine),
+            Self::English => en::cardinal(n),
+        }
+    }
+
+    pub fn ordinal(self, n: u64) -> String {
+        match self {
+            Self::Russian => ru::ordinal(n, ru::Gender::Masculine, ru::Case::Nominative),
+            Self::English => en::ordinal(n),
+        }
+    }
+
+    /// Reads the digits one by one, other characters are skipped.
+    pub fn digits(self, digits: &str) -> String {
+        match self {
+            Self::Russian => ru::digits(digits),
+            Self::English => en::digits(digits),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Currency {
+    Ruble,
+    Dollar,
+    Euro,
+    Pound,
+}
+
+/// Rule verbalizing the token starting at the byte offset `at` of the text,
+/// it gives the length of the token in bytes and the words to speak instead.
+/// Rules are only tried at word starts and may look at the text before the token.
+pub trait NormalizeRule: Send + Sync {
+    fn normalize(&self, text: &str, at: usize, lang: Lang) -> Option<(usize, String)>;
+}
+
+impl<F> NormalizeRule for F
+where
+    F: Fn(&str, usize, Lang) -> Option<(usize, String)> + Send + Sync,
+{
+    fn normalize(&self, text: &str, at: usize, lang: Lang) -> Option<(usize, String)> {
+        self(text, at, lang)
+    }
+}
+
+/// Text normalization of SPEAK bodies: numbers, dates, times, currency, phone
+/// numbers and abbreviations are spelled out in words of the language.
+pub struct Normalizer {
+    lang: Lang,
+    rules: Vec<Box<dyn NormalizeRule>>,
+    builtin: bool,
+}
+
+impl Normalizer {
+    /// Normalizer with the built-in rules, `lang` is used when the content
+    /// does not specify its language.
+    pub fn new(lang: Lang) -> Self {
+        Self {
+            lang,
+            rules: Vec::new(),
+            builtin: true,
+        }
+    }
+
+    /// Normalizer with no rules but the ones added.
+    pub fn empty(lang: Lang) -> Self {
+        Self {
+            builtin: false,
+            ..Self::new(lang)
+        }
+    }
+
+    /// Adds a rule, added rules are tried in order before the built-in ones.
+    pub fn rule(mut self, rule: impl NormalizeRule + 'static) -> Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    pub fn lang(&self) -> Lang {
+        self.lang
+    }
+
+    pub fn normalize(&self, text: &str) -> String {
+        self.normalize_lang(text, self.lang)
+    }
+
+    pub fn normalize_lang(&self, text: &str, lang: Lang) -> String {
+        let builtin = match self.builtin {
+            true => rules::BUILTIN,
+            false => &[],
+        };
+        let mut normalized = String::with_capacity(text.len());
+        let mut position = 0;
+        let mut previous = None;
+        while let Some(c) = text[position..].chars().next() {
+            let at_word_start = !previous.is_some_and(char::is_alphanumeric);
+            let found = at_word_start
+                .then(|| {
+                    self.rules
+                        .iter()
+                        .find_map(|rule| rule.normalize(text, position, lang))
+                        .or_else(|| builtin.iter().find_map(|rule| rule(text, position, lang)))
+                })
+                .flatten()
+                .filter(|(len, _)| *len > 0 && text.is_char_boundary(position + len));
+            let Some((len, words)) = found else {
+                normalized.push(c);
+                previous = Some(c);
+                position += c.len_utf8();
+                continue;
+            };
+            if normalized.ends_with(char::is_alphanumeric)
+                && words.starts_with(char::is_alphanumeric)
+            {
+                normalized.push(' ');
+            }
+            normalized.push_str(&words);
+            position += len;
+            previous = text[..position].chars().next_back();
+            if words.ends_with(char::is_alphanumeric)
+                && text[position..].starts_with(char::is_alphanumeric)
+            {
+                normalized.push(' ');
+            }
+        }
+        normalized
+    }
+
+    /// Words for the text of a `say-as` element, `None` if the interpretation
+    /// is unknown or the text does not fit it.
+    pub fn say_as(
+        &self,
+        text: &str,
+        interpret_as: &str,
+        format: Option<&str>,
+        lang: Lang,
+    ) -> Option<String> {
+        let text = text.trim();
+        let interpret_as = interpret_as.to_ascii_lowercase();
+        // SSML 1.0 style `interpret-as="number" format="ordinal"`.
+        let interpret_as = match (interpret_as.as_str(), format) {
+            ("number", Some(format)) => format.to_ascii_lowercase(),
+            _ => interpret_as,
+        };
+        let whole = |rule: rules::Rule| {
+            rule(text, 0, lang)
+                .filter(|(len, _)| *len == text.len())
+                .map(|(_, words)| words)
+        };
+        match interpret_as.as_str() {
+            "cardinal" | "number" => whole(rules::number),
+            "ordinal" => whole(rules::ordinal).or_else(|| {
+                let digits = text.trim_end_matches(|c: char| !c.is_ascii_digit());
+                Some(lang.ordinal(digits.parse().ok()?))
+            }),
+            "digits" => text
+                .chars()
+                .all(|c| c.is_ascii_digit() || c.is_whitespace())
+                .then(|| lang.digits(text)),
+            "characters" | "spell-out" | "letters" => Some(
+                text.chars()
+                    .filter(|c| !c.is_whitespace())
+                    .map(|c| match c.to_digit(10) {
+                        Some(_) => lang.digits(&c.to_string()),
+                        None => c.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            "telephone" => {
+                let groups: Vec<&str> = text
+                    .split(|c: char| !c.is_ascii_digit())
+                    .filter(|group| !group.is_empty())
+                    .collect();
+                let plus = text.starts_with('+');
+                (!groups.is_empty()).then(|| match lang {
+                    Lang::Russian => ru::phone(plus, &groups),
+                    Lang::English => en::phone(plus, &groups),
+                })
+            }
+            "date" => say_as_date(text, format, lang).or_else(|| whole(rules::date)),
+            "time" => whole(rules::time),
+            "currency" => whole(rules::money),
+            _ => None,
+        }
+    }
+
+    /// Normalizes the text of the document in the language of each element,
+    /// `say-as` elements are replaced with their words. Text of `sub` and `phoneme`
+    /// is left as is, so is text in languages without rules.
+    pub fn normalize_document(&self, document: &mut Document) {
+        let lang = match document.lang() {
+            Some(tag) => Lang::from_tag(tag),
+            None => Some(self.lang),
+        };
+        self.normalize_element(&mut document.root, lang);
+    }
+
+    /// Parses, normalizes and serializes the SSML document.
+    pub fn normalize_ssml(&self, source: &str) -> crate::Result<String> {
+        let mut document = Document::parse(source)?;
+        self.normalize_document(&mut document);
+        Ok(document.to_ssml())
+    }
+
+    /// Normalizes plain text and SSML content in the language, URI lists
+    /// and multipart bodies are given as is.
+    pub fn normalize_content(
+        &self,
+        content: &SpeakContent,
+        lang: Lang,
+    ) -> crate::Result<SpeakContent> {
+        Ok(match content {
+            SpeakContent::PlainText(text) => {
+                SpeakContent::PlainText(self.normalize_lang(text, lang))
+            }
+            SpeakContent::Ssml(source) => {
+                let mut document = Document::parse(source)?;
+                let lang = match document.lang() {
+                    Some(tag) => Lang::from_tag(tag),
+                    None => Some(lang),
+                };
+                self.normalize_element(&mut document.root, lang);
+                SpeakContent::Ssml(document.to_ssml())
+            }
+            other => other.clone(),
+        })
+    }
+
+    fn normalize_element(&self, element: &mut Element, lang: Option<Lang>) {
+        if matches!(
+            element.kind,
+            ElementKind::Sub
+                | ElementKind::Phoneme
+                | ElementKind::Desc
+                | ElementKind::Lexicon
+                | ElementKind::Meta
+                | ElementKind::Metadata
+        ) {
+            return;
+        }
+        let lang = match element.lang() {
+            Some(tag) => Lang::from_tag(tag),
+            None => lang,
+        };
+        let mut children = Vec::with_capacity(element.children.len());
+        for child in std::mem::take(&mut element.children) {
+            match child {
+                Node::Text { text, span } => {
+                    let text = match lang {
+                        Some(lang) => self.normalize_lang(&text, lang),
+                        None => text,
+                    };
+                    children.push(Node::Text { text, span });
+                }
+                // Unknown interpretations are left for the backend.
+                Node::Element(child) if child.kind == ElementKind::SayAs => {
+                    let said =
+                        lang.zip(child.attr("interpret-as"))
+                            .and_then(|(lang, interpret_as)| {
+                                self.say_as(
+                                    &child.plain_text(),
+                                    interpret_as,
+                                    child.attr("format"),
+                                    lang,
+                                )
+                            });
+                    match said {
+                        Some(text) => children.push(Node::Text {
+                            text,
+                            span: child.span,
+                        }),
+                        None => children.push(Node::Element(child)),
+                    }
+                }
+                Node::Element(mut child) => {
+                    self.normalize_element(&mut child, lang);
+                    children.push(Node::Element(child));
+                }
+            }
+        }
+        element.children = children;
+    }
+}
+
+impl Default for Normalizer {
+    fn default() -> Self {
+        Self::new(Lang::Russian)
+    }
+}
+
+/// Date by the field order of the format, e.g. `dmy`, `mdy`, `ym` or `d`.
+fn say_as_date(text: &str, format: Option<&str>, lang: Lang) -> Option<String> {
+    let numbers: Vec<&str> = text
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|number| !number.is_empty())
+        .collect();
+    let format = match format {
+        Some(format) => format.to_ascii_lowercase(),
+        None if numbers.first().is_some_and(|first| first.len() == 4) => "ymd".to_owned(),
+        None => match lang {
+            Lang::Russian => "dmy".to_owned(),
+            Lang::English => "mdy".to_owned(),
+        },
+    };
+    if format.len() != numbers.len() {
+        return None;
+    }
+    let (mut day, mut month, mut year) = (None, None, None);
+    for (field, number) in format.chars().zip(&numbers) {
+        match field {
+            'd' => day = Some(number.parse().ok()?),
+            'm' => month = Some(number.parse().ok()?),
+            'y' => year = Some(number.parse().ok()?),
+            _ => return None,
+        }
+    }
+    let valid = match (day, month) {
+        (Some(day), Some(month)) => rules::valid_date(day, month),
+        (Some(day), None) => (1..=31).contains(&day),
+        (None, Some(month)) => (1..=12).contains(&month),
+        (None, None) => year.is_some(),
+    };
+    valid.then(|| rules::spoken_date(lang, day, month, year))
+}
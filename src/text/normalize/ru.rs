@@ -0,0 +1,688 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use super::Currency;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Gender {
+    Masculine,
+    Feminine,
+    Neuter,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Case {
+    Nominative,
+    Genitive,
+    Dative,
+    Accusative,
+    Instrumental,
+    Prepositional,
+}
+
+const UNITS: [&str; 10] = [
+    "",
+    "один",
+    "два",
+    "три",
+    "четыре",
+    "пять",
+    "шесть",
+    "семь",
+    "восемь",
+    "девять",
+];
+const TEENS: [&str; 10] = [
+    "десять",
+    "одиннадцать",
+    "двенадцать",
+    "тринадцать",
+    "четырнадцать",
+    "пятнадцать",
+    "шестнадцать",
+    "семнадцать",
+    "восемнадцать",
+    "девятнадцать",
+];
+const TENS: [&str; 10] = [
+    "",
+    "",
+    "двадцать",
+    "тридцать",
+    "сорок",
+    "пятьдесят",
+    "шестьдесят",
+    "семьдесят",
+    "восемьдесят",
+    "девяносто",
+];
+const HUNDREDS: [&str; 10] = [
+    "",
+    "сто",
+    "двести",
+    "триста",
+    "четыреста",
+    "пятьсот",
+    "шестьсот",
+    "семьсот",
+    "восемьсот",
+    "девятьсот",
+];
+/// Scales with their gender and plural forms, from a thousand up.
+const SCALES: [(Gender, [&str; 3]); 4] = [
+    (Gender::Feminine, ["тысяча", "тысячи", "тысяч"]),
+    (Gender::Masculine, ["миллион", "миллиона", "миллионов"]),
+    (Gender::Masculine, ["миллиард", "миллиарда", "миллиардов"]),
+    (Gender::Masculine, ["триллион", "триллиона", "триллионов"]),
+];
+/// Genitive forms used in compound ordinals like `двухтысячный`.
+const UNITS_GENITIVE: [&str; 10] = [
+    "",
+    "одно",
+    "двух",
+    "трёх",
+    "четырёх",
+    "пяти",
+    "шести",
+    "семи",
+    "восьми",
+    "девяти",
+];
+const TEENS_GENITIVE: [&str; 10] = [
+    "десяти",
+    "одиннадцати",
+    "двенадцати",
+    "тринадцати",
+    "четырнадцати",
+    "пятнадцати",
+    "шестнадцати",
+    "семнадцати",
+    "восемнадцати",
+    "девятнадцати",
+];
+const TENS_GENITIVE: [&str; 10] = [
+    "",
+    "",
+    "двадцати",
+    "тридцати",
+    "сорока",
+    "пятидесяти",
+    "шестидесяти",
+    "семидесяти",
+    "восьмидесяти",
+    "девяноста",
+];
+const HUNDREDS_GENITIVE: [&str; 10] = [
+    "",
+    "сто",
+    "двухсот",
+    "трёхсот",
+    "четырёхсот",
+    "пятисот",
+    "шестисот",
+    "семисот",
+    "восьмисот",
+    "девятисот",
+];
+const SCALE_STEMS: [&str; 4] = ["тысячн", "миллионн", "миллиардн", "триллионн"];
+const MONTHS: [&str; 12] = [
+    "января",
+    "февраля",
+    "марта",
+    "апреля",
+    "мая",
+    "июня",
+    "июля",
+    "августа",
+    "сентября",
+    "октября",
+    "ноября",
+    "декабря",
+];
+const DIGITS: [&str; 10] = [
+    "ноль",
+    "один",
+    "два",
+    "три",
+    "четыре",
+    "пять",
+    "шесть",
+    "семь",
+    "восемь",
+    "девять",
+];
+
+/// Form by the number: one, few or many, e.g. `рубль`, `рубля`, `рублей`.
+pub(super) fn plural<'a>(n: u64, forms: &[&'a str; 3]) -> &'a str {
+    match (n % 100, n % 10) {
+        (11..=14, _) => forms[2],
+        (_, 1) => forms[0],
+        (_, 2..=4) => forms[1],
+        _ => forms[2],
+    }
+}
+
+pub(super) fn cardinal(n: u64, gender: Gender) -> String {
+    if n == 0 {
+        return DIGITS[0].to_owned();
+    }
+    if n > super::MAX_SPOKEN {
+        return digits(&n.to_string());
+    }
+    cardinal_words(n, gender).join(" ")
+}
+
+fn cardinal_words(n: u64, gender: Gender) -> Vec<&'static str> {
+    let mut words = Vec::new();
+    let mut groups = Vec::new();
+    let mut rest = n;
+    while rest > 0 {
+        groups.push(rest % 1000);
+        rest /= 1000;
+    }
+    for (scale, group) in groups.iter().enumerate().rev() {
+        if *group == 0 {
+            continue;
+        }
+        if scale == 0 {
+            words.extend(triad(*group, gender));
+            continue;
+        }
+        let Some((scale_gender, forms)) = SCALES.get(scale - 1) else {
+            // Numbers beyond trillions are read by digits before getting here.
+            continue;
+        };
+        let leading_thousand = scale == 1 && *group == 1 && words.is_empty();
+        if !leading_thousand {
+            words.extend(triad(*group, *scale_gender));
+        }
+        words.push(plural(*group, forms));
+    }
+    words
+}
+
+fn triad(n: u64, gender: Gender) -> Vec<&'static str> {
+    let mut words = Vec::new();
+    let (hundreds, tens, units) = (
+        (n / 100) as usize,
+        (n / 10 % 10) as usize,
+        (n % 10) as usize,
+    );
+    if hundreds > 0 {
+        words.push(HUNDREDS[hundreds]);
+    }
+    if tens == 1 {
+        words.push(TEENS[units]);
+        return words;
+    }
+    if tens > 1 {
+        words.push(TENS[tens]);
+    }
+    if units > 0 {
+        words.push(match (units, gender) {
+            (1, Gender::Feminine) => "одна",
+            (1, Gender::Neuter) => "одно",
+            (2, Gender::Feminine) => "две",
+            _ => UNITS[units],
+        });
+    }
+    words
+}
+
+#[derive(Clone, Copy)]
+enum Declension {
+    /// `первый`
+    Hard,
+    /// `второй`
+    Stressed,
+    /// `третий`
+    Third,
+}
+
+fn ending(declension: Declension, gender: Gender, case: Case) -> &'static str {
+    use Case::*;
+    use Gender::*;
+    if let Declension::Third = declension {
+        return match (gender, case) {
+            (Masculine, Nominative | Accusative) => "ий",
+            (Feminine, Nominative) => "ья",
+            (Feminine, Accusative) => "ью",
+            (Feminine, _) => "ьей",
+            (Neuter, Nominative | Accusative) => "ье",
+            (_, Genitive) => "ьего",
+            (_, Dative) => "ьему",
+            (_, Instrumental) => "ьим",
+            (_, Prepositional) => "ьем",
+        };
+    }
+    match (gender, case) {
+        (Masculine, Nominative | Accusative) => match declension {
+            Declension::Stressed => "ой",
+            _ => "ый",
+        },
+        (Feminine, Nominative) => "ая",
+        (Feminine, Accusative) => "ую",
+        (Feminine, _) => "ой",
+        (Neuter, Nominative | Accusative) => "ое",
+        (_, Genitive) => "ого",
+        (_, Dative) => "ому",
+        (_, Instrumental) => "ым",
+        (_, Prepositional) => "ом",
+    }
+}
+
+/// Stem of the ordinal of a number below a thousand which is not spelled in parts.
+fn stem(n: u64) -> (String, Declension) {
+    let (hundreds, tens, units) = (
+        (n / 100) as usize,
+        (n / 10 % 10) as usize,
+        (n % 10) as usize,
+    );
+    if matches!(n % 100, 0) {
+        let stem = match hundreds {
+            1 => "сот",
+            _ => HUNDREDS_GENITIVE[hundreds],
+        };
+        return (stem.to_owned(), Declension::Hard);
+    }
+    if tens == 1 {
+        return (
+            TEENS[units].trim_end_matches('ь').to_owned(),
+            Declension::Hard,
+        );
+    }
+    if units == 0 {
+        return match tens {
+            4 => ("сороков".to_owned(), Declension::Stressed),
+            9 => ("девяност".to_owned(), Declension::Hard),
+            5..=8 => (
+                TENS_GENITIVE[tens].trim_end_matches('и').to_owned(),
+                Declension::Hard,
+            ),
+            _ => (
+                TENS[tens].trim_end_matches('ь').to_owned(),
+                Declension::Hard,
+            ),
+        };
+    }
+    match units {
+        1 => ("перв".to_owned(), Declension::Hard),
+        2 => ("втор".to_owned(), Declension::Stressed),
+        3 => ("трет".to_owned(), Declension::Third),
+        4 => ("четвёрт".to_owned(), Declension::Hard),
+        5 => ("пят".to_owned(), Declension::Hard),
+        6 => ("шест".to_owned(), Declension::Stressed),
+        7 => ("седьм".to_owned(), Declension::Stressed),
+        8 => ("восьм".to_owned(), Declension::Stressed),
+        _ => ("девят".to_owned(), Declension::Hard),
+    }
+}
+
+/// Ordinal, only its last word is declined, e.g. `две тысячи двадцать четвёртого`.
+pub(super) fn ordinal(n: u64, gender: Gender, case: Case) -> String {
+    if n == 0 {
+        return format!("нулев{}", ending(Declension::Stressed, gender, case));
+    }
+    if n > super::MAX_SPOKEN {
+        return digits(&n.to_string());
+    }
+    let low = n % 1000;
+    if low != 0 {
+        let mut words = cardinal_words(n - low, Gender::Masculine);
+        let (hundreds, rest) = (low / 100, low % 100);
+        let last = if rest == 0 {
+            low
+        } else {
+            if hundreds > 0 {
+                words.push(HUNDREDS[hundreds as usize]);
+            }
+            if rest > 20 && rest % 10 != 0 {
+                words.push(TENS[(rest / 10) as usize]);
+                rest % 10
+            } else {
+                rest
+            }
+        };
+        let (stem, declension) = stem(last);
+        let mut words: Vec<String> = words.into_iter().map(ToOwned::to_owned).collect();
+        words.push(stem + ending(declension, gender, case));
+        return words.join(" ");
+    }
+    // Round thousands, millions and so on make a compound word.
+    let mut scale = 0;
+    let mut rest = n;
+    while matches!(rest % 1000, 0) {
+        rest /= 1000;
+        scale += 1;
+    }
+    let Some(scale_stem) = SCALE_STEMS.get(scale - 1) else {
+        return cardinal(n, Gender::Masculine);
+    };
+    let multiplier = rest % 1000;
+    let higher = cardinal_words(
+        n - multiplier * 1000u64.pow(scale as u32),
+        Gender::Masculine,
+    );
+    let mut compound = String::new();
+    if multiplier != 1 {
+        let (hundreds, tens, units) = (
+            (multiplier / 100) as usize,
+            (multiplier / 10 % 10) as usize,
+            (multiplier % 10) as usize,
+        );
+        compound.push_str(HUNDREDS_GENITIVE[hundreds]);
+        if tens == 1 {
+            compound.push_str(TEENS_GENITIVE[units]);
+        } else {
+            compound.push_str(TENS_GENITIVE[tens]);
+            compound.push_str(UNITS_GENITIVE[units]);
+        }
+    }
+    compound.push_str(scale_stem);
+    compound.push_str(ending(Declension::Hard, gender, case));
+    let mut words: Vec<String> = higher.into_iter().map(ToOwned::to_owned).collect();
+    words.push(compound);
+    words.join(" ")
+}
+
+/// Form of an ordinal by its written ending, e.g. `5-й`, `2-го`, `21-я`.
+pub(super) fn ordinal_form(suffix: &str) -> Option<(Gender, Case)> {
+    Some(match suffix {
+        "й" | "ый" | "ий" => (Gender::Masculine, Case::Nominative),
+        "я" | "ая" | "ья" => (Gender::Feminine, Case::Nominative),
+        "ю" | "ую" | "ью" => (Gender::Feminine, Case::Accusative),
+        "ой" | "ей" => (Gender::Feminine, Case::Genitive),
+        "е" | "ое" | "ье" => (Gender::Neuter, Case::Nominative),
+        "го" | "ого" | "его" => (Gender::Masculine, Case::Genitive),
+        "му" | "ому" | "ему" => (Gender::Masculine, Case::Dative),
+        "м" | "ым" | "им" => (Gender::Masculine, Case::Instrumental),
+        "ом" | "ем" => (Gender::Masculine, Case::Prepositional),
+        _ => return None,
+    })
+}
+
+pub(super) fn digits(digits: &str) -> String {
+    digits
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .map(|digit| DIGITS[digit as usize])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `три целых четырнадцать сотых`, fractions longer than six digits are read by digits.
+pub(super) fn decimal(integer: u64, fraction: &str) -> String {
+    const DENOMINATORS: [&str; 6] = [
+        "десят",
+        "сот",
+        "тысячн",
+        "десятитысячн",
+        "стотысячн",
+        "миллионн",
+    ];
+    let whole = format!(
+        "{} {}",
+        cardinal(integer, Gender::Feminine),
+        if integer % 10 == 1 && integer % 100 != 11 {
+            "целая"
+        } else {
+            "целых"
+        }
+    );
+    let Some(denominator) = DENOMINATORS.get(fraction.len().wrapping_sub(1)) else {
+        return format!("{whole} и {}", self::digits(fraction));
+    };
+    let numerator: u64 = fraction.parse().unwrap_or_default();
+    let ending = if numerator % 10 == 1 && numerator % 100 != 11 {
+        "ая"
+    } else {
+        "ых"
+    };
+    format!(
+        "{whole} {} {denominator}{ending}",
+        cardinal(numerator, Gender::Feminine)
+    )
+}
+
+/// `двенадцатое мая две тысячи двадцать четвёртого года`.
+pub(super) fn date(day: Option<u32>, month: Option<u32>, year: Option<u64>) -> String {
+    let mut words = Vec::new();
+    if let Some(day) = day {
+        words.push(ordinal(day.into(), Gender::Neuter, Case::Nominative));
+    }
+    if let Some(month) = month {
+        let name = MONTHS[(month - 1) as usize];
+        // A month without a day is named in the nominative.
+        words.push(match day {
+            Some(_) => name.to_owned(),
+            None => month_nominative(name),
+        });
+    }
+    if let Some(year) = year {
+        words.push(year_words(year, Case::Genitive));
+    }
+    words.join(" ")
+}
+
+fn month_nominative(genitive: &str) -> String {
+    match genitive {
+        "мая" => "май".to_owned(),
+        "марта" | "августа" => genitive.trim_end_matches('а').to_owned(),
+        other => format!("{}ь", other.trim_end_matches('я')),
+    }
+}
+
+/// `две тысячи двадцать четвёртого года`, `двадцать четвёртом году`.
+pub(super) fn year_words(year: u64, case: Case) -> String {
+    let noun = match case {
+        Case::Nominative | Case::Accusative => "год",
+        Case::Genitive => "года",
+        Case::Dative | Case::Prepositional => "году",
+        Case::Instrumental => "годом",
+    };
+    format!("{} {noun}", ordinal(year, Gender::Masculine, case))
+}
+
+/// `пятнадцать часов тридцать минут`.
+pub(super) fn time(hours: u32, minutes: u32, seconds: Option<u32>) -> String {
+    let mut words = vec![
+        cardinal(hours.into(), Gender::Masculine),
+        plural(hours.into(), &["час", "часа", "часов"]).to_owned(),
+    ];
+    if minutes > 0 || seconds.is_some() {
+        words.push(cardinal(minutes.into(), Gender::Feminine));
+        words.push(plural(minutes.into(), &["минута", "минуты", "минут"]).to_owned());
+    }
+    if let Some(seconds) = seconds {
+        words.push(cardinal(seconds.into(), Gender::Feminine));
+        words.push(plural(seconds.into(), &["секунда", "секунды", "секунд"]).to_owned());
+    }
+    words.join(" ")
+}
+
+/// Units are given by their digits, more than fit in words are read by digits.
+pub(super) fn money(currency: Currency, units: &str, cents: u64) -> String {
+    let (unit_gender, unit_forms, cent_gender, cent_forms) = match currency {
+        Currency::Ruble => (
+            Gender::Masculine,
+            ["рубль", "рубля", "рублей"],
+            Gender::Feminine,
+            ["копейка", "копейки", "копеек"],
+        ),
+        Currency::Dollar => (
+            Gender::Masculine,
+            ["доллар", "доллара", "долларов"],
+            Gender::Masculine,
+            ["цент", "цента", "центов"],
+        ),
+        Currency::Euro => (
+            Gender::Masculine,
+            ["евро", "евро", "евро"],
+            Gender::Masculine,
+            ["цент", "цента", "центов"],
+        ),
+        Currency::Pound => (
+            Gender::Masculine,
+            ["фунт", "фунта", "фунтов"],
+            Gender::Masculine,
+            ["пенс", "пенса", "пенсов"],
+        ),
+    };
+    let mut words = match units.parse() {
+        Ok(units) if units <= super::MAX_SPOKEN => format!(
+            "{} {}",
+            cardinal(units, unit_gender),
+            plural(units, &unit_forms)
+        ),
+        _ => format!("{} {}", digits(units), unit_forms[2]),
+    };
+    if cents > 0 {
+        words.push_str(&format!(
+            " {} {}",
+            cardinal(cents, cent_gender),
+            plural(cents, &cent_forms)
+        ));
+    }
+    words
+}
+
+/// Groups of a phone number as numbers, the ones with leading zeros by digits.
+pub(super) fn phone(plus: bool, groups: &[&str]) -> String {
+    let spoken = groups
+        .iter()
+        .map(|group| match group.parse::<u64>() {
+            Ok(n) if !group.starts_with('0') && group.len() <= 3 => cardinal(n, Gender::Masculine),
+            _ => digits(group),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    if plus {
+        format!("плюс {spoken}")
+    } else {
+        spoken
+    }
+}
+
+pub(super) fn month(name: &str) -> Option<u32> {
+    let name = name.to_lowercase();
+    MONTHS
+        .iter()
+        .position(|month| *month == name)
+        .map(|index| index as u32 + 1)
+}
+
+pub(super) const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("т.е.", "то есть"),
+    ("т.д.", "так далее"),
+    ("т.п.", "тому подобное"),
+    ("т.к.", "так как"),
+    ("и др.", "и другие"),
+    ("см.", "смотри"),
+    ("ул.", "улица"),
+    ("пр-т", "проспект"),
+    ("просп.", "проспект"),
+    ("пер.", "переулок"),
+    ("наб.", "набережная"),
+    ("пл.", "площадь"),
+    ("ш.", "шоссе"),
+    ("д.", "дом"),
+    ("корп.", "корпус"),
+    ("стр.", "строение"),
+    ("кв.", "квартира"),
+    ("г.", "город"),
+    ("обл.", "область"),
+    ("р-н", "район"),
+    ("им.", "имени"),
+    ("тыс.", "тысяч"),
+    ("млн", "миллионов"),
+    ("млрд", "миллиардов"),
+    ("руб.", "рублей"),
+    ("коп.", "копеек"),
+    ("мин.", "минут"),
+    ("сек.", "секунд"),
+    ("кг", "килограммов"),
+    ("км", "километров"),
+    ("№", "номер"),
+];
+
+/// Abbreviations whose period may also end the sentence, others precede a name or a number.
+pub(super) const SENTENCE_FINAL: &[&str] = &[
+    "т.д.", "т.п.", "и др.", "руб.", "коп.", "тыс.", "мин.", "сек.",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cardinal_genders() {
+        assert_eq!(cardinal(0, Gender::Masculine), "ноль");
+        assert_eq!(cardinal(21, Gender::Feminine), "двадцать одна");
+        assert_eq!(cardinal(1000, Gender::Masculine), "тысяча");
+        assert_eq!(
+            cardinal(2_001_002, Gender::Masculine),
+            "два миллиона одна тысяча два"
+        );
+    }
+
+    #[test]
+    fn ordinals() {
+        assert_eq!(
+            ordinal(21, Gender::Feminine, Case::Nominative),
+            "двадцать первая"
+        );
+        assert_eq!(
+            ordinal(2024, Gender::Masculine, Case::Genitive),
+            "две тысячи двадцать четвёртого"
+        );
+        assert_eq!(
+            ordinal(2000, Gender::Masculine, Case::Nominative),
+            "двухтысячный"
+        );
+        assert_eq!(ordinal(3, Gender::Neuter, Case::Nominative), "третье");
+        assert_eq!(
+            ordinal(50, Gender::Masculine, Case::Nominative),
+            "пятидесятый"
+        );
+        assert_eq!(
+            ordinal(80, Gender::Feminine, Case::Nominative),
+            "восьмидесятая"
+        );
+        assert_eq!(
+            ordinal(1950, Gender::Masculine, Case::Genitive),
+            "тысяча девятьсот пятидесятого"
+        );
+        assert_eq!(
+            ordinal(1980, Gender::Masculine, Case::Prepositional),
+            "тысяча девятьсот восьмидесятом"
+        );
+    }
+
+    #[test]
+    fn plurals() {
+        let forms = ["рубль", "рубля", "рублей"];
+        assert_eq!(plural(1, &forms), "рубль");
+        assert_eq!(plural(3, &forms), "рубля");
+        assert_eq!(plural(11, &forms), "рублей");
+        assert_eq!(plural(111, &forms), "рублей");
+        assert_eq!(plural(121, &forms), "рубль");
+    }
+
+    #[test]
+    fn money_forms() {
+        assert_eq!(money(Currency::Ruble, "1500", 0), "тысяча пятьсот рублей");
+        assert_eq!(money(Currency::Ruble, "2", 1), "два рубля одна копейка");
+        assert_eq!(money(Currency::Euro, "1", 0), "один евро");
+    }
+}
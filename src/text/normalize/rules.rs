@@ -0,0 +1,669 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+//! Built-in rules, each verbalizes a token starting at a byte offset of the text.
+
+use super::{en, ru, Currency, Lang};
+
+pub(super) type Rule = fn(&str, usize, Lang) -> Option<(usize, String)>;
+
+/// In the order they are tried, the more specific patterns go first.
+pub(super) const BUILTIN: &[Rule] = &[
+    phone,
+    date,
+    time,
+    money,
+    year,
+    ordinal,
+    number,
+    abbreviation,
+];
+
+const CURRENCY_SYMBOLS: &[(&str, Currency)] = &[
+    ("$", Currency::Dollar),
+    ("€", Currency::Euro),
+    ("£", Currency::Pound),
+    ("₽", Currency::Ruble),
+];
+const CURRENCY_SUFFIXES: &[(&str, Currency)] = &[
+    ("₽", Currency::Ruble),
+    ("руб.", Currency::Ruble),
+    ("руб", Currency::Ruble),
+    ("р.", Currency::Ruble),
+    ("RUB", Currency::Ruble),
+    ("$", Currency::Dollar),
+    ("USD", Currency::Dollar),
+    ("€", Currency::Euro),
+    ("EUR", Currency::Euro),
+    ("£", Currency::Pound),
+    ("GBP", Currency::Pound),
+];
+
+/// Number with its digits as written, without group separators.
+pub(super) struct Number {
+    pub digits: String,
+    /// `None` if the number is too long to be read as a whole.
+    pub value: Option<u64>,
+    pub fraction: Option<String>,
+    /// Length in bytes of the number in the text.
+    pub len: usize,
+}
+
+impl Number {
+    /// Leading zeros and long numbers like account ids are read by digits.
+    fn spoken(&self, lang: Lang) -> String {
+        let value = match self.value {
+            Some(value) if value <= super::MAX_SPOKEN => value,
+            _ => return lang.digits(&self.digits),
+        };
+        match &self.fraction {
+            Some(fraction) => match lang {
+                Lang::Russian => ru::decimal(value, fraction),
+                Lang::English => en::decimal(value, fraction),
+            },
+            None if self.digits.len() > 1 && self.digits.starts_with('0') => {
+                lang.digits(&self.digits)
+            }
+            None => lang.cardinal(value),
+        }
+    }
+}
+
+/// Parses a number at the start of the text: `1 500` or `1,500` by the language,
+/// with a fraction after one of the separators.
+pub(super) fn parse_number(text: &str, lang: Lang, fraction_separators: &[char]) -> Option<Number> {
+    let first = leading_digits(text);
+    if first.is_empty() {
+        return None;
+    }
+    let mut digits = first.to_owned();
+    let mut len = first.len();
+    if first.len() <= 3 && !first.starts_with('0') {
+        let group_separators: &[char] = match lang {
+            Lang::Russian => &[' ', '\u{a0}', '\u{202f}'],
+            Lang::English => &[','],
+        };
+        while let Some(separator) = text[len..]
+            .chars()
+            .next()
+            .filter(|c| group_separators.contains(c))
+        {
+            let group = leading_digits(&text[len + separator.len_utf8()..]);
+            if group.len() != 3 {
+                break;
+            }
+            digits.push_str(group);
+            len += separator.len_utf8() + group.len();
+        }
+    }
+    let mut fraction = None;
+    if let Some(separator) = text[len..]
+        .chars()
+        .next()
+        .filter(|c| fraction_separators.contains(c))
+    {
+        let after = leading_digits(&text[len + separator.len_utf8()..]);
+        if !after.is_empty() {
+            fraction = Some(after.to_owned());
+            len += separator.len_utf8() + after.len();
+        }
+    }
+    let value = match digits.len() {
+        ..=18 => digits.parse().ok(),
+        _ => None,
+    };
+    Some(Number {
+        digits,
+        value,
+        fraction,
+        len,
+    })
+}
+
+fn decimal_separator(lang: Lang) -> char {
+    match lang {
+        Lang::Russian => ',',
+        Lang::English => '.',
+    }
+}
+
+/// `12`, `-12`, `1 500`, `3,14`, `007`.
+pub(super) fn number(text: &str, at: usize, lang: Lang) -> Option<(usize, String)> {
+    let rest = &text[at..];
+    let sign = rest
+        .chars()
+        .next()
+        .filter(|c| matches!(c, '-' | '−'))
+        .filter(|_| {
+            matches!(
+                text[..at].chars().next_back(),
+                None | Some(' ' | '\t' | '\n' | '(')
+            )
+        })
+        .map_or(0, char::len_utf8);
+    let number = parse_number(&rest[sign..], lang, &[decimal_separator(lang)])?;
+    let spoken = number.spoken(lang);
+    if sign == 0 {
+        return Some((number.len, spoken));
+    }
+    let minus = match lang {
+        Lang::Russian => "минус",
+        Lang::English => "minus",
+    };
+    Some((sign + number.len, format!("{minus} {spoken}")))
+}
+
+/// `5-й`, `2-го` in Russian, `1st`, `22nd` in English.
+pub(super) fn ordinal(text: &str, at: usize, lang: Lang) -> Option<(usize, String)> {
+    let rest = &text[at..];
+    let digits = leading_digits(rest);
+    if digits.is_empty() {
+        return None;
+    }
+    // Longer numbers are read by digits, the last two give the suffix.
+    let n = digits.parse().ok().filter(|n| *n <= super::MAX_SPOKEN);
+    let last = digits[digits.len().saturating_sub(2)..].parse().ok()?;
+    let after = &rest[digits.len()..];
+    match lang {
+        Lang::Russian => {
+            let suffix_text = after.strip_prefix('-')?;
+            let suffix: String = suffix_text
+                .chars()
+                .take_while(|c| c.is_alphabetic())
+                .collect();
+            let (gender, case) = ru::ordinal_form(&suffix)?;
+            let len = digits.len() + 1 + suffix.len();
+            boundary(rest, len).then(|| {
+                (
+                    len,
+                    n.map_or_else(|| lang.digits(digits), |n| ru::ordinal(n, gender, case)),
+                )
+            })
+        }
+        Lang::English => {
+            let suffix = after.get(..2)?;
+            let len = digits.len() + 2;
+            (en::ordinal_suffix_matches(last, suffix) && boundary(rest, len))
+                .then(|| (len, n.map_or_else(|| lang.digits(digits), en::ordinal)))
+        }
+    }
+}
+
+/// `15:30`, `9:05:10`, `9:05 pm` in English.
+pub(super) fn time(text: &str, at: usize, lang: Lang) -> Option<(usize, String)> {
+    let rest = &text[at..];
+    let hours_digits = leading_digits(rest);
+    if hours_digits.is_empty() || hours_digits.len() > 2 {
+        return None;
+    }
+    let mut len = hours_digits.len();
+    let mut parts = vec![hours_digits.parse::<u32>().ok()?];
+    while parts.len() < 3 {
+        let Some(after) = rest[len..].strip_prefix(':') else {
+            break;
+        };
+        let digits = leading_digits(after);
+        if digits.len() != 2 {
+            return None;
+        }
+        parts.push(digits.parse().ok()?);
+        len += 1 + digits.len();
+    }
+    let (hours, minutes, seconds) = match parts[..] {
+        [hours, minutes] => (hours, minutes, None),
+        [hours, minutes, seconds] if seconds < 60 => (hours, minutes, Some(seconds)),
+        _ => return None,
+    };
+    if hours > 23 || minutes > 59 || rest[len..].starts_with(':') || !boundary(rest, len) {
+        return None;
+    }
+    match lang {
+        Lang::Russian => Some((len, ru::time(hours, minutes, seconds))),
+        Lang::English => {
+            let mut spoken = en::time(hours, minutes, seconds);
+            let after = &rest[len..];
+            let trimmed = after.trim_start_matches(' ');
+            for (marker, words) in [
+                ("a.m.", "a m"),
+                ("p.m.", "p m"),
+                ("am", "a m"),
+                ("pm", "p m"),
+            ] {
+                let matched = trimmed
+                    .get(..marker.len())
+                    .is_some_and(|written| written.eq_ignore_ascii_case(marker));
+                if matched && (marker.ends_with('.') || boundary(trimmed, marker.len())) {
+                    len += after.len() - trimmed.len() + marker.len();
+                    spoken = format!("{spoken} {words}");
+                    break;
+                }
+            }
+            Some((len, spoken))
+        }
+    }
+}
+
+/// `12.05.2024`, `2024-05-12`, `05/12/2024` in English, `12 мая 2024 г.` and `May 12, 2024`.
+pub(super) fn date(text: &str, at: usize, lang: Lang) -> Option<(usize, String)> {
+    numeric_date(text, at, lang).or_else(|| written_date(text, at, lang))
+}
+
+fn numeric_date(text: &str, at: usize, lang: Lang) -> Option<(usize, String)> {
+    let rest = &text[at..];
+    let first = leading_digits(rest);
+    let separator = rest[first.len()..].chars().next()?;
+    if !matches!(separator, '.' | '/' | '-') {
+        return None;
+    }
+    let second = leading_digits(&rest[first.len() + 1..]);
+    let second_end = first.len() + 1 + second.len();
+    if !rest[second_end..].starts_with(separator) {
+        return None;
+    }
+    let third = leading_digits(&rest[second_end + 1..]);
+    let mut len = second_end + 1 + third.len();
+    if !boundary(rest, len)
+        || rest[len..].starts_with(|c: char| {
+            matches!(c, '.' | '/' | '-')
+                && rest[len + 1..].starts_with(|c: char| c.is_ascii_digit())
+        })
+    {
+        return None;
+    }
+    let short = |digits: &str| matches!(digits.len(), 1 | 2);
+    let (day, month, year) = if first.len() == 4 && short(second) && short(third) {
+        (third, second, first)
+    } else if short(first) && short(second) && third.len() == 4 {
+        match (lang, separator) {
+            (Lang::English, '/' | '-') => (second, first, third),
+            _ => (first, second, third),
+        }
+    } else {
+        return None;
+    };
+    let (day, month, year) = (day.parse().ok()?, month.parse().ok()?, year.parse().ok()?);
+    if !valid_date(day, month) {
+        return None;
+    }
+    if lang == Lang::Russian {
+        if let Some((suffix, ru::Case::Genitive)) = ru_year_suffix(&rest[len..]) {
+            len += suffix;
+        }
+    }
+    Some((len, spoken_date(lang, Some(day), Some(month), Some(year))))
+}
+
+fn written_date(text: &str, at: usize, lang: Lang) -> Option<(usize, String)> {
+    let rest = &text[at..];
+    let (day, month, mut len) = match lang {
+        Lang::Russian => {
+            let day = leading_digits(rest);
+            let after = rest[day.len()..].strip_prefix(' ')?;
+            let name = leading_word(after);
+            let month = ru::month(name)?;
+            (day.parse().ok()?, month, day.len() + 1 + name.len())
+        }
+        Lang::English => {
+            let name = leading_word(rest);
+            let month = en::month(name)?;
+            let mut len = name.len();
+            if rest[len..].starts_with('.') {
+                len += 1;
+            }
+            let after = rest[len..].strip_prefix(' ')?;
+            let day = leading_digits(after);
+            len += 1 + day.len();
+            if let Some(suffix) = rest.get(len..len + 2) {
+                if suffix.chars().all(|c| c.is_ascii_alphabetic())
+                    && en::ordinal_suffix_matches(day.parse().ok()?, suffix)
+                {
+                    len += 2;
+                }
+            }
+            (day.parse().ok()?, month, len)
+        }
+    };
+    if !valid_date(day, month) || !boundary(rest, len) {
+        return None;
+    }
+    let mut year = None;
+    let separator = match lang {
+        Lang::Russian => " ",
+        Lang::English if rest[len..].starts_with(", ") => ", ",
+        Lang::English => " ",
+    };
+    if let Some(after) = rest[len..].strip_prefix(separator) {
+        let digits = leading_digits(after);
+        if digits.len() == 4 && boundary(after, 4) {
+            year = digits.parse().ok();
+            len += separator.len() + 4;
+            if lang == Lang::Russian {
+                if let Some((suffix, ru::Case::Genitive)) = ru_year_suffix(&rest[len..]) {
+                    len += suffix;
+                }
+            }
+        }
+    }
+    Some((len, spoken_date(lang, Some(day), Some(month), year)))
+}
+
+pub(super) fn spoken_date(
+    lang: Lang,
+    day: Option<u32>,
+    month: Option<u32>,
+    year: Option<u64>,
+) -> String {
+    match lang {
+        Lang::Russian => ru::date(day, month, year),
+        Lang::English => en::date(day, month, year),
+    }
+}
+
+pub(super) fn valid_date(day: u32, month: u32) -> bool {
+    (1..=31).contains(&day) && (1..=12).contains(&month)
+}
+
+/// `2024 г.`, `в 2024 году` in Russian.
+pub(super) fn year(text: &str, at: usize, lang: Lang) -> Option<(usize, String)> {
+    if lang != Lang::Russian {
+        return None;
+    }
+    let rest = &text[at..];
+    let digits = leading_digits(rest);
+    if digits.len() != 4 {
+        return None;
+    }
+    let (suffix, mut case) = ru_year_suffix(&rest[4..])?;
+    let preceding = text[..at].trim_end();
+    let preposition = preceding
+        .rsplit(|c: char| !c.is_alphabetic())
+        .next()
+        .unwrap_or_default();
+    if case == ru::Case::Genitive
+        && rest[4..].trim_start().starts_with("г.")
+        && matches!(preposition, "в" | "В" | "во" | "Во")
+    {
+        case = ru::Case::Prepositional;
+    }
+    Some((4 + suffix, ru::year_words(digits.parse().ok()?, case)))
+}
+
+/// `г.`, `года`, `году` after a year with the case they call for.
+fn ru_year_suffix(text: &str) -> Option<(usize, ru::Case)> {
+    let trimmed = text.trim_start();
+    let spaces = text.len() - trimmed.len();
+    for (word, case) in [
+        ("г.", ru::Case::Genitive),
+        ("года", ru::Case::Genitive),
+        ("году", ru::Case::Prepositional),
+        ("годом", ru::Case::Instrumental),
+        ("годе", ru::Case::Prepositional),
+        ("год", ru::Case::Nominative),
+    ] {
+        if trimmed.starts_with(word) && (word.ends_with('.') || boundary(trimmed, word.len())) {
+            // The period of `г.` may also end the sentence.
+            let len = if word.ends_with('.') && ends_sentence(trimmed, word.len()) {
+                word.len() - 1
+            } else {
+                word.len()
+            };
+            return Some((spaces + len, case));
+        }
+    }
+    None
+}
+
+/// `1 500 руб.`, `$3.50`, `100 €`.
+pub(super) fn money(text: &str, at: usize, lang: Lang) -> Option<(usize, String)> {
+    let rest = &text[at..];
+    let prefix = CURRENCY_SYMBOLS
+        .iter()
+        .find(|(symbol, _)| rest.starts_with(symbol));
+    let (currency, number, len) = match prefix {
+        Some((symbol, currency)) => {
+            let offset = symbol.len() + usize::from(rest[symbol.len()..].starts_with(' '));
+            let number = parse_number(&rest[offset..], lang, &['.', ','])?;
+            let len = offset + number.len;
+            (*currency, number, len)
+        }
+        None => {
+            let number = parse_number(rest, lang, &['.', ','])?;
+            let after = &rest[number.len..];
+            let trimmed = after.trim_start_matches([' ', '\u{a0}']);
+            let (suffix, currency) = CURRENCY_SUFFIXES.iter().find(|(suffix, _)| {
+                trimmed.starts_with(suffix)
+                    && (!suffix.ends_with(char::is_alphanumeric) || boundary(trimmed, suffix.len()))
+            })?;
+            let mut len = number.len + after.len() - trimmed.len() + suffix.len();
+            if suffix.ends_with('.') && ends_sentence(rest, len) {
+                len -= 1;
+            }
+            (*currency, number, len)
+        }
+    };
+    let cents = match &number.fraction {
+        None => 0,
+        Some(fraction) if fraction.len() == 2 => fraction.parse().ok()?,
+        Some(_) => return None,
+    };
+    if !boundary(rest, len) && !rest[..len].ends_with('.') {
+        return None;
+    }
+    let spoken = match lang {
+        Lang::Russian => ru::money(currency, &number.digits, cents),
+        Lang::English => en::money(currency, &number.digits, cents),
+    };
+    Some((len, spoken))
+}
+
+/// `+7 (495) 123-45-67`, `8-800-555-35-35`.
+pub(super) fn phone(text: &str, at: usize, lang: Lang) -> Option<(usize, String)> {
+    let rest = &text[at..];
+    let plus = rest.starts_with('+');
+    let mut position = usize::from(plus);
+    let mut len = position;
+    let mut groups = Vec::new();
+    let mut punctuated = false;
+    loop {
+        let separators = rest[position..]
+            .chars()
+            .take(3)
+            .take_while(|c| matches!(c, ' ' | '-' | '(' | ')'))
+            .count();
+        if separators == 0 && !groups.is_empty() {
+            break;
+        }
+        let group = leading_digits(&rest[position + separators..]);
+        if group.is_empty() {
+            break;
+        }
+        punctuated |= rest[position..position + separators].contains(['-', '(', ')']);
+        groups.push(group);
+        position += separators + group.len();
+        len = position;
+    }
+    let total: usize = groups.iter().map(|group| group.len()).sum();
+    let valid = (10..=15).contains(&total)
+        && (plus || (punctuated && groups.len() >= 3))
+        && boundary(rest, len);
+    if !valid {
+        return None;
+    }
+    let spoken = match lang {
+        Lang::Russian => ru::phone(plus, &groups),
+        Lang::English => en::phone(plus, &groups),
+    };
+    Some((len, spoken))
+}
+
+/// Abbreviations of the language, `т. е.` matches `т.е.`.
+pub(super) fn abbreviation(text: &str, at: usize, lang: Lang) -> Option<(usize, String)> {
+    let (table, sentence_final) = match lang {
+        Lang::Russian => (ru::ABBREVIATIONS, ru::SENTENCE_FINAL),
+        Lang::English => (en::ABBREVIATIONS, en::SENTENCE_FINAL),
+    };
+    let rest = &text[at..];
+    table.iter().find_map(|(abbreviation, expansion)| {
+        let len = match_abbreviation(rest, abbreviation)?;
+        if abbreviation.ends_with(char::is_alphanumeric) && !boundary(rest, len) {
+            return None;
+        }
+        // The period is kept if it also ends the sentence.
+        let ends_text = rest[len..].trim().is_empty();
+        let keeps_period = abbreviation.ends_with('.')
+            && (ends_text || (sentence_final.contains(abbreviation) && ends_sentence(rest, len)));
+        match keeps_period {
+            true => Some((len, format!("{expansion}."))),
+            false => Some((len, (*expansion).to_owned())),
+        }
+    })
+}
+
+fn match_abbreviation(text: &str, abbreviation: &str) -> Option<usize> {
+    let mut len = 0;
+    let mut pattern = abbreviation.chars().peekable();
+    while let Some(expected) = pattern.next() {
+        let rest = &text[len..];
+        if expected == ' ' {
+            let trimmed = rest.trim_start();
+            if trimmed.len() == rest.len() {
+                return None;
+            }
+            len += rest.len() - trimmed.len();
+            continue;
+        }
+        if !rest.starts_with(expected) {
+            return None;
+        }
+        len += expected.len_utf8();
+        if expected == '.' && pattern.peek().is_some_and(|next| next.is_alphabetic()) {
+            len += text[len..].len() - text[len..].trim_start_matches(' ').len();
+        }
+    }
+    Some(len)
+}
+
+/// Whether the period before `end` ends a sentence: it is the last one
+/// or the next word is capitalized.
+fn ends_sentence(text: &str, end: usize) -> bool {
+    let rest = &text[end..];
+    let trimmed = rest.trim_start();
+    trimmed.is_empty() || (trimmed.len() < rest.len() && trimmed.starts_with(char::is_uppercase))
+}
+
+/// Whether the token ending at `end` is not followed by a letter or a digit.
+fn boundary(text: &str, end: usize) -> bool {
+    !text[end..].starts_with(char::is_alphanumeric)
+}
+
+fn leading_digits(text: &str) -> &str {
+    let end = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+fn leading_word(text: &str) -> &str {
+    let end = text
+        .find(|c: char| !c.is_alphabetic())
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::Normalizer;
+
+    fn ru(text: &str) -> String {
+        Normalizer::new(Lang::Russian).normalize(text)
+    }
+
+    fn en(text: &str) -> String {
+        Normalizer::new(Lang::English).normalize(text)
+    }
+
+    #[test]
+    fn date() {
+        assert_eq!(
+            ru("12.05.2024"),
+            "двенадцатое мая две тысячи двадцать четвёртого года"
+        );
+        assert_eq!(en("May 12, 2024"), "May twelfth, twenty twenty-four");
+        assert_eq!(ru("в 1980 г."), "в тысяча девятьсот восьмидесятом году.");
+    }
+
+    #[test]
+    fn money() {
+        assert_eq!(ru("1 500 руб."), "тысяча пятьсот рублей.");
+        assert_eq!(
+            ru("Итого 1 500 руб. к оплате"),
+            "Итого тысяча пятьсот рублей к оплате"
+        );
+        assert_eq!(ru("$3.50"), "три доллара пятьдесят центов");
+        assert_eq!(en("$1"), "one dollar");
+    }
+
+    #[test]
+    fn ordinal() {
+        assert_eq!(ru("21-я"), "двадцать первая");
+        assert_eq!(ru("до 2-го этажа"), "до второго этажа");
+        assert_eq!(en("22nd"), "twenty-second");
+        assert_eq!(en("22st"), "twenty-two st");
+    }
+
+    #[test]
+    fn phone() {
+        assert_eq!(
+            ru("+7 (495) 123-45-67"),
+            "плюс семь, четыреста девяносто пять, сто двадцать три, сорок пять, шестьдесят семь"
+        );
+    }
+
+    #[test]
+    fn long_numbers_are_read_by_digits() {
+        assert_eq!(
+            ru("999999999999999"),
+            "девятьсот девяносто девять триллионов девятьсот девяносто девять миллиардов \
+             девятьсот девяносто девять миллионов девятьсот девяносто девять тысяч \
+             девятьсот девяносто девять"
+        );
+        assert_eq!(
+            ru("1000000000000000 руб."),
+            "один ноль ноль ноль ноль ноль ноль ноль ноль ноль ноль ноль ноль ноль ноль ноль рублей."
+        );
+        assert_eq!(
+            ru("12345678901234567890123-й"),
+            "один два три четыре пять шесть семь восемь девять ноль один два три \
+             четыре пять шесть семь восемь девять ноль один два три"
+        );
+        assert_eq!(
+            Lang::English.cardinal(1_000_000_000_000_000),
+            "one zero zero zero zero zero zero zero zero zero zero zero zero zero zero zero"
+        );
+    }
+
+    #[test]
+    fn parse_number_groups() {
+        let number = parse_number("1 500,25 руб", Lang::Russian, &[',']).unwrap();
+        assert_eq!(number.digits, "1500");
+        assert_eq!(number.value, Some(1500));
+        assert_eq!(number.fraction.as_deref(), Some("25"));
+        assert_eq!(number.len, "1 500,25".len());
+        assert_eq!(
+            parse_number("1 50", Lang::Russian, &[]).unwrap().digits,
+            "1"
+        );
+    }
+}
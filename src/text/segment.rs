@@ -0,0 +1,611 @@
+// Copyright 2024 ООО Оптимумсити
+
+//    Licensed under the Apache License, Version 2.0 (the "License");
+//    you may not use this file except in compliance with the License.
+//    You may obtain a copy of the License at
+
+//        http://www.apache.org/licenses/LICENSE-2.0
+
+//    Unless required by applicable law or agreed to in writing, software
+//    distributed under the License is distributed on an "AS IS" BASIS,
+//    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//    See the License for the specific language governing permissions and
+//    limitations under the License.
+
+use crate::ssml::{escape_text, Document, Element, ElementKind, Node};
+
+/// Abbreviations followed by a period that does not end a sentence, without the final period.
+const ABBREVIATIONS: &[&str] = &[
+    "mr", "mrs", "ms", "dr", "prof", "st", "jr", "sr", "vs", "etc", "e.g", "i.e", "inc", "ltd",
+    "co", "no", "fig", "approx", "dept", "est", "т.е", "т.д", "т.п", "т.к", "т.н", "г", "гг", "ул",
+    "д", "кв", "им", "см", "стр", "руб", "коп", "тыс", "млн", "млрд", "проф", "акад", "др", "пр",
+    "напр", "доц", "тел", "обл", "р", "с", "пос", "ок", "рис", "табл", "мин", "сек", "ч",
+];
+
+const TERMINATORS: &[char] = &['.', '!', '?', '…'];
+const CLAUSE_SEPARATORS: &[char] = &[',', ';', ':', '—', '–'];
+const CLOSING: &[char] = &['"', '\'', '»', '”', '’', ')', ']'];
+
+/// Why a chunk ends where it does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Boundary {
+    Sentence,
+    Paragraph,
+    /// Clause separator of a sentence longer than the limit.
+    Clause,
+    /// Whitespace or any character of a clause longer than the limit.
+    Forced,
+    End,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// Plain text, or an SSML document for chunks of a document.
+    pub text: String,
+    pub boundary: Boundary,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SegmenterParams {
+    /// Longer sentences are split at clauses, then at whitespace.
+    pub max_chars: usize,
+    /// Shorter sentences are joined with the following one.
+    pub min_chars: usize,
+}
+
+impl Default for SegmenterParams {
+    fn default() -> Self {
+        Self {
+            max_chars: 300,
+            min_chars: 20,
+        }
+    }
+}
+
+/// Splits text into sentence and clause chunks to synthesize and stream one by one.
+#[derive(Debug, Clone)]
+pub struct Segmenter {
+    params: SegmenterParams,
+    abbreviations: Vec<String>,
+}
+
+impl Default for Segmenter {
+    fn default() -> Self {
+        Self::new(SegmenterParams::default())
+    }
+}
+
+impl Segmenter {
+    pub fn new(params: SegmenterParams) -> Self {
+        Self {
+            params,
+            abbreviations: ABBREVIATIONS.iter().map(|&a| a.to_owned()).collect(),
+        }
+    }
+
+    /// Adds abbreviations, with or without the final period, e.g. `approx.` or `пер`.
+    pub fn with_abbreviations<S: AsRef<str>>(
+        mut self,
+        abbreviations: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.abbreviations.extend(
+            abbreviations
+                .into_iter()
+                .map(|a| a.as_ref().trim_end_matches('.').to_lowercase()),
+        );
+        self
+    }
+
+    pub fn params(&self) -> &SegmenterParams {
+        &self.params
+    }
+
+    pub fn split(&self, text: &str) -> Vec<Chunk> {
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        for (end, boundary) in self.boundaries(text, &[]) {
+            let piece = text[start..end].trim();
+            if !piece.is_empty() {
+                chunks.push(Chunk {
+                    text: piece.to_owned(),
+                    boundary,
+                });
+            }
+            start = end;
+        }
+        chunks
+    }
+
+    /// Splits the document into SSML documents. Elements spanning a boundary are closed
+    /// at the end of a chunk and opened again in the next one. A `p` always ends a chunk,
+    /// an `s` ends a sentence.
+    pub fn split_document(&self, document: &Document) -> Vec<Chunk> {
+        let mut items = Vec::new();
+        flatten(&document.root, &mut items);
+        let mut text = String::new();
+        let mut forced = Vec::new();
+        let mut ranges = Vec::with_capacity(items.len());
+        for item in &items {
+            let start = text.len();
+            match item {
+                Item::Text(content) => text.push_str(content),
+                Item::Atomic(element) => text.push_str(&element.plain_text()),
+                Item::Close(element) => match element.kind {
+                    ElementKind::Paragraph => {
+                        text.push_str("\n\n");
+                        forced.push((text.len(), Boundary::Paragraph));
+                    }
+                    ElementKind::Sentence => {
+                        text.push(' ');
+                        forced.push((text.len(), Boundary::Sentence));
+                    }
+                    _ => {}
+                },
+                Item::Open(_) => {}
+            }
+            ranges.push(start..text.len());
+        }
+        let mut writer = ChunkWriter::new(&document.root);
+        let mut boundaries = self.boundaries(&text, &forced).into_iter().peekable();
+        for (item, range) in items.iter().zip(ranges) {
+            match item {
+                Item::Open(element) => writer.open(element),
+                Item::Close(_) => writer.close(),
+                Item::Atomic(element) => {
+                    writer.push(element.to_string(), !element.plain_text().trim().is_empty())
+                }
+                Item::Text(content) => {
+                    let mut start = range.start;
+                    while let Some(&(end, boundary)) = boundaries.peek() {
+                        if end > range.end {
+                            break;
+                        }
+                        boundaries.next();
+                        let piece = &content[start - range.start..end - range.start];
+                        writer.push(escape_text(piece), !piece.trim().is_empty());
+                        writer.cut(boundary);
+                        start = end;
+                    }
+                    let piece = &content[start - range.start..];
+                    writer.push(escape_text(piece), !piece.trim().is_empty());
+                    continue;
+                }
+            }
+            while let Some(&(end, boundary)) = boundaries.peek() {
+                if end > range.end {
+                    break;
+                }
+                boundaries.next();
+                writer.cut(boundary);
+            }
+        }
+        writer.finish()
+    }
+
+    /// Ends of the chunks as byte offsets, the last one is the end of the text.
+    fn boundaries(&self, text: &str, forced: &[(usize, Boundary)]) -> Vec<(usize, Boundary)> {
+        let mut sentences = self.sentences(text);
+        sentences.extend_from_slice(forced);
+        sentences.sort_by_key(|&(end, _)| end);
+        // Boundaries with only whitespace between them are joined at the later one.
+        sentences.dedup_by(|next, previous| {
+            if text[previous.0..next.0].trim().is_empty() {
+                previous.0 = next.0;
+                if next.1 == Boundary::Paragraph {
+                    previous.1 = Boundary::Paragraph;
+                }
+                true
+            } else {
+                false
+            }
+        });
+        let mut merged: Vec<(usize, Boundary)> = Vec::new();
+        let mut start = 0;
+        for (end, boundary) in sentences {
+            let length = text[start..end].trim().chars().count();
+            if length == 0 {
+                continue;
+            }
+            if length < self.params.min_chars && boundary == Boundary::Sentence && end < text.len()
+            {
+                continue;
+            }
+            merged.push((end, boundary));
+            start = end;
+        }
+        let mut boundaries = Vec::with_capacity(merged.len() + 1);
+        let mut start = 0;
+        for (end, boundary) in merged {
+            self.split_long(text, start, end, &mut boundaries);
+            boundaries.push((end, boundary));
+            start = end;
+        }
+        if start < text.len() {
+            self.split_long(text, start, text.len(), &mut boundaries);
+        }
+        if boundaries.last().is_some_and(|&(end, _)| end == text.len()) {
+            boundaries.pop();
+        }
+        boundaries.push((text.len(), Boundary::End));
+        boundaries
+    }
+
+    /// Sentence and paragraph ends as byte offsets after the terminating punctuation.
+    fn sentences(&self, text: &str) -> Vec<(usize, Boundary)> {
+        let chars: Vec<(usize, char)> = text.char_indices().collect();
+        let mut boundaries = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let (offset, c) = chars[i];
+            if c == '\n' {
+                let mut j = i + 1;
+                while j < chars.len() && chars[j].1.is_whitespace() && chars[j].1 != '\n' {
+                    j += 1;
+                }
+                if j < chars.len() && chars[j].1 == '\n' {
+                    boundaries.push((offset, Boundary::Paragraph));
+                    while j < chars.len() && chars[j].1.is_whitespace() {
+                        j += 1;
+                    }
+                    i = j;
+                    continue;
+                }
+            }
+            if !TERMINATORS.contains(&c) {
+                i += 1;
+                continue;
+            }
+            let mut j = i + 1;
+            while j < chars.len()
+                && (TERMINATORS.contains(&chars[j].1) || CLOSING.contains(&chars[j].1))
+            {
+                j += 1;
+            }
+            let end = chars.get(j).map_or(text.len(), |&(offset, _)| offset);
+            if j < chars.len() && !chars[j].1.is_whitespace() {
+                i = j;
+                continue;
+            }
+            let next = chars[j..]
+                .iter()
+                .map(|&(_, c)| c)
+                .find(|c| !c.is_whitespace());
+            let ends_sentence = match next {
+                None => true,
+                Some(next) if next.is_lowercase() => false,
+                Some(_) => c != '.' || !self.is_abbreviation(&text[..offset]),
+            };
+            if ends_sentence {
+                boundaries.push((end, Boundary::Sentence));
+            }
+            i = j;
+        }
+        boundaries
+    }
+
+    /// Checks the word before a period: known abbreviations and single letter initials.
+    fn is_abbreviation(&self, before: &str) -> bool {
+        let word = before
+            .rsplit(|c: char| c.is_whitespace() || c == '(' || c == '«' || c == '"')
+            .next()
+            .unwrap_or_default();
+        let mut letters = word.chars();
+        if let (Some(first), None) = (letters.next(), letters.next()) {
+            if first.is_uppercase() {
+                return true;
+            }
+        }
+        let word = word.to_lowercase();
+        self.abbreviations.contains(&word)
+    }
+
+    /// Adds clause or forced boundaries so that no piece of `start..end` exceeds the limit.
+    fn split_long(
+        &self,
+        text: &str,
+        mut start: usize,
+        end: usize,
+        boundaries: &mut Vec<(usize, Boundary)>,
+    ) {
+        let max = self.params.max_chars.max(1);
+        while text[start..end].trim().chars().count() > max {
+            let piece = &text[start..end];
+            let leading = piece.len() - piece.trim_start().len();
+            let limit = piece[leading..]
+                .char_indices()
+                .nth(max)
+                .map_or(piece.len(), |(offset, _)| leading + offset);
+            let window = &piece[..limit];
+            let clause = window
+                .char_indices()
+                .filter(|&(offset, c)| {
+                    CLAUSE_SEPARATORS.contains(&c)
+                        && !matches!(
+                            window[offset + c.len_utf8()..].chars().next(),
+                            Some(next) if !next.is_whitespace()
+                        )
+                })
+                .map(|(offset, c)| offset + c.len_utf8())
+                .next_back();
+            let (cut, boundary) = match clause {
+                Some(cut) if cut > leading => (cut, Boundary::Clause),
+                _ => match window.rfind(char::is_whitespace) {
+                    Some(cut) if cut > leading => (cut, Boundary::Forced),
+                    _ => (limit, Boundary::Forced),
+                },
+            };
+            boundaries.push((start + cut, boundary));
+            start += cut;
+        }
+    }
+}
+
+enum Item<'a> {
+    Open(&'a Element),
+    Close(&'a Element),
+    Text(&'a str),
+    /// Elements which are never split: break, mark, sub, say-as, phoneme, audio.
+    Atomic(&'a Element),
+}
+
+fn flatten<'a>(element: &'a Element, items: &mut Vec<Item<'a>>) {
+    for child in &element.children {
+        match child {
+            Node::Text { text, .. } => items.push(Item::Text(text)),
+            Node::Element(child) => match child.kind {
+                ElementKind::Break
+                | ElementKind::Mark
+                | ElementKind::Sub
+                | ElementKind::SayAs
+                | ElementKind::Phoneme
+                | ElementKind::Audio
+                | ElementKind::Desc
+                | ElementKind::Lexicon
+                | ElementKind::Meta
+                | ElementKind::Metadata => items.push(Item::Atomic(child)),
+                _ => {
+                    items.push(Item::Open(child));
+                    flatten(child, items);
+                    items.push(Item::Close(child));
+                }
+            },
+        }
+    }
+}
+
+/// Builds SSML chunks keeping the elements open at a cut.
+struct ChunkWriter<'a> {
+    root: &'a Element,
+    open: Vec<&'a Element>,
+    current: String,
+    speakable: bool,
+    chunks: Vec<Chunk>,
+}
+
+impl<'a> ChunkWriter<'a> {
+    fn new(root: &'a Element) -> Self {
+        Self {
+            root,
+            open: Vec::new(),
+            current: String::new(),
+            speakable: false,
+            chunks: Vec::new(),
+        }
+    }
+
+    fn open(&mut self, element: &'a Element) {
+        self.current.push_str(&element.start_tag());
+        self.open.push(element);
+    }
+
+    fn close(&mut self) {
+        if let Some(element) = self.open.pop() {
+            self.current.push_str(&element.end_tag());
+        }
+    }
+
+    fn push(&mut self, content: String, speakable: bool) {
+        self.current.push_str(&content);
+        self.speakable |= speakable;
+    }
+
+    /// Ends the current chunk unless there is nothing to speak in it yet.
+    fn cut(&mut self, boundary: Boundary) {
+        if !self.speakable {
+            return;
+        }
+        let mut text = self.root.start_tag();
+        text.push_str(&self.current);
+        for element in self.open.iter().rev() {
+            text.push_str(&element.end_tag());
+        }
+        text.push_str(&self.root.end_tag());
+        self.chunks.push(Chunk { text, boundary });
+        self.current = self
+            .open
+            .iter()
+            .map(|element| element.start_tag())
+            .collect();
+        self.speakable = false;
+    }
+
+    fn finish(mut self) -> Vec<Chunk> {
+        self.cut(Boundary::End);
+        if let Some(last) = self.chunks.last_mut() {
+            last.boundary = Boundary::End;
+        }
+        self.chunks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(chunks: &[Chunk]) -> Vec<&str> {
+        chunks.iter().map(|chunk| chunk.text.as_str()).collect()
+    }
+
+    fn segmenter(max_chars: usize, min_chars: usize) -> Segmenter {
+        Segmenter::new(SegmenterParams {
+            max_chars,
+            min_chars,
+        })
+    }
+
+    #[test]
+    fn abbreviations_and_initials() {
+        let chunks = segmenter(300, 0).split(
+            "Dr. Smith met J. R. Brown at 5 p.m. today. They talked, e.g. about work! Done?",
+        );
+        assert_eq!(
+            texts(&chunks),
+            [
+                "Dr. Smith met J. R. Brown at 5 p.m. today.",
+                "They talked, e.g. about work!",
+                "Done?"
+            ]
+        );
+        assert_eq!(chunks[0].boundary, Boundary::Sentence);
+        assert_eq!(chunks[2].boundary, Boundary::End);
+    }
+
+    #[test]
+    fn cyrillic_abbreviations() {
+        let chunks = segmenter(300, 0)
+            .split("Он живёт: ул. Ленина, д. 5. Проф. Иванов, т.е. А. С. Иванов, здесь. Всё.");
+        assert_eq!(
+            texts(&chunks),
+            [
+                "Он живёт: ул. Ленина, д. 5.",
+                "Проф. Иванов, т.е. А. С. Иванов, здесь.",
+                "Всё."
+            ]
+        );
+    }
+
+    #[test]
+    fn added_abbreviations() {
+        let text = "Идите на пер. Садовый. Там.";
+        assert_eq!(segmenter(300, 0).split(text).len(), 3);
+        let chunks = segmenter(300, 0).with_abbreviations(["пер."]).split(text);
+        assert_eq!(texts(&chunks), ["Идите на пер. Садовый.", "Там."]);
+    }
+
+    #[test]
+    fn lowercase_and_closing_quotes() {
+        let chunks = segmenter(300, 0).split("He said «wait…» and left. «Why?» she asked. Ok.");
+        assert_eq!(
+            texts(&chunks),
+            ["He said «wait…» and left.", "«Why?» she asked.", "Ok."]
+        );
+    }
+
+    #[test]
+    fn paragraphs() {
+        let chunks = segmenter(300, 20).split("Title\n\nFirst line. Second line\n  \nEnd");
+        assert_eq!(texts(&chunks), ["Title", "First line. Second line", "End"]);
+        assert_eq!(chunks[0].boundary, Boundary::Paragraph);
+        assert_eq!(chunks[1].boundary, Boundary::Paragraph);
+    }
+
+    #[test]
+    fn short_sentences_are_merged() {
+        let chunks = segmenter(300, 20).split("Yes. No. Maybe so, who knows really. Ok.");
+        assert_eq!(
+            texts(&chunks),
+            ["Yes. No. Maybe so, who knows really.", "Ok."]
+        );
+        assert_eq!(chunks[0].boundary, Boundary::Sentence);
+    }
+
+    #[test]
+    fn long_sentences_split_at_clauses() {
+        let text = "First we go to the market, then we buy some bread, cheese and milk, \
+                    after that we walk home slowly; finally we cook dinner.";
+        let chunks = segmenter(60, 0).split(text);
+        assert_eq!(
+            texts(&chunks),
+            [
+                "First we go to the market, then we buy some bread,",
+                "cheese and milk, after that we walk home slowly;",
+                "finally we cook dinner."
+            ]
+        );
+        assert_eq!(chunks[0].boundary, Boundary::Clause);
+        assert_eq!(chunks[1].boundary, Boundary::Clause);
+        assert!(chunks.iter().all(|chunk| chunk.text.chars().count() <= 60));
+    }
+
+    #[test]
+    fn long_clauses_are_forced() {
+        let chunks = segmenter(10, 0).split("aaaa bbbb cccc dddddddddddddd");
+        assert_eq!(texts(&chunks), ["aaaa bbbb", "cccc", "dddddddddd", "dddd"]);
+        assert_eq!(chunks[0].boundary, Boundary::Forced);
+        assert_eq!(chunks[2].boundary, Boundary::Forced);
+        assert_eq!(chunks[3].boundary, Boundary::End);
+    }
+
+    #[test]
+    fn documents_reopen_elements() {
+        let source = "<speak version=\"1.0\" xml:lang=\"en-US\">\
+                      <p>First paragraph here. <prosody rate=\"slow\">Slow sentence one. \
+                      Slow <mark name=\"m\"/>sentence two.</prosody></p>\
+                      <p>Second &amp; last.</p></speak>";
+        let chunks = segmenter(300, 0).split_document(&Document::parse(source).unwrap());
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|chunk| chunk.boundary)
+                .collect::<Vec<_>>(),
+            [
+                Boundary::Sentence,
+                Boundary::Sentence,
+                Boundary::Paragraph,
+                Boundary::End
+            ]
+        );
+        let plain: Vec<String> = chunks
+            .iter()
+            .map(|chunk| {
+                let document = Document::parse(&chunk.text).unwrap();
+                assert_eq!(document.root.kind, ElementKind::Speak);
+                document
+                    .plain_text()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+        assert_eq!(
+            plain,
+            [
+                "First paragraph here.",
+                "Slow sentence one.",
+                "Slow sentence two.",
+                "Second & last."
+            ]
+        );
+        assert!(chunks[0].text.ends_with("here.</p></speak>"));
+        assert!(chunks[1]
+            .text
+            .ends_with("<prosody rate=\"slow\">Slow sentence one.</prosody></p></speak>"));
+        assert!(chunks[2]
+            .text
+            .starts_with("<speak version=\"1.0\" xml:lang=\"en-US\"><p><prosody rate=\"slow\">"));
+        assert!(chunks[2].text.contains("<mark name=\"m\"/>"));
+        assert!(chunks[3].text.contains("Second &amp; last."));
+    }
+
+    #[test]
+    fn document_sentences_end_chunks() {
+        let source = "<speak><s>one two</s><s>three</s> four</speak>";
+        let chunks = segmenter(300, 0).split_document(&Document::parse(source).unwrap());
+        assert_eq!(chunks.len(), 3);
+        for chunk in &chunks {
+            Document::parse(&chunk.text).unwrap();
+        }
+        assert_eq!(chunks[0].boundary, Boundary::Sentence);
+    }
+}